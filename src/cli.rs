@@ -1,6 +1,14 @@
-use crate::types::{EditField, Status, TaskCommand};
-use crate::utils::parse_due_date;
-use chrono::{DateTime, Utc};
+use crate::config::{Config, ListStatus};
+use crate::types::{
+    ConflictStrategy, DataFormat, DueFilter, DuePrecision, EditField, ExportOptions, ImportOptions,
+    OutputFormat, Priority, Recurrence, Status, TagFilter, TaskCommand, TaskError, TaskField,
+    TaskSort,
+};
+use crate::utils::{
+    end_of_local_day, parse_due, parse_due_date_at, parse_duration, split_name_and_tags,
+    validate_tag_name,
+};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, Utc};
 use clap::{Arg, Command};
 use std::str::FromStr;
 
@@ -23,10 +31,15 @@ _tarea() {
         --standby)
             filter="--filter=done,pending"
             ;;
-        --show|--edit|-e|--delete)
+        --show|--edit|-e|--delete|--history|--move)
             # No filter, allow matching any task
             filter="--filter=done,pending,standby"
             ;;
+        --profile|--to|--rename-profile|--delete-profile)
+            COMPREPLY=( $(compgen -W "$(tarea --profiles 2>/dev/null | cut -c3-)" \
+                              -- "${COMP_WORDS[COMP_CWORD]}") )
+            return
+            ;;
         *)
             _tarea_clap "$@"
             return
@@ -50,7 +63,7 @@ function __tarea_status_complete
                 set filter "--filter=done,standby"
             case --standby
                 set filter "--filter=done,pending"
-            case --show --edit --delete -e
+            case --show --edit --delete --history --move -e
                 set filter "--filter=done,pending,standby"
         end
     end
@@ -67,6 +80,10 @@ complete -r -f -c tarea -l standby -a '(__tarea_status_complete)' -d 'Mark tasks
 complete -r -f -c tarea -l show -a '(__tarea_status_complete)' -d 'Show specific task by ID'
 complete -r -f -c tarea -l edit -a '(__tarea_status_complete)' -d 'Edit task'
 complete -r -f -c tarea -l delete -a '(__tarea_status_complete)' -d 'Delete a task by ID'
+complete -r -f -c tarea -l history -a '(__tarea_status_complete)' -d 'Show the history of a task'
+complete -r -f -c tarea -l move -a '(__tarea_status_complete)' -d 'Move tasks to another profile'
+complete -r -f -c tarea -l profile -a '(tarea --profiles 2>/dev/null | cut -c3-)' -d 'Work in profile'
+complete -r -f -c tarea -l to -a '(tarea --profiles 2>/dev/null | cut -c3-)' -d 'Profile to move tasks to'
 "#;
 
 pub fn build_cli() -> Command {
@@ -79,6 +96,12 @@ pub fn build_cli() -> Command {
                 .help("Show all tasks regardless of status")
                 .action(clap::ArgAction::SetTrue),
         )
+        .arg(
+            Arg::new("archived")
+                .long("archived")
+                .help("List deleted (archived) tasks")
+                .action(clap::ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("completions")
                 .long("completions")
@@ -89,9 +112,9 @@ pub fn build_cli() -> Command {
         .arg(
             Arg::new("delete")
                 .long("delete")
-                .help("Delete a task by ID or list index")
+                .help("Archive tasks by ID or list index (see --restore, --purge)")
                 .value_name("TASK")
-                .num_args(1),
+                .num_args(1..),
         )
         .arg(
             Arg::new("delete-database")
@@ -110,17 +133,118 @@ pub fn build_cli() -> Command {
         .arg(
             Arg::new("done")
                 .long("done")
-                .help("Mark task as done (if TASK_ID given) or list done tasks")
-                .num_args(0..=1)
+                .help(
+                    "Mark tasks as done (if TASK_IDs given) or list done tasks; \
+                     accepts lists (1,3), ranges (5-8) and '-' for stdin",
+                )
+                .num_args(0..)
                 .value_name("TASK_ID"),
         )
         .arg(
             Arg::new("due-date")
                 .long("due")
-                .help("Set due date (today, tomorrow, 2h, 60m or YYYY-MM-DD [HH:MM[:SS]])")
+                .help(
+                    "Set due date (today, friday, next week, tonight, 2h, 3d, 5bd, in 2 weeks \
+                     or YYYY-MM-DD [HH:MM[:SS]])",
+                )
                 .num_args(1..)
                 .value_name("DATE"),
         )
+        .arg(
+            Arg::new("due-after")
+                .long("due-after")
+                .help("Only list tasks due after DATE (e.g. today, friday, 2025-08-01)")
+                .num_args(1)
+                .value_name("DATE")
+                .conflicts_with_all(["due-today", "due-this-week"]),
+        )
+        .arg(
+            Arg::new("due-before")
+                .long("due-before")
+                .help("Only list tasks due by DATE (e.g. friday, 'next week', 3d)")
+                .num_args(1)
+                .value_name("DATE")
+                .conflicts_with_all(["due-today", "due-this-week", "overdue"]),
+        )
+        .arg(
+            Arg::new("due-this-week")
+                .long("due-this-week")
+                .help("Only list tasks due this week, Monday to Sunday")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with_all(["due-today", "overdue"]),
+        )
+        .arg(
+            Arg::new("due-today")
+                .long("due-today")
+                .help("Only list tasks due today")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with("overdue"),
+        )
+        .arg(
+            Arg::new("export")
+                .long("export")
+                .help("Write every task to stdout in FORMAT (json, csv, tsv, markdown, todotxt, taskwarrior, ical, org)")
+                .value_name("FORMAT")
+                .value_parser(DataFormat::from_str),
+        )
+        .arg(
+            Arg::new("columns")
+                .long("columns")
+                .help(
+                    "With --export csv/tsv, the columns to write \
+                     (id, name, description, status, created, due, completed, tags)",
+                )
+                .value_name("FIELD[,FIELD...]")
+                .value_parser(TaskField::parse_list)
+                .requires("export"),
+        )
+        .arg(
+            Arg::new("group")
+                .long("group")
+                .help("With --export markdown, list tasks under a heading per status")
+                .action(clap::ArgAction::SetTrue)
+                .requires("export"),
+        )
+        .arg(
+            Arg::new("import")
+                .long("import")
+                .help("Import tasks from FILE ('-' for stdin)")
+                .value_name("FILE"),
+        )
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .help("With --import, the file format (default: from the extension, else json)")
+                .value_name("FORMAT")
+                .value_parser(DataFormat::from_str)
+                .requires("import"),
+        )
+        .arg(
+            Arg::new("on-conflict")
+                .long("on-conflict")
+                .help(
+                    "With --import, what to do with tasks whose id exists: skip, overwrite, rename",
+                )
+                .value_name("STRATEGY")
+                .value_parser(ConflictStrategy::from_str)
+                .requires("import"),
+        )
+        .arg(
+            Arg::new("map")
+                .long("map")
+                .help("With --import csv/tsv, read column HEADER as FIELD (repeatable)")
+                .value_name("HEADER=FIELD")
+                .value_parser(parse_header_mapping)
+                .action(clap::ArgAction::Append)
+                .requires("import"),
+        )
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .help("With --import, only show what would be imported")
+                .action(clap::ArgAction::SetTrue)
+                .requires("import"),
+        )
         .arg(
             Arg::new("name")
                 .long("name")
@@ -136,6 +260,23 @@ pub fn build_cli() -> Command {
                 .help("Edit task name, description, or due date")
                 .value_name("EDIT"),
         )
+        .arg(
+            Arg::new("every")
+                .long("every")
+                .help(
+                    "Repeat the task when it is done (day, weekday, week, 2w, month, \
+                     '1st monday', 'last friday', ... [after done]; 'never' with --edit)",
+                )
+                .num_args(1)
+                .value_name("RULE"),
+        )
+        .arg(
+            Arg::new("priority")
+                .long("priority")
+                .help("Set the task priority, a letter from A (most urgent) to Z ('none' with --edit)")
+                .num_args(1)
+                .value_name("LETTER"),
+        )
         .arg(
             Arg::new("filter")
                 .long("filter")
@@ -143,27 +284,194 @@ pub fn build_cli() -> Command {
                 .value_name("STATUS[,STATUS...]")
                 .help("Only show tasks with any of the given statuses (used with --ids)"),
         )
+        .arg(
+            Arg::new("no-tag")
+                .long("no-tag")
+                .help("Hide tasks carrying TAG (repeatable)")
+                .value_name("TAG")
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            Arg::new("no-due")
+                .long("no-due")
+                .help("Only list tasks without a due date")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with_all([
+                    "due-after",
+                    "due-before",
+                    "due-this-week",
+                    "due-today",
+                    "overdue",
+                ]),
+        )
+        .arg(
+            Arg::new("older-than")
+                .long("older-than")
+                .help(
+                    "With --purge, only purge tasks archived at least DURATION ago (e.g. 30d, 2w)",
+                )
+                .value_name("DURATION")
+                .requires("purge"),
+        )
+        .arg(
+            Arg::new("overdue")
+                .long("overdue")
+                .help("Only list open tasks whose due date has passed")
+                .action(clap::ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("pending")
                 .long("pending")
-                .help("Mark task as pending (if TASK_ID given) or list pending tasks")
-                .num_args(0..=1)
+                .help(
+                    "Mark tasks as pending (if TASK_IDs given) or list pending tasks; \
+                     accepts lists (1,3), ranges (5-8) and '-' for stdin",
+                )
+                .num_args(0..)
                 .value_name("TASK_ID"),
         )
+        .arg(
+            Arg::new("profile")
+                .long("profile")
+                .help("Work in profile NAME instead of $TAREA_PROFILE or the default one")
+                .value_name("NAME"),
+        )
+        .arg(
+            Arg::new("profiles")
+                .long("profiles")
+                .help("List profiles, marking the current one")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("create-profile")
+                .long("create-profile")
+                .help("Create a profile with its own database")
+                .value_name("NAME"),
+        )
+        .arg(
+            Arg::new("rename-profile")
+                .long("rename-profile")
+                .help("Rename profile OLD to NEW")
+                .num_args(2)
+                .value_names(["OLD", "NEW"]),
+        )
+        .arg(
+            Arg::new("delete-profile")
+                .long("delete-profile")
+                .help("Delete a profile and all of its tasks")
+                .value_name("NAME"),
+        )
+        .arg(
+            Arg::new("move")
+                .long("move")
+                .help("Move tasks by ID or list index to the profile given with --to")
+                .value_name("TASK")
+                .num_args(1..)
+                .requires("to"),
+        )
+        .arg(
+            Arg::new("to")
+                .long("to")
+                .help("With --move, the profile to move tasks to")
+                .value_name("PROFILE")
+                .requires("move"),
+        )
+        .arg(
+            Arg::new("purge")
+                .long("purge")
+                .help("Permanently delete archived tasks")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("redo")
+                .long("redo")
+                .help("Re-apply the last change reverted with --undo")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("restore")
+                .long("restore")
+                .help("Restore an archived task by ID or --archived list index")
+                .value_name("TASK")
+                .num_args(1),
+        )
         .arg(
             Arg::new("show")
                 .long("show")
                 .help("Show specific task by ID")
                 .value_name("TASK_ID"),
         )
+        .arg(
+            Arg::new("sort")
+                .long("sort")
+                .help(
+                    "Sort listings by KEY[,KEY...]: created, due, name, status, urgency; \
+                     prefix a key with '-' to reverse it",
+                )
+                .value_name("KEYS")
+                .value_parser(TaskSort::from_str)
+                .allow_hyphen_values(true),
+        )
         .arg(
             Arg::new("standby")
                 .long("standby")
-                .help("Mark task as standby (if TASK_ID given) or list standby tasks")
-                .num_args(0..=1)
+                .help(
+                    "Mark tasks as standby (if TASK_IDs given) or list standby tasks; \
+                     accepts lists (1,3), ranges (5-8) and '-' for stdin",
+                )
+                .num_args(0..)
                 .value_name("TASK_ID"),
         )
-        .arg(Arg::new("task").help("Task name to add").num_args(0..))
+        .arg(
+            Arg::new("tag")
+                .long("tag")
+                .help("Only show tasks carrying TAG (repeatable)")
+                .value_name("TAG")
+                .action(clap::ArgAction::Append),
+        )
+        .arg(
+            Arg::new("task")
+                .help("Task name to add; words starting with '+' become tags")
+                .num_args(0..),
+        )
+        .arg(
+            Arg::new("history")
+                .long("history")
+                .help("Show every recorded change of a task")
+                .value_name("TASK")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .help("Print tasks as JSON (list, --show, --name, --ids)")
+                .conflicts_with("jsonl")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("jsonl")
+                .long("jsonl")
+                .help("Print tasks as JSON Lines, one object per line")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("log")
+                .long("log")
+                .help("Show recorded changes across all tasks, newest first")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("undo")
+                .long("undo")
+                .help("Revert the last add, edit, status change, delete or restore")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("yes")
+                .short('y')
+                .long("yes")
+                .help("Skip confirmation prompts")
+                .action(clap::ArgAction::SetTrue),
+        )
         .arg(
             Arg::new("ids")
                 .short('i')
@@ -180,7 +488,7 @@ pub fn build_cli() -> Command {
         )
 }
 
-fn status_flag(matches: &clap::ArgMatches) -> Option<(Status, Option<String>)> {
+fn status_flag(matches: &clap::ArgMatches) -> Option<(Status, Vec<String>)> {
     [
         ("done", Status::Done),
         ("pending", Status::Pending),
@@ -188,17 +496,64 @@ fn status_flag(matches: &clap::ArgMatches) -> Option<(Status, Option<String>)> {
     ]
     .iter()
     .find_map(|(flag, st)| {
-        matches
-            .contains_id(flag)
-            .then(|| (st.clone(), matches.get_one::<String>(flag).cloned()))
+        matches.contains_id(flag).then(|| {
+            let ids = matches
+                .get_many::<String>(flag)
+                .map(|ids| ids.cloned().collect())
+                .unwrap_or_default();
+            (st.clone(), ids)
+        })
     })
 }
 
-pub fn parse_command() -> TaskCommand {
-    let matches = build_cli().get_matches();
+/// The profile named with `--profile`, if any.
+pub fn get_profile(matches: &clap::ArgMatches) -> Option<&str> {
+    matches.get_one::<String>("profile").map(String::as_str)
+}
+
+pub fn parse_command(matches: &clap::ArgMatches, config: &Config) -> TaskCommand {
+    let assume_yes = matches.get_flag("yes") || !config.confirm;
 
+    // Deleting the whole database asks even when prompts are turned off
     if matches.get_flag("delete-database") {
-        return TaskCommand::DeleteDatabase;
+        return TaskCommand::DeleteDatabase {
+            assume_yes: matches.get_flag("yes"),
+        };
+    }
+
+    if matches.get_flag("profiles") {
+        return TaskCommand::Profiles;
+    }
+
+    if let Some(name) = matches.get_one::<String>("create-profile") {
+        return TaskCommand::CreateProfile { name: name.clone() };
+    }
+
+    if let Some(mut names) = matches.get_many::<String>("rename-profile") {
+        let (Some(from), Some(to)) = (names.next(), names.next()) else {
+            unreachable!("clap requires two values");
+        };
+        return TaskCommand::RenameProfile {
+            from: from.clone(),
+            to: to.clone(),
+        };
+    }
+
+    // Like the whole database, a profile's tasks are only deleted on request
+    if let Some(name) = matches.get_one::<String>("delete-profile") {
+        return TaskCommand::DeleteProfile {
+            name: name.clone(),
+            assume_yes: matches.get_flag("yes"),
+        };
+    }
+
+    if let Some(ids) = matches.get_many::<String>("move") {
+        let profile = matches.get_one::<String>("to").cloned().unwrap_or_default();
+        return TaskCommand::Move {
+            ids_or_indices: ids.cloned().collect(),
+            profile,
+            assume_yes,
+        };
     }
 
     if matches.get_flag("ids") && !matches.contains_id("task") {
@@ -216,12 +571,16 @@ pub fn parse_command() -> TaskCommand {
         return TaskCommand::Ids {
             short_only: short,
             filter,
+            tags: get_tag_filter(matches),
+            due: get_due_filter(matches),
+            sort: get_sort(matches, config),
+            output: get_output_format(matches),
         };
     }
 
     if matches.contains_id("name") && !matches.contains_id("task") {
         let id_opt = matches.get_one::<String>("name").cloned();
-        let status = status_flag(&matches).map(|(s, _)| s);
+        let status = status_flag(matches).map(|(s, _)| s);
 
         if let Some(id) = id_opt {
             return TaskCommand::ShowName {
@@ -230,38 +589,132 @@ pub fn parse_command() -> TaskCommand {
             };
         }
 
+        let (status, show_all) = match status {
+            Some(status) => (Some(status), matches.get_flag("all")),
+            None => default_listing(matches, config),
+        };
         return TaskCommand::ListNames {
-            show_all: matches.get_flag("all"),
+            show_all,
             status,
+            tags: get_tag_filter(matches),
+            due: get_due_filter(matches),
+            sort: get_sort(matches, config),
+            output: get_output_format(matches),
         };
     }
 
-    if let Some((status, id_opt)) = status_flag(&matches) {
-        return match id_opt {
-            Some(id) => TaskCommand::UpdateStatus { id, status },
-            None => TaskCommand::List {
+    if let Some((status, ids)) = status_flag(matches) {
+        if ids.is_empty() {
+            return TaskCommand::List {
                 status: Some(status),
                 show_all: matches.get_flag("all"),
                 show_descriptions: matches.contains_id("description"),
-            },
+                tags: get_tag_filter(matches),
+                due: get_due_filter(matches),
+                sort: get_sort(matches, config),
+                output: get_output_format(matches),
+            };
+        }
+        return TaskCommand::UpdateStatus {
+            ids,
+            status,
+            assume_yes,
         };
     }
 
     if let Some(id_val) = matches.get_one::<String>("edit") {
-        return parse_edit_command(&matches, id_val);
+        return parse_edit_command(matches, id_val);
     }
 
     if let Some(task_id) = matches.get_one::<String>("show") {
         return TaskCommand::Show {
             id: task_id.clone(),
+            output: get_output_format(matches),
         };
     }
 
-    if let Some(task_id) = matches.get_one::<String>("delete") {
-        let status = status_flag(&matches).map(|(s, _)| s);
+    if let Some(ids) = matches.get_many::<String>("delete") {
+        let status = status_flag(matches).map(|(s, _)| s);
         return TaskCommand::Delete {
-            id_or_index: task_id.clone(),
+            ids_or_indices: ids.cloned().collect(),
             status,
+            assume_yes,
+        };
+    }
+
+    if let Some(task_id) = matches.get_one::<String>("history") {
+        return TaskCommand::History {
+            id_or_index: task_id.clone(),
+        };
+    }
+
+    if let Some(format) = matches.get_one::<DataFormat>("export") {
+        return TaskCommand::Export {
+            format: *format,
+            options: ExportOptions {
+                columns: matches
+                    .get_one::<Vec<TaskField>>("columns")
+                    .cloned()
+                    .unwrap_or_default(),
+                group_by_status: matches.get_flag("group"),
+            },
+        };
+    }
+
+    if let Some(path) = matches.get_one::<String>("import") {
+        return TaskCommand::Import {
+            path: path.clone(),
+            format: matches.get_one::<DataFormat>("format").copied(),
+            on_conflict: matches
+                .get_one::<ConflictStrategy>("on-conflict")
+                .copied()
+                .unwrap_or_default(),
+            dry_run: matches.get_flag("dry-run"),
+            options: ImportOptions {
+                mapping: matches
+                    .get_many::<(String, TaskField)>("map")
+                    .map(|pairs| pairs.cloned().collect())
+                    .unwrap_or_default(),
+            },
+        };
+    }
+
+    if matches.get_flag("log") {
+        return TaskCommand::Log;
+    }
+
+    if matches.get_flag("undo") {
+        return TaskCommand::Undo;
+    }
+
+    if matches.get_flag("redo") {
+        return TaskCommand::Redo;
+    }
+
+    if matches.get_flag("archived") {
+        return TaskCommand::Archived;
+    }
+
+    if let Some(task_id) = matches.get_one::<String>("restore") {
+        return TaskCommand::Restore {
+            id_or_index: task_id.clone(),
+        };
+    }
+
+    if matches.get_flag("purge") {
+        let older_than =
+            matches
+                .get_one::<String>("older-than")
+                .map(|raw| match parse_duration(raw) {
+                    Ok(d) => d,
+                    Err(e) => {
+                        eprintln!("{e}");
+                        std::process::exit(1);
+                    }
+                });
+        return TaskCommand::Purge {
+            older_than,
+            assume_yes,
         };
     }
 
@@ -273,30 +726,36 @@ pub fn parse_command() -> TaskCommand {
         };
     }
 
-    if let Some(name) = get_task_name(&matches) {
-        return parse_add_command(&matches, name);
+    if let Some(name) = get_task_name(matches) {
+        return parse_add_command(matches, name);
     }
 
-    let show_descriptions = get_show_descriptions(&matches);
-    let show_all = matches.get_flag("all");
+    let show_descriptions = get_show_descriptions(matches);
+    let (status, show_all) = default_listing(matches, config);
 
     TaskCommand::List {
-        status: None,
+        status,
         show_all,
         show_descriptions,
+        tags: get_tag_filter(matches),
+        due: get_due_filter(matches),
+        sort: get_sort(matches, config),
+        output: get_output_format(matches),
     }
 }
 
 fn parse_edit_command(matches: &clap::ArgMatches, id_val: &str) -> TaskCommand {
     let has_due = matches.contains_id("due-date");
     let has_desc = matches.contains_id("description");
+    let has_every = matches.contains_id("every");
+    let has_priority = matches.contains_id("priority");
     let explicit_name = matches.contains_id("name")
         || matches
             .get_many::<String>("task")
             .map(|vals| !vals.collect::<Vec<_>>().is_empty())
             .unwrap_or(false);
 
-    let should_open_editor = !has_due && !has_desc && !explicit_name;
+    let should_open_editor = !has_due && !has_desc && !has_every && !has_priority && !explicit_name;
 
     if should_open_editor {
         return TaskCommand::EditWithEditor {
@@ -310,7 +769,7 @@ fn parse_edit_command(matches: &clap::ArgMatches, id_val: &str) -> TaskCommand {
             .collect::<Vec<_>>()
             .join(" ");
 
-        let new_due = match parse_due_date(&raw) {
+        let (new_due, precision) = match parse_due(&raw) {
             Ok(d) => d,
             Err(e) => {
                 eprintln!("{e}");
@@ -319,7 +778,24 @@ fn parse_edit_command(matches: &clap::ArgMatches, id_val: &str) -> TaskCommand {
         };
         return TaskCommand::Edit {
             id_or_index: id_val.to_string(),
-            field: EditField::DueDate(new_due),
+            field: EditField::DueDate(new_due, precision),
+        };
+    }
+
+    if has_every {
+        let rule = get_every(matches).filter(|rule| rule != "never");
+        let recurrence = rule.map(|rule| parse_recurrence(&rule));
+        return TaskCommand::Edit {
+            id_or_index: id_val.to_string(),
+            field: EditField::Recurrence(recurrence),
+        };
+    }
+
+    if let Some(letter) = matches.get_one::<String>("priority") {
+        let priority = (letter != "none").then(|| parse_priority(letter));
+        return TaskCommand::Edit {
+            id_or_index: id_val.to_string(),
+            field: EditField::Priority(priority),
         };
     }
 
@@ -380,14 +856,149 @@ fn get_edit_name(matches: &clap::ArgMatches) -> String {
 fn parse_add_command(matches: &clap::ArgMatches, name: String) -> TaskCommand {
     let description = get_description(matches);
     let due_date = get_due_date(matches);
+    let recurrence = get_every(matches).map(|rule| parse_recurrence(&rule));
+    let priority = matches
+        .get_one::<String>("priority")
+        .map(|letter| parse_priority(letter));
+    let (name, mut tags) = split_name_and_tags(&name);
+    tags.sort();
+    tags.dedup();
 
     TaskCommand::Add {
         name,
         description,
         due_date,
+        tags,
+        recurrence,
+        priority,
+    }
+}
+
+fn get_every(matches: &clap::ArgMatches) -> Option<String> {
+    matches.get_one::<String>("every").cloned()
+}
+
+fn parse_recurrence(rule: &str) -> Recurrence {
+    rule.parse().unwrap_or_else(|e| {
+        eprintln!("{}", TaskError::InvalidInput(e));
+        std::process::exit(1);
+    })
+}
+
+fn parse_priority(letter: &str) -> Priority {
+    letter.parse().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    })
+}
+
+fn parse_header_mapping(s: &str) -> Result<(String, TaskField), String> {
+    let (header, field) = s
+        .rsplit_once('=')
+        .ok_or_else(|| format!("Expected HEADER=FIELD, got '{}'", s))?;
+    Ok((header.trim().to_string(), field.parse()?))
+}
+
+fn get_output_format(matches: &clap::ArgMatches) -> OutputFormat {
+    if matches.get_flag("json") {
+        OutputFormat::Json
+    } else if matches.get_flag("jsonl") {
+        OutputFormat::JsonLines
+    } else {
+        OutputFormat::Text
+    }
+}
+
+fn get_sort(matches: &clap::ArgMatches, config: &Config) -> TaskSort {
+    matches
+        .get_one::<TaskSort>("sort")
+        .cloned()
+        .unwrap_or_else(|| config.list.sort.clone())
+}
+
+/// The status and `--all` of a listing without a status flag.
+fn default_listing(matches: &clap::ArgMatches, config: &Config) -> (Option<Status>, bool) {
+    if matches.get_flag("all") {
+        return (None, true);
+    }
+    match &config.list.status {
+        ListStatus::All => (None, true),
+        ListStatus::Only(Status::Pending) => (None, false),
+        ListStatus::Only(status) => (Some(status.clone()), false),
+    }
+}
+
+fn get_tag_filter(matches: &clap::ArgMatches) -> TagFilter {
+    let collect = |id: &str| -> Vec<String> {
+        matches
+            .get_many::<String>(id)
+            .map(|vals| vals.map(|tag| filter_tag(tag)).collect())
+            .unwrap_or_default()
+    };
+
+    TagFilter {
+        include: collect("tag"),
+        exclude: collect("no-tag"),
+    }
+}
+
+fn filter_tag(raw: &str) -> String {
+    let tag = raw.trim_start_matches('+');
+    if let Err(e) = validate_tag_name(tag) {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
+    tag.to_string()
+}
+
+fn get_due_filter(matches: &clap::ArgMatches) -> DueFilter {
+    match due_filter_at(matches, Local::now()) {
+        Ok(due) => due,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
     }
 }
 
+/// The due filter `matches` asks for, with dates counted from `now`.
+fn due_filter_at(matches: &clap::ArgMatches, now: DateTime<Local>) -> Result<DueFilter, TaskError> {
+    let bound = |id: &str| {
+        matches
+            .get_one::<String>(id)
+            .map(|raw| parse_due_date_at(raw, now))
+            .transpose()
+    };
+
+    let mut due = DueFilter {
+        by: bound("due-before")?,
+        after: bound("due-after")?,
+        missing: matches.get_flag("no-due"),
+        overdue: matches.get_flag("overdue"),
+    };
+    let today = now.date_naive();
+    if matches.get_flag("due-today") {
+        let (after, by) = day_bounds(today, today)?;
+        (due.after, due.by) = (Some(after), Some(by));
+    }
+    if matches.get_flag("due-this-week") {
+        let monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+        let (after, by) = day_bounds(monday, monday + Duration::days(6))?;
+        (due.after, due.by) = (Some(after), Some(by));
+    }
+    Ok(due)
+}
+
+/// `after` and `by` bounds covering the days from `first` to `last`: the end
+/// of the day before `first` and the end of `last`.
+fn day_bounds(
+    first: NaiveDate,
+    last: NaiveDate,
+) -> Result<(DateTime<Utc>, DateTime<Utc>), TaskError> {
+    let before = end_of_local_day(first - Duration::days(1))?;
+    Ok((before, end_of_local_day(last)?))
+}
+
 fn get_task_name(matches: &clap::ArgMatches) -> Option<String> {
     matches
         .get_many::<String>("task")
@@ -412,15 +1023,15 @@ fn get_description(matches: &clap::ArgMatches) -> Option<String> {
     }
 }
 
-fn get_due_date(matches: &clap::ArgMatches) -> Option<DateTime<Utc>> {
+fn get_due_date(matches: &clap::ArgMatches) -> Option<(DateTime<Utc>, DuePrecision)> {
     if let Some(date_vals) = matches.get_many::<String>("due-date") {
         let date_str = date_vals
             .map(|status| status.as_str())
             .collect::<Vec<_>>()
             .join(" ");
 
-        match parse_due_date(&date_str) {
-            Ok(dt) => Some(dt),
+        match parse_due(&date_str) {
+            Ok(due) => Some(due),
             Err(e) => {
                 eprintln!("{}", e);
                 std::process::exit(1);
@@ -438,3 +1049,138 @@ fn get_show_descriptions(matches: &clap::ArgMatches) -> bool {
         matches.contains_id("description")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn local(y: i32, m: u32, d: u32, h: u32, min: u32, s: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(y, m, d, h, min, s).unwrap()
+    }
+
+    fn end_of(y: i32, m: u32, d: u32) -> Option<DateTime<Utc>> {
+        Some(local(y, m, d, 23, 59, 59).with_timezone(&Utc))
+    }
+
+    fn due_filter(args: &[&str], now: DateTime<Local>) -> DueFilter {
+        let matches = build_cli()
+            .try_get_matches_from(["tarea"].iter().chain(args))
+            .unwrap();
+        due_filter_at(&matches, now).unwrap()
+    }
+
+    #[test]
+    fn test_due_today_covers_the_local_day() {
+        let due = due_filter(&["--due-today"], local(2025, 8, 6, 0, 0, 1));
+        assert_eq!(
+            (due.after, due.by),
+            (end_of(2025, 8, 5), end_of(2025, 8, 6))
+        );
+
+        let due = due_filter(&["--due-today"], local(2025, 8, 6, 23, 59, 59));
+        assert_eq!(
+            (due.after, due.by),
+            (end_of(2025, 8, 5), end_of(2025, 8, 6))
+        );
+    }
+
+    #[test]
+    fn test_due_this_week_runs_monday_to_sunday() {
+        // Monday 2025-08-04 to Sunday 2025-08-10, whatever day it is
+        for now in [
+            local(2025, 8, 4, 0, 0, 0),
+            local(2025, 8, 6, 10, 30, 0),
+            local(2025, 8, 10, 23, 59, 59),
+        ] {
+            let due = due_filter(&["--due-this-week"], now);
+            assert_eq!(
+                (due.after, due.by),
+                (end_of(2025, 8, 3), end_of(2025, 8, 10)),
+                "{now}"
+            );
+        }
+
+        // Across a month and a year
+        let due = due_filter(&["--due-this-week"], local(2025, 12, 31, 12, 0, 0));
+        assert_eq!(
+            (due.after, due.by),
+            (end_of(2025, 12, 28), end_of(2026, 1, 4))
+        );
+    }
+
+    #[test]
+    fn test_due_filter_keeps_overdue_and_missing() {
+        let now = local(2025, 8, 6, 10, 30, 0);
+
+        let due = due_filter(&["--overdue", "--due-after", "today"], now);
+        assert!(due.overdue && !due.missing);
+        assert_eq!((due.after, due.by), (end_of(2025, 8, 6), None));
+
+        let due = due_filter(&["--no-due"], now);
+        assert!(due.missing && !due.overdue);
+        assert_eq!((due.after, due.by), (None, None));
+
+        for args in [
+            ["--overdue", "--due-today"],
+            ["--no-due", "--due-this-week"],
+        ] {
+            let matches = build_cli().try_get_matches_from(["tarea"].iter().chain(&args));
+            assert!(matches.is_err(), "{args:?}");
+        }
+
+        let due = due_filter(&["--overdue"], now);
+        assert_eq!(
+            due,
+            DueFilter {
+                overdue: true,
+                ..DueFilter::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_due_before_and_after_count_from_now() {
+        let due = due_filter(
+            &["--due-after", "today", "--due-before", "friday"],
+            local(2025, 8, 6, 10, 30, 0),
+        );
+        assert_eq!(
+            (due.after, due.by),
+            (end_of(2025, 8, 6), end_of(2025, 8, 8))
+        );
+
+        // A phrase is one quoted value; later words are left to the task
+        let due = due_filter(&["--due-before", "next week"], local(2025, 8, 6, 10, 30, 0));
+        assert_eq!(due.by, end_of(2025, 8, 11));
+
+        let matches = build_cli()
+            .try_get_matches_from(["tarea", "--due-after", "today", "Water", "plants"])
+            .unwrap();
+        assert_eq!(
+            matches.get_one::<String>("due-after").map(String::as_str),
+            Some("today")
+        );
+        assert_eq!(matches.get_many::<String>("task").unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_every_takes_one_rule_and_leaves_the_name() {
+        let matches = build_cli()
+            .try_get_matches_from(["tarea", "--every", "week", "Water", "plants"])
+            .unwrap();
+        let TaskCommand::Add {
+            name, recurrence, ..
+        } = parse_command(&matches, &Config::default())
+        else {
+            panic!("expected an add");
+        };
+        assert_eq!(name, "Water plants");
+        assert_eq!(recurrence.unwrap().to_string(), "week");
+
+        let matches = build_cli()
+            .try_get_matches_from(["tarea", "Standup", "--every", "1st monday"])
+            .unwrap();
+        assert_eq!(get_every(&matches).as_deref(), Some("1st monday"));
+    }
+}
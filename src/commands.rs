@@ -1,28 +1,55 @@
+use crate::config::Config;
 use crate::database::TaskManager;
-use crate::display::{StatusDisplay, format_task_line_with_number, print_task_details};
+use crate::display::{
+    StatusDisplay, display_options, format_archived_line, format_task_line_with_number, pretty_due,
+    print_event_log, print_replayed_events, print_task_details, print_task_history,
+};
 use crate::editor;
-use crate::types::{EditField, Status, StatusFilter, Task, TaskCommand, TaskError};
+use crate::formats::{self, ImportAction, json};
+use crate::profile::{self, Profile};
+use crate::snapshot::ListSnapshot;
+use crate::types::{
+    ConflictStrategy, DataFormat, DueFilter, DuePrecision, EditField, ExportOptions, ImportOptions,
+    OutputFormat, Priority, Recurrence, Status, StatusFilter, TagFilter, Task, TaskCommand,
+    TaskError, TaskFilter, TaskSort,
+};
 use crate::utils::{
-    delete_database, format_task_not_found_message, is_number, parse_due_date, resolve_task,
-    save_last_list_all, status_filter_from_params, was_last_list_all,
+    confirm, delete_database, expand_task_refs, format_task_not_found_message, indexed_len,
+    is_number, parse_due, read_stdin_refs, resolve_task, resolve_tasks, status_filter_from_params,
 };
-use chrono::{DateTime, NaiveDateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use clap_complete::generate;
 use clap_complete::shells::{Bash, Elvish, Fish, PowerShell, Zsh};
 use colored::*;
+use std::fs;
 use std::io::{self, Write};
+use std::path::Path;
 use terminal_size::{Width, terminal_size};
 
-const WRAP_COLUMN: usize = 80;
-const SHORT_ID_LENGTH: usize = 8;
-
-pub fn execute_command(manager: &TaskManager, command: TaskCommand) -> Result<(), TaskError> {
+pub fn execute_command(
+    manager: &TaskManager,
+    config: &Config,
+    command: TaskCommand,
+) -> Result<(), TaskError> {
     match command {
         TaskCommand::Add {
             name,
             description,
             due_date,
-        } => handle_add(manager, name, description, due_date),
+            tags,
+            recurrence,
+            priority,
+        } => handle_add(
+            manager,
+            name,
+            description,
+            due_date,
+            tags,
+            recurrence,
+            priority,
+        ),
+
+        TaskCommand::Archived => handle_archived(manager),
 
         TaskCommand::Completions {
             shell,
@@ -31,19 +58,41 @@ pub fn execute_command(manager: &TaskManager, command: TaskCommand) -> Result<()
         } => handle_completions(shell, dynamic_bash, dynamic_fish),
 
         TaskCommand::Delete {
-            id_or_index,
+            ids_or_indices,
             status,
-        } => handle_delete(manager, id_or_index, status),
+            assume_yes,
+        } => handle_delete(manager, ids_or_indices, status, assume_yes),
 
         TaskCommand::List {
             status,
             show_all,
             show_descriptions,
-        } => handle_list(manager, status, show_all, show_descriptions),
+            tags,
+            due,
+            sort,
+            output,
+        } => handle_list(
+            manager,
+            status,
+            show_all,
+            show_descriptions,
+            tags,
+            due,
+            sort,
+            output,
+            &ListSnapshot::path()?,
+        ),
 
-        TaskCommand::ListNames { show_all, status } => handle_list_names(manager, show_all, status),
+        TaskCommand::ListNames {
+            show_all,
+            status,
+            tags,
+            due,
+            sort,
+            output,
+        } => handle_list_names(manager, show_all, status, tags, due, sort, output),
 
-        TaskCommand::Show { id } => handle_show(manager, id),
+        TaskCommand::Show { id, output } => handle_show(manager, id, output),
 
         TaskCommand::ShowName {
             id_or_index,
@@ -52,11 +101,60 @@ pub fn execute_command(manager: &TaskManager, command: TaskCommand) -> Result<()
 
         TaskCommand::Edit { id_or_index, field } => handle_edit(manager, id_or_index, field),
 
-        TaskCommand::UpdateStatus { id, status } => handle_update_status(manager, id, status),
+        TaskCommand::Export { format, options } => handle_export(manager, format, options),
+
+        TaskCommand::Import {
+            path,
+            format,
+            on_conflict,
+            dry_run,
+            options,
+        } => handle_import(manager, path, format, on_conflict, dry_run, options),
+
+        TaskCommand::History { id_or_index } => handle_history(manager, id_or_index),
+
+        TaskCommand::Log => handle_log(manager),
 
-        TaskCommand::DeleteDatabase => delete_database(),
+        TaskCommand::Redo => handle_redo(manager),
 
-        TaskCommand::Ids { short_only, filter } => handle_ids(manager, short_only, filter),
+        TaskCommand::Undo => handle_undo(manager),
+
+        TaskCommand::UpdateStatus {
+            ids,
+            status,
+            assume_yes,
+        } => handle_update_status(manager, ids, status, assume_yes),
+
+        TaskCommand::DeleteDatabase { assume_yes } => {
+            delete_database(&profile::current().db_path(config)?, assume_yes)
+        }
+
+        command @ (TaskCommand::Profiles
+        | TaskCommand::CreateProfile { .. }
+        | TaskCommand::RenameProfile { .. }
+        | TaskCommand::DeleteProfile { .. }) => execute_profile_command(command),
+
+        TaskCommand::Move {
+            ids_or_indices,
+            profile,
+            assume_yes,
+        } => handle_move(manager, config, ids_or_indices, profile, assume_yes),
+
+        TaskCommand::Purge {
+            older_than,
+            assume_yes,
+        } => handle_purge(manager, older_than, assume_yes),
+
+        TaskCommand::Restore { id_or_index } => handle_restore(manager, id_or_index),
+
+        TaskCommand::Ids {
+            short_only,
+            filter,
+            tags,
+            due,
+            sort,
+            output,
+        } => handle_ids(manager, short_only, filter, tags, due, sort, output),
 
         TaskCommand::EditWithEditor { id_or_index } => {
             handle_edit_with_editor(manager, id_or_index)
@@ -68,9 +166,18 @@ fn handle_add(
     manager: &TaskManager,
     name: String,
     description: Option<String>,
-    due_date: Option<DateTime<Utc>>,
+    due_date: Option<(DateTime<Utc>, DuePrecision)>,
+    tags: Vec<String>,
+    recurrence: Option<Recurrence>,
+    priority: Option<Priority>,
 ) -> Result<(), TaskError> {
-    let task = Task::new(name, description, due_date)?;
+    let mut task = Task::new(name, description, due_date.map(|(due, _)| due))?;
+    if let Some((_, precision)) = due_date {
+        task.due_precision = precision;
+    }
+    task.tags = tags;
+    task.recurrence = recurrence;
+    task.priority = priority;
     manager.add_task(task.clone())?;
     println!("{}", "task created successfully".bright_green());
     print_task_details(&task, true);
@@ -107,87 +214,506 @@ fn handle_completions(
 
 fn handle_delete(
     manager: &TaskManager,
-    id_or_index: String,
+    ids_or_indices: Vec<String>,
     status: Option<Status>,
+    assume_yes: bool,
 ) -> Result<(), TaskError> {
-    let use_all = was_last_list_all();
-    let filter = match (status.clone(), use_all) {
-        (Some(_s), _) if use_all => StatusFilter::All,
-        (Some(s), _) => StatusFilter::AnyOf(vec![s]),
-        (None, true) => StatusFilter::All,
-        (None, false) => StatusFilter::PendingOnly,
+    let mut references = ids_or_indices;
+    let from_stdin = read_stdin_refs(&mut references)?;
+    let references = expand_task_refs(&references, indexed_len(manager, status.clone())?)?;
+
+    let (tasks, missing) = resolve_tasks(manager, &references, status.clone())?;
+    let context = status.clone().map(|s| format!(" in {} tasks", s));
+
+    if tasks.is_empty() {
+        print_missing_tasks(&missing, context.as_deref());
+        return Ok(());
+    }
+
+    let prompt = match tasks.as_slice() {
+        [task] => format!(
+            "delete task '{}'?",
+            truncate_with_dots(&task.name, display_options().name_width)
+        ),
+        _ => format!(
+            "delete {} tasks?\n{}",
+            tasks.len(),
+            task_summary_lines(&tasks)
+        ),
     };
-    let task_list = manager.list_tasks(filter)?;
+    if !(assume_yes || confirm(&prompt, from_stdin)?) {
+        println!("{}", "task deletion cancelled".bright_yellow());
+        return Ok(());
+    }
+
+    let archived = manager.batch(|| {
+        let mut archived = 0;
+        for task in &tasks {
+            if manager.delete_task_by_id(&task.id)? {
+                archived += 1;
+            }
+        }
+        Ok(archived)
+    })?;
+
+    match (archived, tasks.as_slice()) {
+        (0, _) => println!(
+            "{}",
+            "task not found (may have been already deleted)".bright_red()
+        ),
+        (1, [task]) => println!(
+            "{}",
+            format!("task archived (undo with --restore {})", short_id(&task.id)).bright_green()
+        ),
+        (n, _) => println!(
+            "{}",
+            format!("{} archived (undo with --undo)", count_tasks(n)).bright_green()
+        ),
+    }
+    print_missing_tasks(&missing, context.as_deref());
+
+    if archived > 0 {
+        print_updated_list(manager, status)?;
+    }
+    Ok(())
+}
+
+/// Re-print the list after a change, keeping the `--all` and sort of the
+/// last listing.
+fn print_updated_list(manager: &TaskManager, status: Option<Status>) -> Result<(), TaskError> {
+    let (show_all, sort) = ListSnapshot::load()
+        .map(|s| (s.show_all, s.sort.parse().unwrap_or_default()))
+        .unwrap_or_default();
+
+    println!();
+    println!("Updated list of tasks:");
+    handle_list(
+        manager,
+        status,
+        show_all,
+        false,
+        TagFilter::default(),
+        DueFilter::default(),
+        sort,
+        OutputFormat::Text,
+        &ListSnapshot::path()?,
+    )
+}
+
+fn task_summary_lines(tasks: &[Task]) -> String {
+    tasks
+        .iter()
+        .map(|t| {
+            format!(
+                "  {}  {}",
+                short_id(&t.id),
+                truncate_with_dots(&t.name, display_options().name_width)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
 
-    let task_opt = if is_number(&id_or_index) {
+/// `1 task`, `3 tasks`.
+fn count_tasks(n: usize) -> String {
+    format!("{} task{}", n, if n == 1 { "" } else { "s" })
+}
+
+fn print_missing_tasks(missing: &[String], context: Option<&str>) {
+    for reference in missing {
+        println!("{}", format_task_not_found_message(reference, context));
+    }
+}
+
+fn handle_archived(manager: &TaskManager) -> Result<(), TaskError> {
+    let archived = manager.list_archived()?;
+
+    if archived.is_empty() {
+        println!("{}", "no archived tasks found".dimmed());
+        return Ok(());
+    }
+
+    let number_width = archived.len().to_string().len();
+    let name_width = archived
+        .iter()
+        .map(|a| truncate_with_dots(&a.task.name, display_options().name_width).len())
+        .max()
+        .unwrap_or(10)
+        .max(10);
+
+    for (idx, entry) in archived.iter().enumerate() {
+        format_archived_line(idx + 1, number_width, entry, name_width);
+    }
+    Ok(())
+}
+
+fn handle_restore(manager: &TaskManager, id_or_index: String) -> Result<(), TaskError> {
+    let id = if is_number(&id_or_index) {
         let idx: usize = id_or_index.parse().unwrap_or(0);
-        task_list.into_iter().nth(idx.saturating_sub(1))
+        let Some(position) = idx.checked_sub(1) else {
+            return Err(TaskError::InvalidInput(format!(
+                "Invalid index '{}': archive indices start at 1",
+                id_or_index
+            )));
+        };
+        match manager.list_archived()?.into_iter().nth(position) {
+            Some(entry) => entry.task.id,
+            None => id_or_index.clone(),
+        }
     } else {
-        task_list
-            .into_iter()
-            .find(|t| t.id.starts_with(&id_or_index))
+        id_or_index.clone()
     };
 
-    match task_opt {
+    match manager.restore_task(&id)? {
         Some(task) => {
-            let task_display = if task.name.len() > 50 {
-                format!("{}...", &task.name[..47])
-            } else {
-                task.name.clone()
-            };
-
-            let confirmed = {
-                print!("delete task '{}'? (y/N): ", task_display);
-                io::stdout().flush()?;
-                let mut input = String::new();
-                io::stdin().read_line(&mut input)?;
-                matches!(input.trim().to_lowercase().as_str(), "y" | "yes")
-            };
-
-            if confirmed {
-                if manager.delete_task_by_id(&task.id)? {
-                    println!("{}", "success".bright_green());
-                    println!();
-                    println!("Updated list of tasks:");
-                    handle_list(manager, status, use_all, false)?;
-                } else {
-                    println!(
-                        "{}",
-                        "task not found (may have been already deleted)".bright_red()
-                    );
-                }
-            } else {
-                println!("{}", "task deletion cancelled".bright_yellow());
+            println!("{}", "task restored".bright_green());
+            print_task_details(&task, true);
+        }
+        None => println!(
+            "{}",
+            format_task_not_found_message(&id_or_index, Some(" in archived tasks"))
+        ),
+    }
+    Ok(())
+}
+
+fn handle_purge(
+    manager: &TaskManager,
+    older_than: Option<Duration>,
+    assume_yes: bool,
+) -> Result<(), TaskError> {
+    let cutoff = older_than.map(|age| Utc::now() - age);
+    let count = manager
+        .list_archived()?
+        .iter()
+        .filter(|a| cutoff.is_none_or(|c| a.deleted_at <= c))
+        .count();
+
+    if count == 0 {
+        println!("{}", "no archived tasks to purge".dimmed());
+        return Ok(());
+    }
+
+    let prompt = format!(
+        "permanently delete {} archived task{}? This action cannot be undone.",
+        count,
+        if count == 1 { "" } else { "s" }
+    );
+
+    if assume_yes || confirm(&prompt, false)? {
+        let purged = manager.purge_archived(cutoff)?;
+        println!(
+            "{}",
+            format!("purged {} from the archive", count_tasks(purged)).bright_green()
+        );
+    } else {
+        println!("{}", "purge cancelled".bright_yellow());
+    }
+    Ok(())
+}
+
+/// Run a command that [manages profiles](TaskCommand::manages_profiles).
+pub fn execute_profile_command(command: TaskCommand) -> Result<(), TaskError> {
+    match command {
+        TaskCommand::Profiles => handle_profiles(),
+        TaskCommand::CreateProfile { name } => handle_create_profile(name),
+        TaskCommand::RenameProfile { from, to } => handle_rename_profile(from, to),
+        TaskCommand::DeleteProfile { name, assume_yes } => handle_delete_profile(name, assume_yes),
+        _ => unreachable!(),
+    }
+}
+
+fn handle_profiles() -> Result<(), TaskError> {
+    let current = profile::current();
+    for profile in Profile::list()? {
+        if profile == *current {
+            println!("{} {}", "*".bright_green(), profile.name.bright_white());
+        } else {
+            println!("  {}", profile.name);
+        }
+    }
+    Ok(())
+}
+
+fn handle_create_profile(name: String) -> Result<(), TaskError> {
+    Profile::new(&name)?.create()?;
+    println!(
+        "{}",
+        format!(
+            "profile '{}' created (use it with --profile {})",
+            name, name
+        )
+        .bright_green()
+    );
+    Ok(())
+}
+
+fn handle_rename_profile(from: String, to: String) -> Result<(), TaskError> {
+    let profile = Profile::new(&from)?;
+    if profile == *profile::current() {
+        return Err(TaskError::InvalidInput(format!(
+            "Profile '{}' is in use, switch to another one to rename it",
+            from
+        )));
+    }
+    profile.rename(&Profile::new(&to)?)?;
+    println!(
+        "{}",
+        format!("profile '{}' renamed to '{}'", from, to).bright_green()
+    );
+    Ok(())
+}
+
+fn handle_delete_profile(name: String, assume_yes: bool) -> Result<(), TaskError> {
+    let profile = Profile::new(&name)?;
+    profile.ensure_named("deleted")?;
+    if profile == *profile::current() {
+        return Err(TaskError::InvalidInput(format!(
+            "Profile '{}' is in use, switch to another one to delete it",
+            name
+        )));
+    }
+
+    let prompt = format!(
+        "delete profile '{}' and all of its tasks? This action cannot be undone.",
+        name
+    );
+    if !(assume_yes || confirm(&prompt, false)?) {
+        println!("{}", "profile deletion cancelled".bright_yellow());
+        return Ok(());
+    }
+
+    profile.delete()?;
+    println!("{}", format!("profile '{}' deleted", name).bright_green());
+    Ok(())
+}
+
+fn handle_move(
+    manager: &TaskManager,
+    config: &Config,
+    ids_or_indices: Vec<String>,
+    profile_name: String,
+    assume_yes: bool,
+) -> Result<(), TaskError> {
+    let target = Profile::new(&profile_name)?;
+    if target == *profile::current() {
+        println!(
+            "{}",
+            format!("tasks are already in profile '{}'", target.name).bright_yellow()
+        );
+        return Ok(());
+    }
+    if !target.exists()? {
+        return Err(TaskError::InvalidInput(format!(
+            "Profile '{}' does not exist",
+            target.name
+        )));
+    }
+
+    let mut references = ids_or_indices;
+    let from_stdin = read_stdin_refs(&mut references)?;
+    let references = expand_task_refs(&references, indexed_len(manager, None)?)?;
+
+    let (tasks, missing) = resolve_tasks(manager, &references, None)?;
+
+    if tasks.is_empty() {
+        print_missing_tasks(&missing, None);
+        return Ok(());
+    }
+
+    if tasks.len() > 1 && !assume_yes {
+        let prompt = format!(
+            "move {} tasks to profile '{}'?\n{}",
+            tasks.len(),
+            target.name,
+            task_summary_lines(&tasks)
+        );
+        if !confirm(&prompt, from_stdin)? {
+            println!("{}", "move cancelled".bright_yellow());
+            return Ok(());
+        }
+    }
+
+    let destination = TaskManager::open(&target.db_path(config)?)?;
+    let moved = move_tasks(manager, &destination, &tasks, &target.name)?;
+
+    match moved {
+        0 => {}
+        1 => println!(
+            "{}",
+            format!("task moved to profile '{}'", target.name).bright_green()
+        ),
+        n => println!(
+            "{}",
+            format!("{} tasks moved to profile '{}'", n, target.name).bright_green()
+        ),
+    }
+    print_missing_tasks(&missing, None);
+
+    if moved > 0 {
+        print_updated_list(manager, None)?;
+    }
+    Ok(())
+}
+
+/// Copy `tasks` into `destination`, the database of profile `profile_name`,
+/// then take them out of `manager`. The copies are committed before anything
+/// is removed, so a failure can leave a task in both profiles but never in
+/// neither.
+fn move_tasks(
+    manager: &TaskManager,
+    destination: &TaskManager,
+    tasks: &[Task],
+    profile_name: &str,
+) -> Result<usize, TaskError> {
+    let added = destination.batch(|| {
+        let mut added = Vec::new();
+        for task in tasks {
+            if destination.has_task_id(&task.id)? {
+                println!(
+                    "{}",
+                    format!(
+                        "task {} already exists in profile '{}', skipped",
+                        short_id(&task.id),
+                        profile_name
+                    )
+                    .bright_yellow()
+                );
+                continue;
             }
+            destination.add_task(task.clone())?;
+            added.push(task.id.as_str());
         }
-        None => {
-            let context = status
-                .map(|s| format!(" in {} tasks", s))
-                .unwrap_or_else(|| {
-                    if was_last_list_all() {
-                        " in all tasks".to_string()
-                    } else {
-                        " in pending tasks".to_string()
-                    }
-                });
+        Ok(added)
+    })?;
+
+    manager.batch(|| {
+        for id in &added {
+            manager.move_out(id, profile_name)?;
+        }
+        Ok(added.len())
+    })
+}
+
+fn handle_export(
+    manager: &TaskManager,
+    format: DataFormat,
+    options: ExportOptions,
+) -> Result<(), TaskError> {
+    let tasks = manager.list_tasks(StatusFilter::All)?;
+    let archived = manager.list_archived()?;
+    let output = formats::export(format, &tasks, &archived, &options)?;
+    print!("{}", output);
+    if !output.ends_with('\n') {
+        println!();
+    }
+    Ok(())
+}
+
+fn handle_import(
+    manager: &TaskManager,
+    path: String,
+    format: Option<DataFormat>,
+    on_conflict: ConflictStrategy,
+    dry_run: bool,
+    options: ImportOptions,
+) -> Result<(), TaskError> {
+    let input = if path == "-" {
+        io::read_to_string(io::stdin())?
+    } else {
+        fs::read_to_string(&path)?
+    };
+    let format = format
+        .or_else(|| DataFormat::from_path(&path))
+        .unwrap_or(DataFormat::Json);
+
+    // Spreadsheet formats number records like spreadsheet rows, text
+    // formats by line
+    let label = match format {
+        DataFormat::Csv | DataFormat::Tsv => "row",
+        DataFormat::Markdown | DataFormat::Org | DataFormat::TodoTxt => "line",
+        DataFormat::Ical => "todo",
+        DataFormat::Json | DataFormat::Taskwarrior => "record",
+    };
+
+    let mut tasks = Vec::new();
+    let mut rejected = 0;
+    for record in formats::parse(format, &input, &options)? {
+        match record.task {
+            Ok(task) => tasks.push((task, record.archived)),
+            Err(e) => {
+                rejected += 1;
+                println!(
+                    "{} {}",
+                    format!("{} {}:", label, record.number).bright_red(),
+                    e
+                );
+            }
+        }
+    }
 
+    if format == DataFormat::Markdown {
+        formats::match_ids_by_name(manager, &mut tasks)?;
+    }
+    let plan = formats::plan_import(manager, tasks, on_conflict)?;
+    let count = |action: ImportAction| plan.iter().filter(|p| p.action == action).count();
+
+    if dry_run {
+        for entry in &plan {
             println!(
-                "{}",
-                format_task_not_found_message(&id_or_index, Some(&context))
+                "{:<9} {}  {}{}",
+                entry.action,
+                short_id(&entry.task.id),
+                truncate_with_dots(&entry.task.name, display_options().name_width),
+                if entry.archived { " (archived)" } else { "" }
             );
         }
+    } else {
+        formats::apply_import(manager, &plan)?;
     }
+
+    let summary = format!(
+        "{}{} added, {} overwritten, {} renamed, {} skipped, {} rejected",
+        if dry_run { "dry run: " } else { "" },
+        count(ImportAction::Add),
+        count(ImportAction::Overwrite),
+        count(ImportAction::Rename),
+        count(ImportAction::Skip),
+        rejected
+    );
+    println!("{}", summary.bright_green());
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn handle_list(
     manager: &TaskManager,
     status: Option<Status>,
     show_all: bool,
     show_descriptions: bool,
+    tags: TagFilter,
+    due: DueFilter,
+    sort: TaskSort,
+    output: OutputFormat,
+    snapshot: &Path,
 ) -> Result<(), TaskError> {
     let filter = status_filter_from_params(status.clone(), show_all);
-    let tasks = manager.list_tasks(filter)?;
+    let narrowed_by = match (tags.is_empty(), due.is_empty()) {
+        (true, true) => None,
+        (false, true) => Some("the tag filter"),
+        (true, false) => Some("the due-date filter"),
+        (false, false) => Some("the tag and due-date filters"),
+    };
+    let tasks = manager.list_tasks(
+        TaskFilter::new(filter, tags.clone())
+            .due_within(due.clone())
+            .sorted_by(sort.clone()),
+    )?;
+    // Only a list the user has seen may back later indices
+    if output != OutputFormat::Text {
+        return json::print_tasks(&tasks, output);
+    }
+    ListSnapshot::new(show_all, status.as_ref(), &tags, &due, &sort, &tasks).save_to(snapshot)?;
 
     if tasks.is_empty() {
         let message = match (show_all, status) {
@@ -195,6 +721,10 @@ fn handle_list(
             (false, Some(s)) => format!("no {} tasks found", s),
             (false, None) => "no pending tasks found".to_string(),
         };
+        let message = match narrowed_by {
+            Some(filters) => format!("{} matching {}", message, filters),
+            None => message,
+        };
         println!("{}", message.dimmed());
         return Ok(());
     }
@@ -214,7 +744,6 @@ fn handle_list(
             StatusDisplay::Dot,
         );
     }
-    save_last_list_all(show_all)?;
     Ok(())
 }
 
@@ -222,9 +751,21 @@ fn handle_list_names(
     manager: &TaskManager,
     show_all: bool,
     status: Option<Status>,
+    tags: TagFilter,
+    due: DueFilter,
+    sort: TaskSort,
+    output: OutputFormat,
 ) -> Result<(), TaskError> {
     let filter = status_filter_from_params(status, show_all);
-    let tasks = manager.list_tasks(filter)?;
+    let tasks = manager.list_tasks(
+        TaskFilter::new(filter, tags)
+            .due_within(due)
+            .sorted_by(sort),
+    )?;
+    if output != OutputFormat::Text {
+        return json::print_tasks(&tasks, output);
+    }
+
     if tasks.is_empty() {
         println!("{}", "no tasks found".dimmed());
     } else {
@@ -235,12 +776,16 @@ fn handle_list_names(
     Ok(())
 }
 
-fn handle_show(manager: &TaskManager, id: String) -> Result<(), TaskError> {
-    let use_all = was_last_list_all();
-    let task_opt = resolve_task(manager, &id, use_all)?;
+fn handle_show(manager: &TaskManager, id: String, output: OutputFormat) -> Result<(), TaskError> {
+    let task_opt = resolve_task(manager, &id)?;
 
     match task_opt {
+        Some(task) if output != OutputFormat::Text => json::print_task(&task, output)?,
         Some(task) => print_task_details(&task, false),
+        // Scripts reading the JSON need a failure they can check for
+        None if output != OutputFormat::Text => {
+            return Err(TaskError::InvalidId(format!("task '{}' not found", id)));
+        }
         None => println!("{}", format_task_not_found_message(&id, None)),
     }
     Ok(())
@@ -251,17 +796,9 @@ fn handle_show_name(
     id_or_index: String,
     status: Option<Status>,
 ) -> Result<(), TaskError> {
-    let use_all = was_last_list_all();
-    let filter = status_filter_from_params(status.clone(), use_all);
-    let task_list = manager.list_tasks(filter)?;
-    let task_opt = if is_number(&id_or_index) {
-        let idx: usize = id_or_index.parse().unwrap_or(0);
-        task_list.into_iter().nth(idx.saturating_sub(1))
-    } else {
-        task_list
-            .into_iter()
-            .find(|t| t.id.starts_with(&id_or_index))
-    };
+    let (mut found, _) =
+        resolve_tasks(manager, std::slice::from_ref(&id_or_index), status.clone())?;
+    let task_opt = found.pop();
     match task_opt {
         Some(t) => println!("{}", t.name),
         None => {
@@ -280,8 +817,7 @@ fn handle_edit(
     id_or_index: String,
     field: EditField,
 ) -> Result<(), TaskError> {
-    let use_all = was_last_list_all();
-    let full_id = match resolve_task(manager, &id_or_index, use_all)? {
+    let full_id = match resolve_task(manager, &id_or_index)? {
         Some(t) => t.id,
         None => {
             println!("{}", format_task_not_found_message(&id_or_index, None));
@@ -292,7 +828,9 @@ fn handle_edit(
     let changed = match field {
         EditField::Name(n) => manager.update_name(&full_id, &n)?,
         EditField::Description(d) => manager.update_description(&full_id, &d)?,
-        EditField::DueDate(dt) => manager.update_due(&full_id, Some(dt))?,
+        EditField::DueDate(dt, precision) => manager.update_due(&full_id, Some((dt, precision)))?,
+        EditField::Recurrence(r) => manager.update_recurrence(&full_id, r)?,
+        EditField::Priority(p) => manager.update_priority(&full_id, p)?,
     };
 
     if changed {
@@ -303,36 +841,158 @@ fn handle_edit(
     Ok(())
 }
 
+fn handle_history(manager: &TaskManager, id_or_index: String) -> Result<(), TaskError> {
+    // Prefer live tasks (so list indices work), then fall back to any task
+    // the log knows about, which covers archived and purged ones.
+    let task_id = match resolve_task(manager, &id_or_index)? {
+        Some(task) => task.id,
+        None => match manager.find_event_task_id(&id_or_index)? {
+            Some(id) => id,
+            None => {
+                println!("{}", format_task_not_found_message(&id_or_index, None));
+                return Ok(());
+            }
+        },
+    };
+
+    let events = manager.list_events(Some(&task_id))?;
+    if events.is_empty() {
+        println!("{}", "no history recorded for this task".dimmed());
+        return Ok(());
+    }
+
+    print_task_history(&task_id, &events);
+    Ok(())
+}
+
+fn handle_log(manager: &TaskManager) -> Result<(), TaskError> {
+    let events = manager.list_events(None)?;
+    if events.is_empty() {
+        println!("{}", "no changes recorded yet".dimmed());
+        return Ok(());
+    }
+
+    print_event_log(&events);
+    Ok(())
+}
+
+fn handle_undo(manager: &TaskManager) -> Result<(), TaskError> {
+    let (events, skipped) = manager.undo()?;
+    if !skipped.is_empty() {
+        print_replayed_events("skipped, cannot be undone:", &skipped);
+    }
+    if events.is_empty() {
+        println!("{}", "nothing to undo".dimmed());
+    } else {
+        print_replayed_events("undone:", &events);
+    }
+    Ok(())
+}
+
+fn handle_redo(manager: &TaskManager) -> Result<(), TaskError> {
+    let events = manager.redo()?;
+    if events.is_empty() {
+        println!("{}", "nothing to redo".dimmed());
+    } else {
+        print_replayed_events("redone:", &events);
+    }
+    Ok(())
+}
+
 fn handle_update_status(
     manager: &TaskManager,
-    id: String,
+    ids: Vec<String>,
     status: Status,
+    assume_yes: bool,
 ) -> Result<(), TaskError> {
-    let target_id = match resolve_task(manager, &id, was_last_list_all())? {
-        Some(t) => t.id,
-        None => {
-            println!("{}", format_task_not_found_message(&id, None));
+    let mut references = ids;
+    let from_stdin = read_stdin_refs(&mut references)?;
+    let references = expand_task_refs(&references, indexed_len(manager, None)?)?;
+
+    let (tasks, missing) = resolve_tasks(manager, &references, None)?;
+
+    if tasks.is_empty() {
+        print_missing_tasks(&missing, None);
+        return Ok(());
+    }
+
+    if tasks.len() > 1 && !assume_yes {
+        let prompt = format!(
+            "mark {} tasks as {}?\n{}",
+            tasks.len(),
+            status,
+            task_summary_lines(&tasks)
+        );
+        if !confirm(&prompt, from_stdin)? {
+            println!("{}", "status update cancelled".bright_yellow());
             return Ok(());
         }
+    }
+
+    let (unchanged, to_change): (Vec<&Task>, Vec<&Task>) =
+        tasks.iter().partition(|t| t.status == status);
+    let updated = manager.batch(|| {
+        let mut updated = 0;
+        for task in &to_change {
+            if manager.update_task_status(&task.id, status.clone())? {
+                updated += 1;
+            }
+        }
+        Ok(updated)
+    })?;
+
+    let color = match status {
+        Status::Done => "green",
+        Status::Pending => "yellow",
+        Status::Standby => "blue",
+    };
+    let single = match references.as_slice() {
+        [reference] => Some(reference),
+        _ => None,
     };
+    match (updated, single) {
+        (0, _) => {}
+        (1, Some(reference)) => println!(
+            "{}",
+            format!("Task {} marked as {}", reference, &status).color(color)
+        ),
+        (n, _) => println!(
+            "{}",
+            format!("{} marked as {}", count_tasks(n), &status).color(color)
+        ),
+    }
+    match (unchanged.len(), single) {
+        (0, _) => {}
+        (1, Some(reference)) => println!(
+            "{}",
+            format!("Task {} is already {}", reference, &status).dimmed()
+        ),
+        (n, _) => println!(
+            "{}",
+            format!("{} already {}", count_tasks(n), &status).dimmed()
+        ),
+    }
+    print_missing_tasks(&missing, None);
 
-    match manager.update_task_status(&target_id, status.clone())? {
-        true => {
-            let color = match status {
-                Status::Done => "green",
-                Status::Pending => "yellow",
-                Status::Standby => "blue",
-            };
-            println!(
-                "{}",
-                format!("Task {} marked as {}", id, &status).color(color)
-            );
-            println!();
-            println!("Updated list of tasks:");
-            let use_all = was_last_list_all();
-            handle_list(manager, Some(status), use_all, false)?;
+    if status == Status::Done {
+        let now = Utc::now();
+        for next in tasks
+            .iter()
+            .filter(|t| t.status != Status::Done)
+            .filter_map(|t| t.next_occurrence(now))
+        {
+            if let Some(due) = next.due_date {
+                let due = pretty_due(due, next.due_precision);
+                println!(
+                    "{}",
+                    format!("next '{}' is due {}", next.name, due).dimmed()
+                );
+            }
         }
-        false => println!("{}", format_task_not_found_message(&id, None)),
+    }
+
+    if updated > 0 {
+        print_updated_list(manager, Some(status))?;
     }
     Ok(())
 }
@@ -341,12 +1001,24 @@ fn handle_ids(
     manager: &TaskManager,
     short_only: bool,
     filter: Vec<Status>,
+    tags: TagFilter,
+    due: DueFilter,
+    sort: TaskSort,
+    output: OutputFormat,
 ) -> Result<(), TaskError> {
-    let tasks = manager.list_tasks(StatusFilter::AnyOf(filter))?;
+    let tasks = manager.list_tasks(
+        TaskFilter::new(StatusFilter::AnyOf(filter), tags)
+            .due_within(due)
+            .sorted_by(sort),
+    )?;
+
+    if output != OutputFormat::Text {
+        return json::print_tasks(&tasks, output);
+    }
 
     for task in tasks {
         let out = if short_only {
-            &task.id[..SHORT_ID_LENGTH]
+            short_id(&task.id)
         } else {
             &task.id
         };
@@ -356,8 +1028,7 @@ fn handle_ids(
 }
 
 fn handle_edit_with_editor(manager: &TaskManager, id_or_index: String) -> Result<(), TaskError> {
-    let use_all = was_last_list_all();
-    let task = match resolve_task(manager, &id_or_index, use_all)? {
+    let task = match resolve_task(manager, &id_or_index)? {
         Some(t) => t,
         None => {
             println!("{}", format_task_not_found_message(&id_or_index, None));
@@ -382,9 +1053,26 @@ fn handle_edit_with_editor(manager: &TaskManager, id_or_index: String) -> Result
         manager.update_description(&task.id, &edited.description)?;
         changed = true;
     }
-    let new_due_date = match edited.due.as_deref() {
-        Some(s) if !s.trim().is_empty() => match parse_due_date(s) {
-            Ok(dt) => Some(dt),
+    let old_due = task.due_date.map(|due| (due, task.due_precision));
+    let new_due = match edited.due.as_deref() {
+        // A due day is shown without its time, so only re-read it if touched
+        Some(s) if Some(s) == editor::EditableTask::from_task(&task).due.as_deref() => old_due,
+        Some(s) if !s.trim().is_empty() => match parse_due(s) {
+            Ok(due) => Some(due),
+            Err(e) => {
+                println!("{}", e);
+                return Ok(());
+            }
+        },
+        _ => None,
+    };
+    if new_due != old_due {
+        manager.update_due(&task.id, new_due)?;
+        changed = true;
+    }
+    let recurrence = match edited.every.as_deref().map(str::trim) {
+        Some(rule) if !rule.is_empty() && rule != "never" => match rule.parse() {
+            Ok(recurrence) => Some(recurrence),
             Err(e) => {
                 println!("{}", e);
                 return Ok(());
@@ -392,8 +1080,26 @@ fn handle_edit_with_editor(manager: &TaskManager, id_or_index: String) -> Result
         },
         _ => None,
     };
-    if new_due_date != task.due_date {
-        manager.update_due(&task.id, new_due_date)?;
+    if recurrence != task.recurrence {
+        manager.update_recurrence(&task.id, recurrence)?;
+        changed = true;
+    }
+    let priority = match edited.priority.as_deref().map(str::trim) {
+        Some(letter) if !letter.is_empty() => match letter.parse() {
+            Ok(priority) => Some(priority),
+            Err(e) => {
+                println!("{}", e);
+                return Ok(());
+            }
+        },
+        _ => None,
+    };
+    if priority != task.priority {
+        manager.update_priority(&task.id, priority)?;
+        changed = true;
+    }
+    if edited.tags != task.tags {
+        manager.set_tags(&task.id, &edited.tags)?;
         changed = true;
     }
     if changed {
@@ -417,13 +1123,7 @@ fn calculate_list_layout(tasks: &[Task], show_descriptions: bool) -> ListLayout
 
     let created_width = tasks
         .iter()
-        .map(|t| {
-            let dt = DateTime::<Utc>::from_naive_utc_and_offset(
-                NaiveDateTime::parse_from_str(&t.date, "%Y-%m-%d %H:%M:%S").unwrap(),
-                Utc,
-            );
-            crate::display::pretty_time(dt).len()
-        })
+        .map(|t| crate::display::list_time(t).chars().count())
         .max()
         .unwrap_or(0);
 
@@ -432,7 +1132,7 @@ fn calculate_list_layout(tasks: &[Task], show_descriptions: bool) -> ListLayout
         .map(|t| {
             if t.status != Status::Done {
                 t.due_date
-                    .map(|d| 3 + crate::display::pretty_time(d).len() + 1)
+                    .map(|d| 3 + pretty_due(d, t.due_precision).chars().count() + 1)
                     .unwrap_or(0)
             } else {
                 0
@@ -442,20 +1142,21 @@ fn calculate_list_layout(tasks: &[Task], show_descriptions: bool) -> ListLayout
         .unwrap_or(0);
 
     let term = term_width();
-    let base_cols = number_width + 2 + SHORT_ID_LENGTH + 1 + 1 + 1 + 1;
+    let wrap_column = display_options().wrap_column;
+    let base_cols = number_width + 2 + display_options().short_id_length + 1 + 1 + 1 + 1;
     let time_width = created_width;
     let cap = term
         .saturating_sub(base_cols + time_width + max_due_extra)
         .max(10);
 
     let longest_date_len = time_width + max_due_extra;
-    let forced_total = WRAP_COLUMN + 1 + longest_date_len;
+    let forced_total = wrap_column + 1 + longest_date_len;
 
     let should_force_time_col =
-        show_descriptions && base_cols < WRAP_COLUMN && term >= forced_total;
+        show_descriptions && base_cols < wrap_column && term >= forced_total;
 
     let name_width = if should_force_time_col {
-        WRAP_COLUMN + 2 - base_cols
+        wrap_column + 2 - base_cols
     } else {
         tasks
             .iter()
@@ -467,7 +1168,7 @@ fn calculate_list_layout(tasks: &[Task], show_descriptions: bool) -> ListLayout
 
     let indent_len = number_width + 2;
     let time_col_start = if should_force_time_col {
-        WRAP_COLUMN
+        wrap_column
     } else {
         base_cols + name_width
     };
@@ -487,6 +1188,11 @@ fn term_width() -> usize {
         .unwrap_or(80)
 }
 
+/// Imported tasks may carry ids shorter than the usual short form.
+fn short_id(id: &str) -> &str {
+    &id[..display_options().short_id_length.min(id.len())]
+}
+
 fn truncate_with_dots(s: &str, limit: usize) -> String {
     if s.len() <= limit {
         return s.to_string();
@@ -502,8 +1208,13 @@ pub fn estimated_lines(command: &TaskCommand, manager: &TaskManager) -> usize {
             show_descriptions,
             show_all,
             status,
+            tags,
+            due,
+            output: OutputFormat::Text,
+            ..
         } => {
             let filter = status_filter_from_params(status.clone(), *show_all);
+            let filter = TaskFilter::new(filter, tags.clone()).due_within(due.clone());
             if let Ok(tasks) = manager.list_tasks(filter) {
                 if *show_descriptions {
                     tasks.len() * 4 // 1 title + 2 blanks + 1 wrapped line (avg)
@@ -514,6 +1225,96 @@ pub fn estimated_lines(command: &TaskCommand, manager: &TaskManager) -> usize {
                 0
             }
         }
+        TaskCommand::Log => manager.list_events(None).map(|e| e.len()).unwrap_or(0),
         _ => 0, // other commands never exceed one screen
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+
+    fn manager_with(names: &[&str]) -> TaskManager {
+        let manager = TaskManager::from_connection(Connection::open_in_memory().unwrap()).unwrap();
+        for name in names {
+            let task = Task::new(name.to_string(), None, None).unwrap();
+            manager.add_task(task).unwrap();
+        }
+        manager
+    }
+
+    #[test]
+    fn test_json_listing_keeps_the_last_list() {
+        let manager = manager_with(&["a", "b"]);
+        let dir = tempfile::tempdir().unwrap();
+        let snapshot = dir.path().join("last_list.toml");
+        let list = |output| {
+            handle_list(
+                &manager,
+                None,
+                true,
+                false,
+                TagFilter::default(),
+                DueFilter::default(),
+                TaskSort::default(),
+                output,
+                &snapshot,
+            )
+            .unwrap()
+        };
+
+        list(OutputFormat::Json);
+        list(OutputFormat::JsonLines);
+        assert_eq!(ListSnapshot::load_from(&snapshot), None);
+
+        list(OutputFormat::Text);
+        let listed = ListSnapshot::load_from(&snapshot).unwrap();
+        assert_eq!(listed.ids.len(), 2);
+    }
+
+    #[test]
+    fn test_show_json_of_a_missing_task_fails() {
+        let manager = manager_with(&["a"]);
+
+        assert!(handle_show(&manager, "f00dfeed".to_string(), OutputFormat::Json).is_err());
+        assert!(handle_show(&manager, "f00dfeed".to_string(), OutputFormat::JsonLines).is_err());
+    }
+
+    #[test]
+    fn test_move_keeps_tasks_when_the_destination_fails() {
+        let source = manager_with(&["a", "b"]);
+        let tasks = source.list_tasks(StatusFilter::All).unwrap();
+
+        // A deferred constraint that only fails once the destination commits
+        let mut conn = Connection::open_in_memory().unwrap();
+        crate::migrations::migrate(&mut conn).unwrap();
+        conn.execute_batch(
+            "PRAGMA foreign_keys = ON;
+             CREATE TABLE parents (id INTEGER PRIMARY KEY);
+             CREATE TABLE children (
+                 parent INTEGER REFERENCES parents (id) DEFERRABLE INITIALLY DEFERRED
+             );
+             CREATE TRIGGER orphan AFTER INSERT ON tasks
+             BEGIN INSERT INTO children VALUES (1); END;",
+        )
+        .unwrap();
+        let destination = TaskManager::from_connection(conn).unwrap();
+
+        assert!(move_tasks(&source, &destination, &tasks, "work").is_err());
+        for task in &tasks {
+            assert!(source.find_task_by_id(&task.id).unwrap().is_some());
+            assert!(!destination.has_task_id(&task.id).unwrap());
+        }
+
+        let destination = manager_with(&[]);
+        assert_eq!(
+            move_tasks(&source, &destination, &tasks, "work").unwrap(),
+            2
+        );
+        for task in &tasks {
+            assert!(source.find_task_by_id(&task.id).unwrap().is_none());
+            assert!(destination.has_task_id(&task.id).unwrap());
+        }
+    }
+}
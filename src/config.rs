@@ -0,0 +1,293 @@
+//! Settings from `~/.tarea/config.toml`. Every key is optional and a missing
+//! file means the defaults.
+
+use crate::database::get_db_path;
+use crate::display::{DisplayOptions, DueSoonThresholds};
+use crate::locale::Locale;
+use crate::types::{Status, TaskError, TaskSort};
+use crate::utils::get_tarea_dir;
+use colored::*;
+use serde::{Deserialize, Deserializer};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::{env, fmt, fs, io};
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Where the task database lives. A leading `~` is the home directory.
+    pub database: Option<PathBuf>,
+    /// Pager for long output when `$PAGER` isn't set; empty turns paging
+    /// off.
+    pub pager: String,
+    /// Ask before deleting tasks, changing several at once or purging the
+    /// archive. `false` is like always passing `--yes`.
+    pub confirm: bool,
+    /// Language of due-date phrases and relative times, e.g. `es`. Takes
+    /// priority over `LANG` and the other locale variables.
+    #[serde(deserialize_with = "deserialize_some_parsed")]
+    pub language: Option<Locale>,
+    pub list: ListDefaults,
+    pub display: DisplayOptions,
+    pub due_soon: DueSoonThresholds,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            database: None,
+            pager: "less -FRX".to_string(),
+            confirm: true,
+            language: None,
+            list: ListDefaults::default(),
+            display: DisplayOptions::default(),
+            due_soon: DueSoonThresholds::default(),
+        }
+    }
+}
+
+/// What a listing shows when the command line doesn't say.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ListDefaults {
+    #[serde(deserialize_with = "deserialize_parsed")]
+    pub status: ListStatus,
+    #[serde(deserialize_with = "deserialize_parsed")]
+    pub sort: TaskSort,
+}
+
+/// The tasks a bare `tarea` lists: those with one status, or all of them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ListStatus {
+    All,
+    Only(Status),
+}
+
+impl Default for ListStatus {
+    fn default() -> Self {
+        ListStatus::Only(Status::Pending)
+    }
+}
+
+impl FromStr for ListStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "all" => Ok(ListStatus::All),
+            _ => s.parse().map(ListStatus::Only),
+        }
+    }
+}
+
+impl Config {
+    pub fn load() -> Result<Config, TaskError> {
+        let path = get_tarea_dir()?.join("config.toml");
+        match fs::read_to_string(&path) {
+            Ok(raw) => {
+                for key in unknown_keys(&raw) {
+                    eprintln!(
+                        "{} unknown key '{}' in {}",
+                        "warning:".bright_yellow(),
+                        key,
+                        path.display()
+                    );
+                }
+                Config::parse(&raw).map_err(|e| {
+                    TaskError::InvalidInput(format!("Invalid config {}: {}", path.display(), e))
+                })
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn parse(raw: &str) -> Result<Config, String> {
+        let config: Config = toml::from_str(raw).map_err(|e| e.to_string())?;
+        config.display.validate()?;
+        Ok(config)
+    }
+
+    /// The database file, `~/.tarea/tasks.db` unless `database` is set.
+    pub fn db_path(&self) -> Result<PathBuf, TaskError> {
+        match &self.database {
+            Some(path) => expand_home(path),
+            None => get_db_path(),
+        }
+    }
+}
+
+fn expand_home(path: &Path) -> Result<PathBuf, TaskError> {
+    let Ok(rest) = path.strip_prefix("~") else {
+        return Ok(path.to_path_buf());
+    };
+    let home = env::var("HOME").map_err(|_| {
+        TaskError::Io(io::Error::new(
+            io::ErrorKind::NotFound,
+            "HOME environment variable not found",
+        ))
+    })?;
+    Ok(PathBuf::from(home).join(rest))
+}
+
+/// Read a value the way the matching command-line flag does.
+fn deserialize_parsed<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    let raw = String::deserialize(deserializer)?;
+    raw.parse().map_err(serde::de::Error::custom)
+}
+
+fn deserialize_some_parsed<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    deserialize_parsed(deserializer).map(Some)
+}
+
+/// Every key the file may set, as dotted paths.
+fn known_keys() -> Vec<String> {
+    let mut keys: Vec<String> = [
+        "database",
+        "pager",
+        "confirm",
+        "language",
+        "list.status",
+        "list.sort",
+        "display.wrap_column",
+        "display.short_id_length",
+        "display.name_width",
+    ]
+    .map(String::from)
+    .to_vec();
+    for status in ["pending", "done", "standby"] {
+        keys.extend(["symbol", "color"].map(|key| format!("display.status.{status}.{key}")));
+    }
+    for range in ["short", "day", "week", "month", "longer"] {
+        keys.extend(["percent", "max"].map(|key| format!("due_soon.{range}.{key}")));
+    }
+    keys
+}
+
+/// Keys in `raw` that tarea doesn't read, most likely typos. Whether the
+/// file is valid at all is left to `Config::parse`.
+fn unknown_keys(raw: &str) -> Vec<String> {
+    let Ok(table) = raw.parse::<toml::Table>() else {
+        return vec![];
+    };
+    let mut unknown = vec![];
+    collect_unknown_keys(&table, "", &known_keys(), &mut unknown);
+    unknown
+}
+
+fn collect_unknown_keys(
+    table: &toml::Table,
+    prefix: &str,
+    known: &[String],
+    unknown: &mut Vec<String>,
+) {
+    for (key, value) in table {
+        let path = format!("{prefix}{key}");
+        let section = format!("{path}.");
+        match value {
+            toml::Value::Table(inner) if known.iter().any(|k| k.starts_with(&section)) => {
+                collect_unknown_keys(inner, &section, known, unknown)
+            }
+            _ if known.contains(&path) => {}
+            _ => unknown.push(path),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_parse_due_soon_thresholds() {
+        let config = Config::parse(
+            "[due_soon.week]\n\
+             percent = 40\n\
+             max = \"12h\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(config.due_soon.week.percent, 40.0);
+        assert_eq!(config.due_soon.week.max, Duration::hours(12));
+        assert_eq!(config.due_soon.day, DueSoonThresholds::default().day);
+
+        assert!(Config::parse("[due_soon.week]\nmax = \"soon\"\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_list_and_display_settings() {
+        let config = Config::parse(
+            "confirm = false\n\
+             [list]\n\
+             status = \"all\"\n\
+             sort = \"due,-name\"\n\
+             [display]\n\
+             short_id_length = 6\n\
+             [display.status.done]\n\
+             symbol = \"✓\"\n",
+        )
+        .unwrap();
+
+        assert!(!config.confirm);
+        assert_eq!(config.language, None);
+        assert_eq!(config.pager, "less -FRX");
+        assert_eq!(config.list.status, ListStatus::All);
+        assert_eq!(config.list.sort, "due,-name".parse().unwrap());
+        assert_eq!(config.display.short_id_length, 6);
+        assert_eq!(config.display.wrap_column, 80);
+        assert_eq!(config.display.status.done.symbol.as_deref(), Some("✓"));
+        assert_eq!(config.display.status.done.color, None);
+
+        assert_eq!(
+            Config::parse("language = \"es_ES\"\n").unwrap().language,
+            Some(Locale::Es)
+        );
+        assert!(Config::parse("language = \"fr\"\n").is_err());
+        assert!(Config::parse("[list]\nstatus = \"later\"\n").is_err());
+        assert!(Config::parse("[display.status.done]\ncolor = \"mauve\"\n").is_err());
+        assert!(Config::parse("[display]\nname_width = 2\n").is_err());
+    }
+
+    #[test]
+    fn test_unknown_keys_are_reported() {
+        let raw = "pagr = \"more\"\n\
+                   [list]\n\
+                   sort = \"name\"\n\
+                   filter = \"all\"\n\
+                   [display.status.urgent]\n\
+                   symbol = \"!\"\n\
+                   [due_soon.week]\n\
+                   percent = 40\n\
+                   max = \"1d\"\n";
+
+        assert_eq!(
+            unknown_keys(raw),
+            ["display.status.urgent", "list.filter", "pagr"]
+        );
+        assert!(unknown_keys("database = \"~/tasks.db\"\n").is_empty());
+    }
+
+    #[test]
+    fn test_db_path_expands_home() {
+        let config = Config {
+            database: Some(PathBuf::from("/srv/tasks.db")),
+            ..Config::default()
+        };
+        assert_eq!(config.db_path().unwrap(), PathBuf::from("/srv/tasks.db"));
+
+        let home = PathBuf::from(env::var("HOME").unwrap());
+        let path = expand_home(Path::new("~/work/tasks.db")).unwrap();
+        assert_eq!(path, home.join("work/tasks.db"));
+    }
+}
@@ -1,43 +1,166 @@
-use crate::types::{Status, StatusFilter, Task, TaskError};
-use crate::utils::validate_task_name;
-use chrono::{DateTime, NaiveDateTime, Utc};
-use rusqlite::{Connection, Result as SqlResult};
+use crate::migrations::migrate;
+use crate::types::{
+    ArchivedTask, DuePrecision, EventKind, Priority, Recurrence, Status, Task, TaskError,
+    TaskEvent, TaskFilter,
+};
+use crate::utils::{validate_tag_name, validate_task_name};
+use chrono::{DateTime, Duration, NaiveDateTime, SubsecRound, Utc};
+use rusqlite::{Connection, OptionalExtension, Result as SqlResult};
+use std::cell::Cell;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::{env, fs};
 
+const TASK_COLUMNS: &str = "id, date, name, description, status, due_date, completed_at, \
+    (SELECT group_concat(tg.name, ' ') FROM task_tags tt \
+     JOIN tags tg ON tg.id = tt.tag_id WHERE tt.task_id = t.id), due_horizon, due_precision, \
+    recurrence, priority";
+
+// Values of `task_events.undone`: part of the current state, reverted but
+// still redoable, or reverted and superseded by a newer change.
+const UNDO_ACTIVE: i64 = 0;
+const UNDO_UNDONE: i64 = 1;
+const UNDO_DISCARDED: i64 = 2;
+
+/// Steps recording any of these can't be reverted, so undo passes over them.
+const IRREVERSIBLE: [EventKind; 2] = [EventKind::Purged, EventKind::Moved];
+
 pub struct TaskManager {
     conn: Connection,
+    /// Undo step that events are currently being recorded into.
+    batch: Cell<Option<i64>>,
 }
 
 impl TaskManager {
-    pub fn new() -> Result<Self, TaskError> {
-        let conn = init_db()?;
-        Ok(TaskManager { conn })
+    /// Open the database at `path`, creating it and its directory if
+    /// needed.
+    pub fn open(path: &Path) -> Result<Self, TaskError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    /// Wrap an already-open connection, migrating it to the current schema.
+    pub fn from_connection(mut conn: Connection) -> Result<Self, TaskError> {
+        migrate(&mut conn)?;
+        Ok(TaskManager {
+            conn,
+            batch: Cell::new(None),
+        })
+    }
+
+    /// Run `f` in a single transaction whose recorded events form one undo
+    /// step. Nested calls join the outer step. Starting a new step drops
+    /// whatever could still be redone.
+    pub fn batch<T>(&self, f: impl FnOnce() -> Result<T, TaskError>) -> Result<T, TaskError> {
+        if self.batch.get().is_some() {
+            return f();
+        }
+
+        let tx = self.conn.unchecked_transaction()?;
+        let batch: i64 = tx.query_row(
+            "SELECT COALESCE(MAX(batch), 0) + 1 FROM task_events",
+            [],
+            |row| row.get(0),
+        )?;
+        tx.execute(
+            "UPDATE task_events SET undone = ?1 WHERE undone = ?2",
+            [UNDO_DISCARDED, UNDO_UNDONE],
+        )?;
+
+        self.batch.set(Some(batch));
+        let result = f();
+        self.batch.set(None);
+
+        let value = result?;
+        tx.commit()?;
+        Ok(value)
     }
 
     pub fn add_task(&self, task: Task) -> Result<(), TaskError> {
         let due_date_str = task
             .due_date
-            .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+            .map(|dt| format_db_datetime(task.due_precision.normalize(dt)))
             .unwrap_or_default();
+        let completed_at_str = task.completed_at.map(format_db_datetime);
 
-        self.conn.execute(
-            "INSERT INTO tasks (id, date, name, description, status, due_date) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-            [
-                &task.id,
-                &task.date,
-                &task.name,
-                &task.description,
-                &task.status.to_string(),
-                &due_date_str,
-            ],
-        )?;
+        self.batch(|| {
+            self.conn.execute(
+                "INSERT INTO tasks
+                (id, date, name, description, status, due_date, completed_at, due_horizon,
+                 due_precision, recurrence, priority)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                rusqlite::params![
+                    &task.id,
+                    &task.date,
+                    &task.name,
+                    &task.description,
+                    &task.status.to_string(),
+                    &due_date_str,
+                    &completed_at_str,
+                    task.due_horizon.map(|h| h.num_seconds()),
+                    task.due_date.map(|_| task.due_precision.to_string()),
+                    task.recurrence.map(|r| r.to_string()),
+                    task.priority.map(|p| p.to_string()),
+                ],
+            )?;
+            self.insert_tags(&task.id, &task.tags)?;
+            self.record_event(&task.id, EventKind::Added, None, Some(&task.name))
+        })
+    }
+
+    /// Replace the tags of task `id` with `tags`.
+    pub fn set_tags(&self, id: &str, tags: &[String]) -> Result<(), TaskError> {
+        let old_tags = self.tags_of(id)?;
+
+        self.batch(|| {
+            self.write_tags(id, tags)?;
+            let new_tags = self.tags_of(id)?;
+            if new_tags != old_tags {
+                self.record_event(
+                    id,
+                    EventKind::Tags,
+                    old_tags.as_deref(),
+                    new_tags.as_deref(),
+                )?;
+            }
+            Ok(())
+        })
+    }
+
+    fn write_tags(&self, id: &str, tags: &[String]) -> Result<(), TaskError> {
+        self.conn
+            .execute("DELETE FROM task_tags WHERE task_id = ?1", [id])?;
+        self.insert_tags(id, tags)
+    }
+
+    fn tags_of(&self, id: &str) -> Result<Option<String>, TaskError> {
+        Ok(self.conn.query_row(
+            "SELECT group_concat(name, ' ') FROM
+             (SELECT tg.name FROM task_tags tt JOIN tags tg ON tg.id = tt.tag_id
+              WHERE tt.task_id = ?1 ORDER BY tg.name)",
+            [id],
+            |row| row.get(0),
+        )?)
+    }
+
+    fn insert_tags(&self, id: &str, tags: &[String]) -> Result<(), TaskError> {
+        for tag in tags {
+            validate_tag_name(tag)?;
+            self.conn
+                .execute("INSERT OR IGNORE INTO tags (name) VALUES (?1)", [tag])?;
+            self.conn.execute(
+                "INSERT OR IGNORE INTO task_tags (task_id, tag_id)
+                 SELECT ?1, id FROM tags WHERE name = ?2",
+                [id, tag],
+            )?;
+        }
         Ok(())
     }
 
-    pub fn list_tasks(&self, filter: StatusFilter) -> Result<Vec<Task>, TaskError> {
-        let (sql, status_strings) = build_task_query(filter);
+    pub fn list_tasks(&self, filter: impl Into<TaskFilter>) -> Result<Vec<Task>, TaskError> {
+        let (sql, status_strings) = build_task_query(filter.into());
         let mut statement = self.conn.prepare(&sql)?;
 
         let map_row_to_task = |row: &rusqlite::Row| self.row_to_task(row);
@@ -66,9 +189,9 @@ impl TaskManager {
         match matching_ids.len() {
             0 => Ok(None),
             1 => {
-                let mut stmt = self.conn.prepare(
-                    "SELECT id, date, name, description, status, due_date FROM tasks WHERE id = ?1",
-                )?;
+                let mut stmt = self
+                    .conn
+                    .prepare(&format!("SELECT {TASK_COLUMNS} FROM tasks t WHERE id = ?1"))?;
                 let mut rows = stmt.query_map([&matching_ids[0]], |row| self.row_to_task(row))?;
 
                 if let Some(task_result) = rows.next() {
@@ -77,22 +200,138 @@ impl TaskManager {
                     Ok(None)
                 }
             }
-            _ => Err(TaskError::InvalidId(format!(
-                "Ambiguous ID '{}', matches: {}",
-                short_id,
-                matching_ids
-                    .iter()
-                    .map(|id| &id[..8.min(id.len())])
-                    .collect::<Vec<_>>()
-                    .join(", ")
-            ))),
+            _ => Err(ambiguous_id_error(short_id, &matching_ids)),
         }
     }
 
+    /// Soft-delete: move the task into `archived_tasks`. Its tags stay
+    /// attached so a later restore brings them back.
     pub fn delete_task_by_id(&self, id: &str) -> Result<bool, TaskError> {
+        let name = self.task_field(id, "name")?;
+
+        self.batch(|| {
+            let deleted = self.archive_row(id)?;
+            if deleted {
+                self.record_event(id, EventKind::Deleted, name.as_deref(), None)?;
+            }
+            Ok(deleted)
+        })
+    }
+
+    fn archive_row(&self, id: &str) -> Result<bool, TaskError> {
+        self.conn.execute(
+            "INSERT INTO archived_tasks
+                (id, date, name, description, status, due_date, completed_at, due_horizon,
+                 due_precision, recurrence, priority, deleted_at)
+             SELECT id, date, name, description, status, due_date, completed_at, due_horizon,
+                 due_precision, recurrence, priority, ?2
+             FROM tasks WHERE id = ?1",
+            [id, &format_db_datetime(Utc::now())],
+        )?;
         Ok(self.conn.execute("DELETE FROM tasks WHERE id = ?1", [id])? > 0)
     }
 
+    fn unarchive_row(&self, id: &str) -> Result<bool, TaskError> {
+        self.conn.execute(
+            "INSERT INTO tasks
+                (id, date, name, description, status, due_date, completed_at, due_horizon,
+                 due_precision, recurrence, priority)
+             SELECT id, date, name, description, status, due_date, completed_at, due_horizon,
+                 due_precision, recurrence, priority
+             FROM archived_tasks WHERE id = ?1",
+            [id],
+        )?;
+        Ok(self
+            .conn
+            .execute("DELETE FROM archived_tasks WHERE id = ?1", [id])?
+            > 0)
+    }
+
+    /// Archived tasks, most recently deleted first.
+    pub fn list_archived(&self) -> Result<Vec<ArchivedTask>, TaskError> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {TASK_COLUMNS}, deleted_at FROM archived_tasks t ORDER BY deleted_at DESC"
+        ))?;
+        let rows = stmt.query_map([], |row| {
+            let deleted_at: String = row.get(12)?;
+            Ok(ArchivedTask {
+                task: self.row_to_task(row)?,
+                deleted_at: parse_db_datetime(&deleted_at).unwrap_or_else(Utc::now),
+            })
+        })?;
+
+        let mut archived = Vec::new();
+        for row in rows {
+            archived.push(row?);
+        }
+        Ok(archived)
+    }
+
+    /// Move an archived task back into `tasks`. Returns the restored task,
+    /// or `None` if no archived task matches `short_id`.
+    pub fn restore_task(&self, short_id: &str) -> Result<Option<Task>, TaskError> {
+        let matching_ids = self.find_matching_archived_ids(short_id)?;
+        let id = match matching_ids.as_slice() {
+            [] => return Ok(None),
+            [id] => id,
+            _ => return Err(ambiguous_id_error(short_id, &matching_ids)),
+        };
+
+        if !self.find_matching_ids(id)?.is_empty() {
+            return Err(TaskError::InvalidId(format!(
+                "Cannot restore '{}': a task with that ID already exists",
+                short_id
+            )));
+        }
+
+        self.batch(|| {
+            self.unarchive_row(id)?;
+            let name = self.task_field(id, "name")?;
+            self.record_event(id, EventKind::Restored, None, name.as_deref())
+        })?;
+
+        self.find_task_by_id(id)
+    }
+
+    /// Permanently remove archived tasks deleted at or before `cutoff`
+    /// (every archived task when `None`). Returns how many were purged.
+    pub fn purge_archived(&self, cutoff: Option<DateTime<Utc>>) -> Result<usize, TaskError> {
+        let cutoff = format_db_datetime(cutoff.unwrap_or_else(Utc::now));
+
+        self.batch(|| {
+            let purged: Vec<(String, String)> = {
+                let mut stmt = self
+                    .conn
+                    .prepare("SELECT id, name FROM archived_tasks WHERE deleted_at <= ?1")?;
+                let rows = stmt.query_map([&cutoff], |row| Ok((row.get(0)?, row.get(1)?)))?;
+                rows.collect::<SqlResult<_>>()?
+            };
+
+            for (id, name) in &purged {
+                self.conn
+                    .execute("DELETE FROM task_tags WHERE task_id = ?1", [id])?;
+                self.conn
+                    .execute("DELETE FROM archived_tasks WHERE id = ?1", [id])?;
+                self.record_event(id, EventKind::Purged, Some(name), None)?;
+            }
+            Ok(purged.len())
+        })
+    }
+
+    /// Remove task `id` because it now lives in `profile`. Like a purge,
+    /// this cannot be undone.
+    pub fn move_out(&self, id: &str, profile: &str) -> Result<bool, TaskError> {
+        self.batch(|| {
+            self.conn
+                .execute("DELETE FROM task_tags WHERE task_id = ?1", [id])?;
+            let removed = self.conn.execute("DELETE FROM tasks WHERE id = ?1", [id])? > 0;
+            if removed {
+                self.record_event(id, EventKind::Moved, None, Some(profile))?;
+            }
+            Ok(removed)
+        })
+    }
+
     pub fn update_task_status(
         &self,
         short_id: &str,
@@ -103,47 +342,494 @@ impl TaskManager {
         match matching_ids.len() {
             0 => Ok(false),
             1 => {
-                let updated = self.conn.execute(
-                    "UPDATE tasks SET status = ?1 WHERE id = ?2",
-                    [&new_status.to_string(), &matching_ids[0]],
-                )?;
-                Ok(updated > 0)
+                let id = &matching_ids[0];
+                let old_status = self.task_field(id, "status")?;
+                let old_completed_at = self.task_field(id, "completed_at")?;
+
+                // completed_at is stamped on the transition to done, kept while
+                // the task stays done and cleared when it is reopened.
+                let was_done = old_status.as_deref() == Some("done");
+                let now = Utc::now();
+                let completed_at = match new_status {
+                    Status::Done if was_done => old_completed_at,
+                    Status::Done => Some(format_db_datetime(now)),
+                    _ => None,
+                };
+
+                self.batch(|| {
+                    let updated = self.set_status(id, &new_status, completed_at)?;
+                    if updated && new_status == Status::Done && !was_done {
+                        self.repeat_task(id, now)?;
+                    }
+                    Ok(updated)
+                })
             }
-            _ => Err(TaskError::InvalidId(format!(
-                "Ambiguous ID '{}', matches: {}",
-                short_id,
-                matching_ids
-                    .iter()
-                    .map(|id| &id[..8.min(id.len())])
-                    .collect::<Vec<_>>()
-                    .join(", ")
-            ))),
+            _ => Err(ambiguous_id_error(short_id, &matching_ids)),
         }
     }
 
-    pub fn update_name(&self, id: &str, name: &str) -> Result<bool, TaskError> {
-        validate_task_name(name)?;
+    /// Add the next occurrence of task `id` if it recurs. The done one stops
+    /// recurring, so reopening and finishing it again adds no second copy.
+    fn repeat_task(&self, id: &str, done_at: DateTime<Utc>) -> Result<(), TaskError> {
+        let Some(next) = self
+            .find_task_by_id(id)?
+            .and_then(|task| task.next_occurrence(done_at))
+        else {
+            return Ok(());
+        };
+        self.add_task(next)?;
+        self.update_recurrence(id, None)?;
+        Ok(())
+    }
+
+    /// Write status and completion time of task `id`, recording whichever
+    /// of the two changed.
+    fn set_status(
+        &self,
+        id: &str,
+        new_status: &Status,
+        completed_at: Option<String>,
+    ) -> Result<bool, TaskError> {
+        let old_status = self.task_field(id, "status")?;
+        let old_completed_at = self.task_field(id, "completed_at")?;
+
+        self.batch(|| {
+            let new_status = new_status.to_string();
+            let updated = self.write_field(id, "status", Some(&new_status))?;
+            self.write_field(id, "completed_at", completed_at.as_deref())?;
+
+            if updated && old_status.as_deref() != Some(&new_status) {
+                self.record_event(
+                    id,
+                    EventKind::Status,
+                    old_status.as_deref(),
+                    Some(&new_status),
+                )?;
+            }
+            if updated && old_completed_at != completed_at {
+                self.record_event(
+                    id,
+                    EventKind::Completed,
+                    old_completed_at.as_deref(),
+                    completed_at.as_deref(),
+                )?;
+            }
+            Ok(updated)
+        })
+    }
+
+    /// Whether a live or archived task has exactly this id.
+    pub fn has_task_id(&self, id: &str) -> Result<bool, TaskError> {
+        Ok(self.conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM tasks WHERE id = ?1)
+                 OR EXISTS (SELECT 1 FROM archived_tasks WHERE id = ?1)",
+            [id],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// The id of a task named exactly `name`, live before archived.
+    pub fn task_id_by_name(&self, name: &str) -> Result<Option<String>, TaskError> {
         Ok(self
             .conn
-            .execute("UPDATE tasks SET name = ?1 WHERE id = ?2", [name, id])?
-            > 0)
+            .query_row(
+                "SELECT id FROM (
+                     SELECT id, 0 AS archived FROM tasks WHERE name = ?1
+                     UNION ALL
+                     SELECT id, 1 AS archived FROM archived_tasks WHERE name = ?1
+                 ) ORDER BY archived LIMIT 1",
+                [name],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    /// Replace every field of the existing task `task.id` with those of
+    /// `task`, restoring it first if it is archived. Changes are recorded
+    /// like individual edits so they can be undone.
+    pub fn overwrite_task(&self, task: &Task) -> Result<(), TaskError> {
+        self.batch(|| {
+            if self.task_field(&task.id, "id")?.is_none() {
+                self.restore_task(&task.id)?;
+            }
+            self.update_name(&task.id, &task.name)?;
+            self.update_description(&task.id, &task.description)?;
+            self.update_due(&task.id, task.due_date.map(|due| (due, task.due_precision)))?;
+            self.write_due_horizon(&task.id, task.due_horizon)?;
+            self.set_status(
+                &task.id,
+                &task.status,
+                task.completed_at.map(format_db_datetime),
+            )?;
+            self.set_tags(&task.id, &task.tags)?;
+            self.update_recurrence(&task.id, task.recurrence)?;
+            self.update_priority(&task.id, task.priority)?;
+            self.write_field(&task.id, "date", Some(&task.date))?;
+            Ok(())
+        })
+    }
+
+    pub fn update_name(&self, id: &str, name: &str) -> Result<bool, TaskError> {
+        validate_task_name(name)?;
+        self.update_field(id, "name", EventKind::Name, name)
     }
 
     pub fn update_description(&self, id: &str, desc: &str) -> Result<bool, TaskError> {
+        self.update_field(id, "description", EventKind::Description, desc)
+    }
+
+    pub fn update_recurrence(
+        &self,
+        id: &str,
+        recurrence: Option<Recurrence>,
+    ) -> Result<bool, TaskError> {
+        let value = recurrence.map(|r| r.to_string()).unwrap_or_default();
+        self.update_field(id, "recurrence", EventKind::Recurrence, &value)
+    }
+
+    pub fn update_priority(&self, id: &str, priority: Option<Priority>) -> Result<bool, TaskError> {
+        let value = priority.map(|p| p.to_string()).unwrap_or_default();
+        self.update_field(id, "priority", EventKind::Priority, &value)
+    }
+
+    /// Set the due date of task `id` and whether it names a day or a time.
+    /// A new one has its horizon measured from now.
+    pub fn update_due(
+        &self,
+        id: &str,
+        due: Option<(DateTime<Utc>, DuePrecision)>,
+    ) -> Result<bool, TaskError> {
+        let due =
+            due.map(|(due, precision)| (precision.normalize(due).trunc_subsecs(0), precision));
+        let old_due = self.task_due(id)?;
+        self.batch(|| {
+            let updated = self.write_due(id, due)?;
+            if updated && old_due != due {
+                if old_due.map(|(d, _)| d) != due.map(|(d, _)| d) {
+                    self.write_due_horizon(id, due.map(|(due, _)| due - Utc::now()))?;
+                }
+                self.record_event(
+                    id,
+                    EventKind::Due,
+                    old_due.map(format_due_value).as_deref(),
+                    due.map(format_due_value).as_deref(),
+                )?;
+            }
+            Ok(updated)
+        })
+    }
+
+    fn task_due(&self, id: &str) -> Result<Option<(DateTime<Utc>, DuePrecision)>, TaskError> {
+        let Some(due) = self
+            .task_field(id, "due_date")?
+            .as_deref()
+            .and_then(parse_db_datetime)
+        else {
+            return Ok(None);
+        };
+        let precision = self
+            .task_field(id, "due_precision")?
+            .and_then(|p| p.parse().ok())
+            .unwrap_or_else(|| DuePrecision::guess(due));
+        Ok(Some((due, precision)))
+    }
+
+    /// Not journaled: the due date of task `id` in its stored form.
+    fn write_due(
+        &self,
+        id: &str,
+        due: Option<(DateTime<Utc>, DuePrecision)>,
+    ) -> Result<bool, TaskError> {
+        let date = due
+            .map(|(due, precision)| format_db_datetime(precision.normalize(due)))
+            .unwrap_or_default();
+        let updated = self.write_field(id, "due_date", Some(&date))?;
+        self.write_field(
+            id,
+            "due_precision",
+            due.map(|(_, precision)| precision.to_string()).as_deref(),
+        )?;
+        Ok(updated)
+    }
+
+    /// Not journaled: a task whose due date is undone falls back to the
+    /// horizon from its creation, see `row_to_task`.
+    fn write_due_horizon(&self, id: &str, horizon: Option<Duration>) -> Result<(), TaskError> {
+        self.conn.execute(
+            "UPDATE tasks SET due_horizon = ?1 WHERE id = ?2",
+            rusqlite::params![horizon.map(|h| h.num_seconds()), id],
+        )?;
+        Ok(())
+    }
+
+    /// Set a single text column of task `id`, recording the change in
+    /// `task_events` when the value actually differs.
+    fn update_field(
+        &self,
+        id: &str,
+        column: &str,
+        kind: EventKind,
+        value: &str,
+    ) -> Result<bool, TaskError> {
+        let old_value = self.task_field(id, column)?;
+
+        self.batch(|| {
+            let updated = self.write_field(id, column, Some(value))?;
+
+            let old_value = old_value.filter(|v| !v.is_empty());
+            let new_value = Some(value).filter(|v| !v.is_empty());
+            if updated && old_value.as_deref() != new_value {
+                self.record_event(id, kind, old_value.as_deref(), new_value)?;
+            }
+            Ok(updated)
+        })
+    }
+
+    fn write_field(&self, id: &str, column: &str, value: Option<&str>) -> Result<bool, TaskError> {
         Ok(self.conn.execute(
-            "UPDATE tasks SET description = ?1 WHERE id = ?2",
-            [desc, id],
+            &format!("UPDATE tasks SET {column} = ?1 WHERE id = ?2"),
+            rusqlite::params![value, id],
         )? > 0)
     }
 
-    pub fn update_due(&self, id: &str, due: Option<DateTime<Utc>>) -> Result<bool, TaskError> {
-        let s = due
-            .map(|d| d.format("%Y-%m-%d %H:%M:%S").to_string())
-            .unwrap_or_default();
-        Ok(self
+    fn task_field(&self, id: &str, column: &str) -> Result<Option<String>, TaskError> {
+        let value = self
             .conn
-            .execute("UPDATE tasks SET due_date = ?1 WHERE id = ?2", [&s, id])?
-            > 0)
+            .query_row(
+                &format!("SELECT {column} FROM tasks WHERE id = ?1"),
+                [id],
+                |row| row.get::<_, Option<String>>(0),
+            )
+            .optional()?;
+        Ok(value.flatten())
+    }
+
+    fn record_event(
+        &self,
+        task_id: &str,
+        kind: EventKind,
+        old_value: Option<&str>,
+        new_value: Option<&str>,
+    ) -> Result<(), TaskError> {
+        self.conn.execute(
+            "INSERT INTO task_events (task_id, kind, old_value, new_value, at, batch)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![
+                task_id,
+                kind.to_string(),
+                old_value,
+                new_value,
+                format_db_datetime(Utc::now()),
+                self.batch.get(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Revert the most recent step that has not been undone yet. Returns its
+    /// events, or an empty list when there is nothing to undo, along with
+    /// those of the newer steps that can't be reverted and were passed over.
+    pub fn undo(&self) -> Result<(Vec<TaskEvent>, Vec<TaskEvent>), TaskError> {
+        let oldest_undone = || -> Result<i64, TaskError> {
+            Ok(self.conn.query_row(
+                "SELECT COALESCE(MIN(batch), 0) FROM task_events WHERE undone = ?1",
+                [UNDO_UNDONE],
+                |row| row.get(0),
+            )?)
+        };
+
+        let before = oldest_undone()?;
+        let events = self.replay(
+            &format!(
+                "SELECT MAX(batch) FROM task_events
+                 WHERE undone = ?1 AND batch NOT IN ({})",
+                irreversible_batches()
+            ),
+            UNDO_ACTIVE,
+            true,
+        )?;
+        let after = oldest_undone()?;
+
+        // Irreversible steps only ever follow the ones still undone, so
+        // those passed over lie between the step just undone (if any) and
+        // the one undone before it.
+        let upper = if before == 0 { i64::MAX } else { before };
+        let skipped = self.query_events(
+            &format!(
+                "e.batch > ?1 AND e.batch < {upper} AND e.batch IN ({})",
+                irreversible_batches()
+            ),
+            if events.is_empty() { 0 } else { after },
+        )?;
+        Ok((events, skipped))
+    }
+
+    /// Re-apply the oldest step among those undone since the last change.
+    pub fn redo(&self) -> Result<Vec<TaskEvent>, TaskError> {
+        self.replay(
+            "SELECT MIN(batch) FROM task_events WHERE undone = ?1",
+            UNDO_UNDONE,
+            false,
+        )
+    }
+
+    /// Undo or redo the step `pick_batch` selects. Steps that only touch
+    /// tasks which are no longer here change nothing and are passed over.
+    fn replay(
+        &self,
+        pick_batch: &str,
+        state: i64,
+        backwards: bool,
+    ) -> Result<Vec<TaskEvent>, TaskError> {
+        let tx = self.conn.unchecked_transaction()?;
+        let new_state = if backwards { UNDO_UNDONE } else { UNDO_ACTIVE };
+
+        loop {
+            let batch: Option<i64> = tx.query_row(pick_batch, [state], |row| row.get(0))?;
+            let Some(batch) = batch else {
+                tx.commit()?;
+                return Ok(Vec::new());
+            };
+
+            let mut events = Vec::new();
+            for event in self.query_events("e.batch = ?1", batch)? {
+                if self.has_task_id(&event.task_id)? {
+                    events.push(event);
+                }
+            }
+            if backwards {
+                events.reverse();
+            }
+
+            for event in &events {
+                self.apply_event(event, backwards)?;
+            }
+
+            tx.execute(
+                "UPDATE task_events SET undone = ?1 WHERE batch = ?2",
+                [new_state, batch],
+            )?;
+            if events.is_empty() {
+                continue;
+            }
+            tx.commit()?;
+
+            if backwards {
+                events.reverse();
+            }
+            return Ok(events);
+        }
+    }
+
+    /// Put a task back into the state before (`backwards`) or after one
+    /// recorded event, without recording anything itself.
+    fn apply_event(&self, event: &TaskEvent, backwards: bool) -> Result<(), TaskError> {
+        let id = event.task_id.as_str();
+        let to = if backwards {
+            event.old_value.as_deref()
+        } else {
+            event.new_value.as_deref()
+        };
+        let or_empty = |v: Option<&str>| Some(v.unwrap_or_default().to_string());
+
+        match event.kind {
+            EventKind::Name => self.write_field(id, "name", to)?,
+            EventKind::Status => self.write_field(id, "status", to)?,
+            EventKind::Completed => self.write_field(id, "completed_at", to)?,
+            EventKind::Description => {
+                self.write_field(id, "description", or_empty(to).as_deref())?
+            }
+            EventKind::Recurrence => self.write_field(id, "recurrence", to)?,
+            EventKind::Priority => self.write_field(id, "priority", to)?,
+            EventKind::Due => {
+                self.write_due_horizon(id, None)?;
+                self.write_due(id, to.and_then(parse_due_value))?
+            }
+            EventKind::Tags => {
+                let tags: Vec<String> = to
+                    .map(|t| t.split(' ').map(String::from).collect())
+                    .unwrap_or_default();
+                self.write_tags(id, &tags)?;
+                true
+            }
+            // Undoing an add or a restore archives the task again, so nothing
+            // is lost if the user changes their mind once more.
+            EventKind::Added | EventKind::Restored if backwards => self.archive_row(id)?,
+            EventKind::Deleted if !backwards => self.archive_row(id)?,
+            EventKind::Added | EventKind::Restored | EventKind::Deleted => {
+                self.unarchive_row(id)?
+            }
+            EventKind::Purged | EventKind::Moved => false,
+        };
+        Ok(())
+    }
+
+    /// Recorded events, oldest first; only those of `task_id` when given.
+    pub fn list_events(&self, task_id: Option<&str>) -> Result<Vec<TaskEvent>, TaskError> {
+        self.query_events("?1 IS NULL OR e.task_id = ?1", task_id)
+    }
+
+    fn query_events(
+        &self,
+        condition: &str,
+        param: impl rusqlite::ToSql,
+    ) -> Result<Vec<TaskEvent>, TaskError> {
+        // Name a task by its live row, its archived row, or failing that
+        // (purged tasks) by the last name an event remembers.
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT e.task_id, e.kind, e.old_value, e.new_value, e.at, e.undone,
+                COALESCE(
+                    (SELECT name FROM tasks WHERE id = e.task_id),
+                    (SELECT name FROM archived_tasks WHERE id = e.task_id),
+                    (SELECT COALESCE(n.new_value, n.old_value) FROM task_events n
+                     WHERE n.task_id = e.task_id
+                       AND n.kind IN ('added', 'name', 'deleted', 'restored', 'purged')
+                     ORDER BY n.id DESC LIMIT 1)
+                )
+             FROM task_events e
+             WHERE {condition}
+             ORDER BY e.id"
+        ))?;
+
+        let rows = stmt.query_map([param], |row| {
+            let kind: String = row.get(1)?;
+            let at: String = row.get(4)?;
+            Ok(TaskEvent {
+                task_id: row.get(0)?,
+                kind: kind.parse().unwrap_or(EventKind::Name),
+                old_value: row.get(2)?,
+                new_value: row.get(3)?,
+                at: parse_db_datetime(&at).unwrap_or_else(Utc::now),
+                undone: row.get::<_, i64>(5)? != UNDO_ACTIVE,
+                task_name: row.get(6)?,
+            })
+        })?;
+
+        let mut events = Vec::new();
+        for event in rows {
+            events.push(event?);
+        }
+        Ok(events)
+    }
+
+    /// Full ID of the task with recorded events whose ID starts with
+    /// `short_id`, including archived and purged tasks.
+    pub fn find_event_task_id(&self, short_id: &str) -> Result<Option<String>, TaskError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT DISTINCT task_id FROM task_events WHERE task_id LIKE ?1 || '%'")?;
+        let rows = stmt.query_map([short_id], |row| row.get::<_, String>(0))?;
+
+        let mut ids = Vec::new();
+        for id in rows {
+            ids.push(id?);
+        }
+
+        match ids.len() {
+            0 => Ok(None),
+            1 => Ok(ids.pop()),
+            _ => Err(ambiguous_id_error(short_id, &ids)),
+        }
     }
 
     fn find_matching_ids(&self, short_id: &str) -> Result<Vec<String>, TaskError> {
@@ -161,32 +847,129 @@ impl TaskManager {
         Ok(ids)
     }
 
+    fn find_matching_archived_ids(&self, short_id: &str) -> Result<Vec<String>, TaskError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id FROM archived_tasks WHERE id LIKE ?1 || '%'")?;
+        let mut ids = Vec::new();
+
+        let rows = stmt.query_map([short_id], |row| row.get::<_, String>(0))?;
+
+        for id_result in rows {
+            ids.push(id_result?);
+        }
+
+        Ok(ids)
+    }
+
     fn row_to_task(&self, row: &rusqlite::Row) -> SqlResult<Task> {
         let status_str: String = row.get(4)?;
         let status = std::str::FromStr::from_str(&status_str).unwrap_or(Status::Pending);
-        let due_date_str: String = row.get(5)?;
+        let due_date_str: String = row.get::<_, Option<String>>(5)?.unwrap_or_default();
 
         let due_date = if due_date_str.is_empty() {
             None
         } else {
-            NaiveDateTime::parse_from_str(&due_date_str, "%Y-%m-%d %H:%M:%S")
-                .ok()
-                .map(|dt| dt.and_utc())
+            parse_db_datetime(&due_date_str)
+        };
+
+        let completed_at = row
+            .get::<_, Option<String>>(6)?
+            .as_deref()
+            .and_then(parse_db_datetime);
+
+        let mut tags: Vec<String> = row
+            .get::<_, Option<String>>(7)?
+            .map(|joined| joined.split(' ').map(String::from).collect())
+            .unwrap_or_default();
+        tags.sort();
+
+        let date: String = row.get(1)?;
+        let due_horizon = match row.get::<_, Option<i64>>(8)? {
+            Some(secs) => Some(Duration::seconds(secs)),
+            None => due_date
+                .zip(parse_db_datetime(&date))
+                .map(|(due, created)| due - created),
         };
+        let due_precision = row
+            .get::<_, Option<String>>(9)?
+            .and_then(|p| p.parse().ok())
+            .unwrap_or_default();
+        let recurrence = row
+            .get::<_, Option<String>>(10)?
+            .and_then(|r| r.parse().ok());
+        let priority = row
+            .get::<_, Option<String>>(11)?
+            .and_then(|p| p.parse().ok());
 
         Ok(Task {
             id: row.get(0)?,
-            date: row.get(1)?,
+            date,
             name: row.get(2)?,
             description: row.get(3)?,
             status,
             due_date,
+            due_horizon,
+            due_precision,
+            completed_at,
+            tags,
+            recurrence,
+            priority,
         })
     }
 }
 
-fn build_task_query(filter: StatusFilter) -> (String, Vec<String>) {
-    let mut sql = String::from("SELECT id, date, name, description, status, due_date FROM tasks");
+pub fn format_db_datetime(dt: DateTime<Utc>) -> String {
+    dt.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+pub fn parse_db_datetime(s: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S")
+        .ok()
+        .map(|dt| dt.and_utc())
+}
+
+/// A due date as `task_events` records it: the time, then `day` or `time`.
+pub fn format_due_value((due, precision): (DateTime<Utc>, DuePrecision)) -> String {
+    format!("{} {}", format_db_datetime(due), precision)
+}
+
+/// Read a due date back from `task_events`. Values from before precision
+/// was recorded get the one their time of day suggests.
+pub fn parse_due_value(s: &str) -> Option<(DateTime<Utc>, DuePrecision)> {
+    let recorded = s
+        .rsplit_once(' ')
+        .and_then(|(time, precision)| Some((time, precision.parse().ok()?)));
+    match recorded {
+        Some((time, precision)) => Some((parse_db_datetime(time)?, precision)),
+        None => parse_db_datetime(s).map(|due| (due, DuePrecision::guess(due))),
+    }
+}
+
+/// The steps that recorded an [`IRREVERSIBLE`] event, as a subquery.
+fn irreversible_batches() -> String {
+    let kinds = IRREVERSIBLE
+        .iter()
+        .map(|kind| format!("'{}'", kind))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("SELECT batch FROM task_events WHERE kind IN ({kinds})")
+}
+
+fn ambiguous_id_error(short_id: &str, matching_ids: &[String]) -> TaskError {
+    TaskError::InvalidId(format!(
+        "Ambiguous ID '{}', matches: {}",
+        short_id,
+        matching_ids
+            .iter()
+            .map(|id| id.chars().take(8).collect::<String>())
+            .collect::<Vec<_>>()
+            .join(", ")
+    ))
+}
+
+fn build_task_query(filter: TaskFilter) -> (String, Vec<String>) {
+    let mut sql = format!("SELECT {TASK_COLUMNS} FROM tasks t");
 
     let (where_clause, params) = filter.to_sql();
     if !where_clause.is_empty() {
@@ -194,7 +977,8 @@ fn build_task_query(filter: StatusFilter) -> (String, Vec<String>) {
         sql.push_str(&where_clause);
     }
 
-    sql.push_str(" ORDER BY date DESC");
+    sql.push(' ');
+    sql.push_str(&filter.sort.to_sql());
     (sql, params)
 }
 
@@ -217,24 +1001,579 @@ pub fn get_db_path() -> Result<PathBuf, TaskError> {
     Ok(get_tarea_dir()?.join("tasks.db"))
 }
 
-fn init_db() -> Result<Connection, TaskError> {
-    let db_path = get_db_path()?;
-    let conn = Connection::open(db_path)?;
-
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS tasks (
-            id TEXT PRIMARY KEY,
-            date TEXT NOT NULL,
-            name TEXT NOT NULL,
-            description TEXT,
-            status TEXT NOT NULL DEFAULT 'pending'
-        )",
-        [],
-    )?;
-
-    // Add due_date column if it doesn't exist
-    conn.execute("ALTER TABLE tasks ADD COLUMN due_date TEXT", [])
-        .or_else(|_| Ok::<usize, rusqlite::Error>(0))?;
-
-    Ok(conn)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{DueFilter, StatusFilter, TagFilter, TaskSort};
+
+    fn manager() -> TaskManager {
+        TaskManager::from_connection(Connection::open_in_memory().unwrap()).unwrap()
+    }
+
+    fn add(manager: &TaskManager, name: &str) -> Task {
+        let task = Task::new(name.to_string(), None, None).unwrap();
+        manager.add_task(task.clone()).unwrap();
+        task
+    }
+
+    fn kinds(manager: &TaskManager, id: &str) -> Vec<EventKind> {
+        manager
+            .list_events(Some(id))
+            .unwrap()
+            .iter()
+            .map(|e| e.kind)
+            .collect()
+    }
+
+    #[test]
+    fn test_events_record_old_and_new_values() {
+        let manager = manager();
+        let task = add(&manager, "Write report");
+
+        manager.update_name(&task.id, "Write final report").unwrap();
+        manager.update_task_status(&task.id, Status::Done).unwrap();
+
+        let events = manager.list_events(Some(&task.id)).unwrap();
+        assert_eq!(
+            kinds(&manager, &task.id),
+            vec![
+                EventKind::Added,
+                EventKind::Name,
+                EventKind::Status,
+                EventKind::Completed
+            ]
+        );
+        assert_eq!(events[1].old_value.as_deref(), Some("Write report"));
+        assert_eq!(events[1].new_value.as_deref(), Some("Write final report"));
+        assert_eq!(events[2].old_value.as_deref(), Some("pending"));
+        assert_eq!(events[2].new_value.as_deref(), Some("done"));
+    }
+
+    #[test]
+    fn test_events_skip_unchanged_values() {
+        let manager = manager();
+        let task = add(&manager, "Same");
+
+        manager.update_name(&task.id, "Same").unwrap();
+        manager
+            .update_task_status(&task.id, Status::Pending)
+            .unwrap();
+        manager.update_due(&task.id, None).unwrap();
+
+        assert_eq!(kinds(&manager, &task.id), vec![EventKind::Added]);
+    }
+
+    #[test]
+    fn test_delete_archives_and_restore_brings_back() {
+        let manager = manager();
+        let mut task = Task::new("Submit report".to_string(), None, None).unwrap();
+        task.tags = vec!["work".to_string()];
+        manager.add_task(task.clone()).unwrap();
+
+        assert!(manager.delete_task_by_id(&task.id).unwrap());
+        assert!(manager.find_task_by_id(&task.id).unwrap().is_none());
+        let archived = manager.list_archived().unwrap();
+        assert_eq!(archived.len(), 1);
+        assert_eq!(archived[0].task.name, "Submit report");
+        assert_eq!(archived[0].task.tags, vec!["work".to_string()]);
+
+        let restored = manager.restore_task(&task.id[..8]).unwrap().unwrap();
+        assert_eq!(restored.name, "Submit report");
+        assert_eq!(restored.tags, vec!["work".to_string()]);
+        assert!(manager.list_archived().unwrap().is_empty());
+        assert!(manager.restore_task(&task.id).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_purge_respects_the_cutoff() {
+        let manager = manager();
+        let task = add(&manager, "Old news");
+        manager.delete_task_by_id(&task.id).unwrap();
+
+        let before_delete = Utc::now() - chrono::Duration::days(1);
+        assert_eq!(manager.purge_archived(Some(before_delete)).unwrap(), 0);
+        assert_eq!(manager.list_archived().unwrap().len(), 1);
+
+        assert_eq!(manager.purge_archived(None).unwrap(), 1);
+        assert!(manager.list_archived().unwrap().is_empty());
+        assert!(manager.restore_task(&task.id).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_completed_at_follows_the_done_status() {
+        let manager = manager();
+        let task = add(&manager, "Ship it");
+        let completed_at = || {
+            manager
+                .find_task_by_id(&task.id)
+                .unwrap()
+                .unwrap()
+                .completed_at
+        };
+        assert_eq!(completed_at(), None);
+
+        manager.update_task_status(&task.id, Status::Done).unwrap();
+        assert!(completed_at().is_some());
+
+        // Marking a done task done again keeps the original time
+        manager
+            .write_field(&task.id, "completed_at", Some("2025-01-02 03:04:05"))
+            .unwrap();
+        manager.update_task_status(&task.id, Status::Done).unwrap();
+        assert_eq!(completed_at(), parse_db_datetime("2025-01-02 03:04:05"));
+
+        manager
+            .update_task_status(&task.id, Status::Standby)
+            .unwrap();
+        assert_eq!(completed_at(), None);
+    }
+
+    #[test]
+    fn test_events_survive_delete_and_purge() {
+        let manager = manager();
+        let task = add(&manager, "Short lived");
+
+        manager.delete_task_by_id(&task.id).unwrap();
+        manager.purge_archived(None).unwrap();
+
+        assert_eq!(
+            kinds(&manager, &task.id),
+            vec![EventKind::Added, EventKind::Deleted, EventKind::Purged]
+        );
+        let events = manager.list_events(None).unwrap();
+        assert_eq!(events[2].task_name.as_deref(), Some("Short lived"));
+        assert_eq!(
+            manager.find_event_task_id(&task.id[..8]).unwrap(),
+            Some(task.id.clone())
+        );
+    }
+
+    #[test]
+    fn test_undo_redo_status_change_restores_completion_time() {
+        let manager = manager();
+        let task = add(&manager, "Ship it");
+        manager.update_task_status(&task.id, Status::Done).unwrap();
+        let done = manager.find_task_by_id(&task.id).unwrap().unwrap();
+
+        manager
+            .update_task_status(&task.id, Status::Pending)
+            .unwrap();
+        manager.undo().unwrap();
+
+        let reverted = manager.find_task_by_id(&task.id).unwrap().unwrap();
+        assert_eq!(reverted.status, Status::Done);
+        assert_eq!(reverted.completed_at, done.completed_at);
+
+        manager.redo().unwrap();
+        let redone = manager.find_task_by_id(&task.id).unwrap().unwrap();
+        assert_eq!(redone.status, Status::Pending);
+        assert_eq!(redone.completed_at, None);
+    }
+
+    #[test]
+    fn test_completing_a_recurring_task_adds_the_next_one() {
+        let manager = manager();
+        let due = parse_db_datetime(&format_db_datetime(Utc::now() + chrono::Duration::hours(1)));
+        let mut task = Task::new("Water plants".to_string(), None, due).unwrap();
+        task.recurrence = Some("week".parse().unwrap());
+        manager.add_task(task.clone()).unwrap();
+
+        manager.update_task_status(&task.id, Status::Done).unwrap();
+
+        let pending = manager
+            .list_tasks(TaskFilter::from(StatusFilter::PendingOnly))
+            .unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_ne!(pending[0].id, task.id);
+        assert_eq!(
+            pending[0].due_date,
+            due.map(|d| d + chrono::Duration::weeks(1))
+        );
+        assert_eq!(pending[0].recurrence, task.recurrence);
+        let done = manager.find_task_by_id(&task.id).unwrap().unwrap();
+        assert_eq!(done.recurrence, None);
+
+        // Reopening and finishing again must not add a second copy
+        manager
+            .update_task_status(&task.id, Status::Pending)
+            .unwrap();
+        manager.update_task_status(&task.id, Status::Done).unwrap();
+        let filter = TaskFilter::from(StatusFilter::All);
+        assert_eq!(manager.list_tasks(filter).unwrap().len(), 2);
+
+        manager.undo().unwrap();
+        manager.undo().unwrap();
+        manager.undo().unwrap();
+        let restored = manager.find_task_by_id(&task.id).unwrap().unwrap();
+        assert_eq!(restored.status, Status::Pending);
+        assert_eq!(restored.recurrence, task.recurrence);
+        let filter = TaskFilter::from(StatusFilter::All);
+        assert_eq!(manager.list_tasks(filter).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_priority_survives_archiving_and_undo() {
+        let manager = manager();
+        let mut task = Task::new("Call the bank".to_string(), None, None).unwrap();
+        task.priority = "A".parse().ok();
+        manager.add_task(task.clone()).unwrap();
+
+        manager.update_priority(&task.id, "C".parse().ok()).unwrap();
+        manager.delete_task_by_id(&task.id).unwrap();
+        manager.restore_task(&task.id).unwrap();
+        let priority = || manager.find_task_by_id(&task.id).unwrap().unwrap().priority;
+        assert_eq!(priority(), "C".parse().ok());
+
+        manager.undo().unwrap();
+        manager.undo().unwrap();
+        manager.undo().unwrap();
+        assert_eq!(priority(), task.priority);
+    }
+
+    #[test]
+    fn test_undo_passes_over_a_move_out() {
+        let manager = manager();
+        let earlier = add(&manager, "Earlier");
+        let task = add(&manager, "Quarterly report");
+
+        assert!(manager.move_out(&task.id, "work").unwrap());
+
+        assert!(!manager.has_task_id(&task.id).unwrap());
+        let events = manager.list_events(Some(&task.id)).unwrap();
+        assert_eq!(events[1].kind, EventKind::Moved);
+        assert_eq!(events[1].new_value.as_deref(), Some("work"));
+        assert_eq!(events[1].task_name.as_deref(), Some("Quarterly report"));
+        assert!(!manager.move_out(&task.id, "work").unwrap());
+
+        // Undo passes over the move and the add of the moved task
+        let (undone, skipped) = manager.undo().unwrap();
+        assert_eq!(undone[0].task_id, earlier.id);
+        assert_eq!(skipped[0].kind, EventKind::Moved);
+    }
+
+    #[test]
+    fn test_undo_walks_back_multiple_steps() {
+        let manager = manager();
+        let task = add(&manager, "First");
+        manager.update_name(&task.id, "Second").unwrap();
+        manager.set_tags(&task.id, &["infra".to_string()]).unwrap();
+        manager.delete_task_by_id(&task.id).unwrap();
+
+        manager.undo().unwrap();
+        let restored = manager.find_task_by_id(&task.id).unwrap().unwrap();
+        assert_eq!(restored.tags, vec!["infra".to_string()]);
+
+        manager.undo().unwrap();
+        manager.undo().unwrap();
+        let renamed_back = manager.find_task_by_id(&task.id).unwrap().unwrap();
+        assert_eq!(renamed_back.name, "First");
+        assert!(renamed_back.tags.is_empty());
+
+        manager.undo().unwrap();
+        assert!(manager.find_task_by_id(&task.id).unwrap().is_none());
+        assert!(manager.undo().unwrap().0.is_empty());
+
+        manager.redo().unwrap();
+        assert!(manager.find_task_by_id(&task.id).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_new_change_discards_redo() {
+        let manager = manager();
+        let task = add(&manager, "Draft");
+        manager.update_name(&task.id, "Final").unwrap();
+
+        manager.undo().unwrap();
+        manager.update_description(&task.id, "notes").unwrap();
+
+        assert!(manager.redo().unwrap().is_empty());
+        let task = manager.find_task_by_id(&task.id).unwrap().unwrap();
+        assert_eq!(task.name, "Draft");
+    }
+
+    #[test]
+    fn test_undo_passes_over_a_purge() {
+        let manager = manager();
+        let kept = add(&manager, "Kept");
+        let gone = add(&manager, "Gone");
+        manager.delete_task_by_id(&gone.id).unwrap();
+        manager.update_name(&kept.id, "Renamed").unwrap();
+        manager.purge_archived(None).unwrap();
+
+        let (undone, skipped) = manager.undo().unwrap();
+        assert_eq!(undone[0].kind, EventKind::Name);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].kind, EventKind::Purged);
+        let kept_task = manager.find_task_by_id(&kept.id).unwrap().unwrap();
+        assert_eq!(kept_task.name, "Kept");
+
+        // Steps that only touched the purged task are passed over as well,
+        // and the purge is reported only once
+        let (undone, skipped) = manager.undo().unwrap();
+        assert_eq!(undone[0].kind, EventKind::Added);
+        assert_eq!(undone[0].task_id, kept.id);
+        assert!(skipped.is_empty());
+
+        let (undone, skipped) = manager.undo().unwrap();
+        assert!(undone.is_empty());
+        assert!(skipped.is_empty());
+
+        assert_eq!(manager.redo().unwrap()[0].task_id, kept.id);
+        assert_eq!(manager.redo().unwrap()[0].kind, EventKind::Name);
+        assert!(manager.redo().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_tag_filter_includes_and_excludes() {
+        let manager = manager();
+        for (name, tags) in [
+            ("deploy", vec!["infra", "urgent"]),
+            ("backup", vec!["infra"]),
+            ("call mum", vec!["home"]),
+            ("read", vec![]),
+        ] {
+            let mut task = Task::new(name.to_string(), None, None).unwrap();
+            task.tags = tags.into_iter().map(String::from).collect();
+            manager.add_task(task).unwrap();
+        }
+
+        let names = |include: &[&str], exclude: &[&str]| -> Vec<String> {
+            let tags = TagFilter {
+                include: include.iter().map(|t| t.to_string()).collect(),
+                exclude: exclude.iter().map(|t| t.to_string()).collect(),
+            };
+            let filter =
+                TaskFilter::new(StatusFilter::All, tags).sorted_by("name".parse().unwrap());
+            manager
+                .list_tasks(filter)
+                .unwrap()
+                .into_iter()
+                .map(|t| t.name)
+                .collect()
+        };
+
+        assert_eq!(names(&["infra"], &[]), ["backup", "deploy"]);
+        assert_eq!(names(&["infra", "urgent"], &[]), ["deploy"]);
+        assert_eq!(names(&[], &["infra"]), ["call mum", "read"]);
+        assert_eq!(names(&["infra"], &["urgent"]), ["backup"]);
+        assert!(names(&["nope"], &[]).is_empty());
+    }
+
+    fn names_sorted_by(manager: &TaskManager, sort: &str) -> Vec<String> {
+        let filter = TaskFilter::from(StatusFilter::All).sorted_by(sort.parse().unwrap());
+        manager
+            .list_tasks(filter)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.name)
+            .collect()
+    }
+
+    #[test]
+    fn test_sort_by_due_puts_missing_due_dates_last() {
+        let manager = manager();
+        let now = Utc::now();
+        for (name, due) in [
+            ("b", None),
+            ("later", Some(now + chrono::Duration::days(2))),
+            ("a", None),
+            ("soon", Some(now + chrono::Duration::hours(1))),
+        ] {
+            let task = Task::new(name.to_string(), None, due).unwrap();
+            manager.add_task(task).unwrap();
+        }
+
+        assert_eq!(
+            names_sorted_by(&manager, "due,name"),
+            ["soon", "later", "a", "b"]
+        );
+        assert_eq!(
+            names_sorted_by(&manager, "-due,-name"),
+            ["later", "soon", "b", "a"]
+        );
+    }
+
+    #[test]
+    fn test_sort_by_status_then_name() {
+        let manager = manager();
+        let done = add(&manager, "Beta");
+        add(&manager, "alpha");
+        add(&manager, "Gamma");
+        manager.update_task_status(&done.id, Status::Done).unwrap();
+
+        assert_eq!(
+            names_sorted_by(&manager, "status,name"),
+            ["alpha", "Gamma", "Beta"]
+        );
+        assert!("due,size".parse::<TaskSort>().is_err());
+    }
+
+    #[test]
+    fn test_due_filters_select_a_window() {
+        let manager = manager();
+        let now = Utc::now();
+        let today =
+            crate::utils::parse_due_date(&chrono::Local::now().format("%Y-%m-%d").to_string());
+        let mut late_done = None;
+        for (name, due) in [
+            ("late", Some(now - chrono::Duration::days(2))),
+            ("late but done", Some(now - chrono::Duration::days(1))),
+            ("today", today.ok()),
+            ("soon", Some(now + chrono::Duration::hours(3))),
+            ("later", Some(now + chrono::Duration::days(10))),
+            ("someday", None),
+        ] {
+            let mut task = Task::new(name.to_string(), None, due).unwrap();
+            if name == "today" {
+                task.due_precision = DuePrecision::Day;
+            }
+            manager.add_task(task.clone()).unwrap();
+            if name == "late but done" {
+                late_done = Some(task.id);
+            }
+        }
+        manager
+            .update_task_status(&late_done.unwrap(), Status::Done)
+            .unwrap();
+
+        let names = |due: DueFilter| -> Vec<String> {
+            let filter = TaskFilter::from(StatusFilter::All)
+                .due_within(due)
+                .sorted_by("due".parse().unwrap());
+            manager
+                .list_tasks(filter)
+                .unwrap()
+                .into_iter()
+                .map(|t| t.name)
+                .collect()
+        };
+
+        // A day isn't over until it ends, even though its midnight has passed
+        let overdue = DueFilter {
+            overdue: true,
+            ..Default::default()
+        };
+        assert_eq!(names(overdue), ["late"]);
+
+        let window = DueFilter {
+            by: Some(now + chrono::Duration::days(1)),
+            after: Some(now - chrono::Duration::days(1) - chrono::Duration::hours(1)),
+            ..Default::default()
+        };
+        let sorted = |mut names: Vec<String>| {
+            names.sort();
+            names
+        };
+        // Whether today ends before "soon" depends on the time of day
+        assert_eq!(sorted(names(window)), ["late but done", "soon", "today"]);
+
+        let after = DueFilter {
+            after: Some(now),
+            ..Default::default()
+        };
+        assert_eq!(sorted(names(after)), ["later", "soon", "today"]);
+
+        let missing = DueFilter {
+            missing: true,
+            ..Default::default()
+        };
+        assert_eq!(names(missing), ["someday"]);
+    }
+
+    #[test]
+    fn test_due_horizon_is_measured_when_the_due_date_changes() {
+        let manager = manager();
+        let due = Utc::now() + chrono::Duration::days(10);
+        let task = Task::new("Report".to_string(), None, Some(due)).unwrap();
+        manager.add_task(task.clone()).unwrap();
+
+        let horizon = || {
+            manager
+                .find_task_by_id(&task.id)
+                .unwrap()
+                .unwrap()
+                .due_horizon
+        };
+        let days =
+            |h: Option<chrono::Duration>| h.map(|h| (h.num_seconds() as f64 / 86_400.0).round());
+        assert_eq!(days(horizon()), Some(10.0));
+
+        // Re-saving the same due date keeps the horizon
+        manager
+            .write_due_horizon(&task.id, Some(chrono::Duration::days(30)))
+            .unwrap();
+        manager
+            .update_due(&task.id, Some((due, DuePrecision::Time)))
+            .unwrap();
+        assert_eq!(days(horizon()), Some(30.0));
+
+        manager
+            .update_due(
+                &task.id,
+                Some((Utc::now() + chrono::Duration::days(3), DuePrecision::Time)),
+            )
+            .unwrap();
+        assert_eq!(days(horizon()), Some(3.0));
+
+        // Undone, the old due date is measured from the task's creation
+        manager.undo().unwrap();
+        assert_eq!(days(horizon()), Some(10.0));
+    }
+
+    #[test]
+    fn test_undo_and_redo_restore_the_due_precision() {
+        let manager = manager();
+        let task = add(&manager, "Call the bank");
+        let due = |manager: &TaskManager| {
+            let task = manager.find_task_by_id(&task.id).unwrap().unwrap();
+            task.due_date.map(|due| (due, task.due_precision))
+        };
+        // Local midnight, which used to be taken for a whole day
+        let midnight = chrono::Local::now()
+            .date_naive()
+            .and_hms_opt(0, 0, 0)
+            .and_then(|naive| naive.and_local_timezone(chrono::Local).latest())
+            .unwrap()
+            .to_utc();
+        let timed = (midnight + Duration::days(2), DuePrecision::Time);
+        let whole_day = (DuePrecision::Day.normalize(midnight), DuePrecision::Day);
+
+        manager.update_due(&task.id, Some(timed)).unwrap();
+        manager
+            .update_due(&task.id, Some((midnight, DuePrecision::Day)))
+            .unwrap();
+        assert_eq!(due(&manager), Some(whole_day));
+
+        manager.undo().unwrap();
+        assert_eq!(due(&manager), Some(timed));
+        manager.redo().unwrap();
+        assert_eq!(due(&manager), Some(whole_day));
+
+        // Only the precision changes
+        let same_day_timed = (whole_day.0, DuePrecision::Time);
+        manager.update_due(&task.id, Some(same_day_timed)).unwrap();
+        manager.undo().unwrap();
+        assert_eq!(due(&manager), Some(whole_day));
+        manager.redo().unwrap();
+        assert_eq!(due(&manager), Some(same_day_timed));
+    }
+
+    #[test]
+    fn test_due_event_values_carry_the_precision() {
+        let due = parse_db_datetime("2025-08-01 12:00:00").unwrap();
+        let value = format_due_value((due, DuePrecision::Time));
+        assert_eq!(value, "2025-08-01 12:00:00 time");
+        assert_eq!(parse_due_value(&value), Some((due, DuePrecision::Time)));
+        assert_eq!(
+            parse_due_value("2025-08-01 12:00:00 day"),
+            Some((due, DuePrecision::Day))
+        );
+
+        // Recorded before the precision was
+        assert_eq!(
+            parse_due_value("2025-08-01 12:00:00"),
+            Some((due, DuePrecision::guess(due)))
+        );
+        assert_eq!(parse_due_value("soon"), None);
+    }
 }
@@ -1,39 +1,42 @@
-use crate::types::{Status, Task};
-use chrono::{DateTime, Duration, NaiveDateTime, Timelike, Utc};
+use crate::database::parse_due_value;
+use crate::locale::Locale;
+use crate::types::{ArchivedTask, DuePrecision, EventKind, Status, Task, TaskEvent};
+use crate::utils::parse_duration;
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDateTime, Utc};
 use colored::*;
+use serde::{Deserialize, Deserializer};
+use std::sync::OnceLock;
 use terminal_size::{Width, terminal_size};
 use textwrap::wrap;
 
-const WRAP_COLUMN: usize = 80;
 const MIN_DESCRIPTION_INDENT: usize = 3;
 const DOT_STATUS_CHARACTER: char = '●';
-const SHORT_ID_LENGTH: usize = 8;
 const SIGN_LATE: char = '!';
 const SIGN_SOON: char = '*';
 const SIGN_DUE: char = '-';
+const SIGN_REPEAT: char = '↻';
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum StatusDisplay {
     Dot,
-    Word,
 }
 
 pub fn format_status_char(status: &Status, display: StatusDisplay) -> ColoredString {
-    let dot = DOT_STATUS_CHARACTER.to_string();
+    let (style, default_color) = match status {
+        Status::Done => (&display_options().status.done, Color::BrightGreen),
+        Status::Pending => (&display_options().status.pending, Color::BrightYellow),
+        Status::Standby => (&display_options().status.standby, Color::BrightBlue),
+    };
+    let color = style.color.unwrap_or(default_color);
     match display {
-        StatusDisplay::Dot => match status {
-            Status::Done => dot.bright_green(),
-            Status::Pending => dot.bright_yellow(),
-            Status::Standby => dot.bright_blue(),
-        },
-        StatusDisplay::Word => match status {
-            Status::Done => "[d]".bright_green(),
-            Status::Pending => "[p]".bright_yellow(),
-            Status::Standby => "[s]".bright_blue(),
+        StatusDisplay::Dot => match &style.symbol {
+            Some(symbol) => symbol.color(color),
+            None => DOT_STATUS_CHARACTER.to_string().color(color),
         },
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn format_task_line_with_number(
     number: usize,
     number_width: usize,
@@ -69,38 +72,48 @@ pub fn format_task_line(
     let status_char = format_status_char(&task.status, status_display);
     let is_done = task.status == Status::Done;
 
-    let short_id = &task.id[..SHORT_ID_LENGTH.min(task.id.len())];
+    let short_id = &task.id[..display_options().short_id_length.min(task.id.len())];
     let display_name = truncate_with_dots(&task.name, name_width);
 
-    let created_dt = DateTime::<Utc>::from_naive_utc_and_offset(
-        NaiveDateTime::parse_from_str(&task.date, "%Y-%m-%d %H:%M:%S").unwrap(),
-        Utc,
-    );
-    let created_str = pretty_time(created_dt);
-    let mut date_display = format!("{:>width$}", created_str, width = time_width)
+    let mut date_display = format!("{:>width$}", list_time(task), width = time_width)
         .dimmed()
         .to_string();
 
-    if !is_done {
-        if let Some(ref due_date) = task.due_date {
-            let due_str = pretty_time(*due_date);
-            let overdue = *due_date < Utc::now();
-            let icon = if overdue {
-                SIGN_LATE
-            } else if is_due_soon(due_date) {
-                SIGN_SOON
-            } else {
-                SIGN_DUE
-            };
-            let due_display = if overdue {
-                format!("{} {} (late)", icon, due_str).bright_red()
-            } else if is_due_soon(due_date) {
-                format!("{} {}", icon, due_str).bright_yellow()
-            } else {
-                format!("{} {}", icon, due_str).dimmed()
-            };
-            date_display = format!("{} {}", date_display, due_display);
-        }
+    if !is_done && let Some(ref due_date) = task.due_date {
+        let due_str = pretty_due(*due_date, task.due_precision);
+        let deadline = task.due_precision.deadline(*due_date);
+        let overdue = deadline < Utc::now();
+        let icon = if overdue {
+            SIGN_LATE
+        } else if is_due_soon(task) {
+            SIGN_SOON
+        } else {
+            SIGN_DUE
+        };
+        let due_display = if overdue {
+            format!("{} {} (late)", icon, due_str).bright_red()
+        } else if is_due_soon(task) {
+            format!("{} {}", icon, due_str).bright_yellow()
+        } else {
+            format!("{} {}", icon, due_str).dimmed()
+        };
+        date_display = format!("{} {}", date_display, due_display);
+    }
+
+    if !is_done && task.recurrence.is_some() {
+        date_display = format!("{} {}", date_display, SIGN_REPEAT.to_string().dimmed());
+    }
+
+    if !is_done && let Some(priority) = task.priority {
+        date_display = format!(
+            "{} {}",
+            date_display,
+            format!("({})", priority).bright_magenta()
+        );
+    }
+
+    if !task.tags.is_empty() {
+        date_display = format!("{} {}", date_display, format_tags(&task.tags).cyan());
     }
 
     println!(
@@ -117,17 +130,52 @@ pub fn format_task_line(
     }
 }
 
+pub fn format_archived_line(
+    number: usize,
+    number_width: usize,
+    archived: &ArchivedTask,
+    name_width: usize,
+) {
+    let task = &archived.task;
+    let short_id = &task.id[..display_options().short_id_length.min(task.id.len())];
+
+    println!(
+        "{:>number_width$}. {} {} {:<name_width$} {}",
+        number,
+        short_id.bright_black(),
+        format_status_char(&task.status, StatusDisplay::Dot),
+        truncate_with_dots(&task.name, name_width).bright_white(),
+        format!("deleted {}", pretty_time(archived.deleted_at)).dimmed(),
+    );
+}
+
+/// The timestamp shown in a listing: when a done task was completed, or
+/// when any other task was created.
+pub fn list_time(task: &Task) -> String {
+    match (&task.status, task.completed_at) {
+        (Status::Done, Some(completed_at)) => format!("done {}", pretty_time(completed_at)),
+        _ => {
+            let created_dt = DateTime::<Utc>::from_naive_utc_and_offset(
+                NaiveDateTime::parse_from_str(&task.date, "%Y-%m-%d %H:%M:%S").unwrap(),
+                Utc,
+            );
+            pretty_time(created_dt)
+        }
+    }
+}
+
 fn print_task_description_formatted(task: &Task, indent_len: usize, time_col_start: usize) {
     // blank line above description
     println!();
 
     let indent = " ".repeat(indent_len.max(MIN_DESCRIPTION_INDENT));
 
-    // preferred wrap column is 80 if the terminal is wide enough,
-    // otherwise we stop *just* before the timestamp column so the two
-    // never collide.
-    let wrap_limit = if term_width() >= WRAP_COLUMN {
-        WRAP_COLUMN
+    // preferred wrap column is the configured one (80) if the terminal is
+    // wide enough, otherwise we stop *just* before the timestamp column so
+    // the two never collide.
+    let wrap_column = display_options().wrap_column;
+    let wrap_limit = if term_width() >= wrap_column {
+        wrap_column
     } else {
         // leave one spare column so we never touch the date
         time_col_start.saturating_sub(1)
@@ -150,13 +198,24 @@ pub fn print_task_details(task: &Task, minimal_mode: bool) {
     print_task_description(task, pad);
     if !minimal_mode {
         print_task_created(task, pad);
+        print_task_completed(task, pad);
     }
     print_task_due_date(task, pad);
+    print_task_recurrence(task, pad);
+    print_task_priority(task, pad);
+    print_task_tags(task, pad);
     if !minimal_mode {
         print_task_status(task, pad, StatusDisplay::Dot);
     }
 }
 
+pub fn format_tags(tags: &[String]) -> String {
+    tags.iter()
+        .map(|tag| format!("+{}", tag))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 fn print_task_id(task: &Task, pad: usize) {
     println!("{:<pad$} {}", "id".dimmed(), task.id.bright_white());
 }
@@ -174,8 +233,9 @@ fn print_task_description(task: &Task, pad: usize) {
     let indent = " ".repeat(indent_len);
 
     let term_w = term_width();
-    let wrap_limit = if term_w >= WRAP_COLUMN {
-        WRAP_COLUMN
+    let wrap_column = display_options().wrap_column;
+    let wrap_limit = if term_w >= wrap_column {
+        wrap_column
     } else {
         term_w.saturating_sub(1)
     };
@@ -209,20 +269,32 @@ fn print_task_created(task: &Task, pad: usize) {
     );
 }
 
+fn print_task_completed(task: &Task, pad: usize) {
+    if let Some(completed_at) = task.completed_at {
+        println!(
+            "{:<pad$} {}",
+            "finished".dimmed(),
+            pretty_time(completed_at),
+            pad = pad
+        );
+    }
+}
+
 fn print_task_due_date(task: &Task, pad: usize) {
     if let Some(ref due_date) = task.due_date {
-        let due_str = pretty_time(*due_date);
-        let icon = if *due_date < Utc::now() {
+        let due_str = pretty_due(*due_date, task.due_precision);
+        let deadline = task.due_precision.deadline(*due_date);
+        let icon = if deadline < Utc::now() {
             SIGN_LATE
-        } else if is_due_soon(due_date) {
+        } else if is_due_soon(task) {
             SIGN_SOON
         } else {
             SIGN_DUE
         };
-        let overdue = *due_date < Utc::now();
+        let overdue = deadline < Utc::now();
         let due_display = if overdue {
             format!("{} {} (late)", icon, due_str).bright_red()
-        } else if is_due_soon(due_date) {
+        } else if is_due_soon(task) {
             format!("{} {}", icon, due_str).bright_yellow()
         } else {
             format!("{} {}", icon, due_str).dimmed()
@@ -232,74 +304,227 @@ fn print_task_due_date(task: &Task, pad: usize) {
     }
 }
 
+fn print_task_recurrence(task: &Task, pad: usize) {
+    if let Some(recurrence) = task.recurrence {
+        println!(
+            "{:<pad$} {}",
+            "repeats".dimmed(),
+            format!("{} every {}", SIGN_REPEAT, recurrence).dimmed()
+        );
+    }
+}
+
+fn print_task_priority(task: &Task, pad: usize) {
+    if let Some(priority) = task.priority {
+        println!(
+            "{:<pad$} {}",
+            "priority".dimmed(),
+            priority.to_string().bright_magenta()
+        );
+    }
+}
+
+fn print_task_tags(task: &Task, pad: usize) {
+    if task.tags.is_empty() {
+        return;
+    }
+    println!(
+        "{:<pad$} {}",
+        "tags".dimmed(),
+        format_tags(&task.tags).cyan()
+    );
+}
+
+pub fn print_task_history(task_id: &str, events: &[TaskEvent]) {
+    let pad = 8;
+    let events: Vec<&TaskEvent> = events.iter().filter(|e| is_listed_event(e)).collect();
+    let when_width = events_when_width(&events);
+
+    println!("{:<pad$} {}", "id".dimmed(), task_id.bright_white());
+    if let Some(name) = events.last().and_then(|e| e.task_name.as_ref()) {
+        println!("{:<pad$} {}", "name".dimmed(), name.bold());
+    }
+
+    for event in events {
+        let line = format!(
+            "{:<pad$} {:<when_width$} {}{}",
+            event.kind.to_string().dimmed(),
+            pretty_time(event.at).dimmed(),
+            describe_change(event),
+            undone_marker(event),
+        );
+        println!("{}", line.trim_end());
+    }
+}
+
+/// Every event across all tasks, newest first.
+pub fn print_event_log(events: &[TaskEvent]) {
+    let events: Vec<&TaskEvent> = events.iter().rev().filter(|e| is_listed_event(e)).collect();
+    let when_width = events_when_width(&events);
+
+    for event in events {
+        let when = format!("{:<when_width$}", pretty_time(event.at));
+        println!(
+            "{}{}",
+            format_event_summary(event, Some(&when)),
+            undone_marker(event)
+        );
+    }
+}
+
+/// Report the events an `--undo` or `--redo` just reverted or re-applied.
+pub fn print_replayed_events(verb: &str, events: &[TaskEvent]) {
+    println!("{}", verb.bright_green());
+    for event in events.iter().filter(|e| is_listed_event(e)) {
+        println!("  {}", format_event_summary(event, None));
+    }
+}
+
+fn format_event_summary(event: &TaskEvent, when: Option<&str>) -> String {
+    let pad = 8;
+    let short_id = &event.task_id[..display_options().short_id_length.min(event.task_id.len())];
+    let name = event.task_name.as_deref().unwrap_or("?");
+    let change = describe_change(event);
+
+    format!(
+        "{} {:<pad$} {}{}{}",
+        short_id.bright_black(),
+        event.kind.to_string().dimmed(),
+        when.map(|w| format!("{} ", w.dimmed())).unwrap_or_default(),
+        name.bright_white(),
+        if change.is_empty() {
+            String::new()
+        } else {
+            format!(": {}", change)
+        },
+    )
+}
+
+// completion timestamps travel with status changes; they are only recorded
+// so that undo can put them back
+fn is_listed_event(event: &TaskEvent) -> bool {
+    event.kind != EventKind::Completed
+}
+
+fn undone_marker(event: &TaskEvent) -> ColoredString {
+    if event.undone {
+        " (undone)".bright_black()
+    } else {
+        "".normal()
+    }
+}
+
+fn events_when_width(events: &[&TaskEvent]) -> usize {
+    events
+        .iter()
+        .map(|e| pretty_time(e.at).chars().count())
+        .max()
+        .unwrap_or(0)
+}
+
+fn describe_change(event: &TaskEvent) -> String {
+    let value = |v: &Option<String>| match (event.kind, v.as_deref()) {
+        (_, None) => "none".to_string(),
+        (EventKind::Due, Some(raw)) => match parse_due_value(raw) {
+            Some((due, DuePrecision::Day)) => {
+                due.with_timezone(&Local).format("%Y-%m-%d").to_string()
+            }
+            Some((due, DuePrecision::Time)) => due
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string(),
+            None => raw.to_string(),
+        },
+        (EventKind::Completed, Some(raw)) => {
+            NaiveDateTime::parse_from_str(raw, "%Y-%m-%d %H:%M:%S")
+                .map(|dt| {
+                    dt.and_utc()
+                        .with_timezone(&chrono::Local)
+                        .format("%Y-%m-%d %H:%M")
+                        .to_string()
+                })
+                .unwrap_or_else(|_| raw.to_string())
+        }
+        (EventKind::Tags, Some(raw)) => {
+            format_tags(&raw.split(' ').map(String::from).collect::<Vec<_>>())
+        }
+        (EventKind::Description, Some(raw)) => {
+            format!("\"{}\"", truncate_with_dots(raw, 30))
+        }
+        (_, Some(raw)) => raw.to_string(),
+    };
+
+    match event.kind {
+        EventKind::Added => String::new(),
+        EventKind::Deleted | EventKind::Restored | EventKind::Purged => String::new(),
+        EventKind::Moved => format!("to {}", value(&event.new_value)),
+        _ => format!("{} → {}", value(&event.old_value), value(&event.new_value)),
+    }
+}
+
 fn print_task_status(task: &Task, pad: usize, display: StatusDisplay) {
     let out = format_status_char(&task.status, display);
     println!("{:<pad$} {}", "status".dimmed(), out, pad = pad);
 }
 
+/// A moment such as when a task was created or completed.
 pub fn pretty_time(dt: DateTime<Utc>) -> String {
+    pretty_time_in(dt, DuePrecision::Time, Locale::current())
+}
+
+/// A due date. A whole day is shown as just the day; an exact time counts
+/// down over the 24 hours around it, and shows its time of day otherwise.
+pub fn pretty_due(due: DateTime<Utc>, precision: DuePrecision) -> String {
+    pretty_time_in(due, precision, Locale::current())
+}
+
+/// [`pretty_due`] in the words of `locale`.
+pub fn pretty_time_in(dt: DateTime<Utc>, precision: DuePrecision, locale: Locale) -> String {
     let now = Utc::now();
-    let secs = (dt - now).num_seconds();
-    let future = secs >= 0;
-    let abs_secs = secs.abs();
-
-    // TODO: Better solution would be to track the original format from database
-    // and pass it here, so we can distinguish between user-specified midnight
-    // (e.g., "2025-08-12 00:00") and date-only input (e.g., "2025-08-12").
-    // For now, we use heuristics:
-    // - Exact midnight local time = date-only input (like "2025-08-12")  
-    // - 23:59:59 local time = "today"/"tomorrow" input (should use relative time)
-    let local_dt = dt.with_timezone(&chrono::Local);
-    let is_date_only = local_dt.time().hour() == 0 && local_dt.time().minute() == 0 && local_dt.time().second() == 0;
-
-    // Use relative time for "today"/"tomorrow" (23:59:59) and other time-specific tasks
-    // But never for date-only tasks (00:00:00)
-    if !is_date_only && abs_secs < 86_400 {
-        let mins = (abs_secs + 59) / 60;
-        let hours = mins / 60;
-        let minutes = mins % 60;
-
-        let mut parts = Vec::new();
-        if hours > 0 {
-            parts.push(format!("{}h", hours));
-        }
-        if minutes > 0 {
-            parts.push(format!("{}m", minutes));
+    let is_day = precision == DuePrecision::Day;
+
+    if !is_day {
+        let secs = (dt - now).num_seconds();
+        // timestamps are stored with whole seconds, so something that happened
+        // a moment ago can round to zero; treat that as the past
+        let future = secs > 0;
+        let abs_secs = secs.abs();
+
+        if abs_secs < 86_400 {
+            let mins = (abs_secs + 59) / 60;
+            let hours = mins / 60;
+            let minutes = mins % 60;
+
+            let mut parts = Vec::new();
+            if hours > 0 {
+                parts.push(format!("{}h", hours));
+            }
+            if minutes > 0 {
+                parts.push(format!("{}m", minutes));
+            }
+            if parts.is_empty() {
+                parts.push("0m".into());
+            }
+
+            return locale.relative(&parts.join(" "), future);
         }
-        if parts.is_empty() {
-            parts.push("0m".into());
-        }
-
-        let phrase = parts.join(" ");
-        return if future {
-            format!("in {}", phrase)
-        } else {
-            format!("{} ago", phrase)
-        };
     }
 
-    // For date-only tasks, use local date for comparison to show correct date
-    // For time-specific tasks, use UTC date as before
-    let (d, nd) = if is_date_only {
-        let local_dt = dt.with_timezone(&chrono::Local);
-        let local_now = now.with_timezone(&chrono::Local);
-        (local_dt.date_naive(), local_now.date_naive())
-    } else {
-        (dt.date_naive(), now.date_naive())
-    };
-    let diff_days = (d - nd).num_days();
+    let local_dt = dt.with_timezone(&Local);
+    let diff_days = (local_dt.date_naive() - now.with_timezone(&Local).date_naive()).num_days();
 
-    match diff_days {
-        0 if is_date_only => "today".to_string(),
-        0 => format!("today at {}", dt.with_timezone(&chrono::Local).format("%H:%M")),
-        -1 if is_date_only => "yesterday".to_string(),
-        -1 => format!("yesterday at {}", dt.with_timezone(&chrono::Local).format("%H:%M")),
-        1 if is_date_only => "tomorrow".to_string(),
-        1 => format!("tomorrow at {}", dt.with_timezone(&chrono::Local).format("%H:%M")),
-        -6..=6 if is_date_only => dt.with_timezone(&chrono::Local).format("%A").to_string(),
-        -6..=6 => dt.with_timezone(&chrono::Local).format("%A at %H:%M").to_string(),
-        _ if is_date_only => dt.with_timezone(&chrono::Local).format("%Y-%m-%d").to_string(),
-        _ => dt.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string(),
+    let day = match diff_days {
+        0 => locale.today(),
+        -1 => locale.yesterday(),
+        1 => locale.tomorrow(),
+        -6..=6 => locale.weekday(local_dt.weekday()),
+        _ if is_day => return local_dt.format("%Y-%m-%d").to_string(),
+        _ => return local_dt.format("%Y-%m-%d %H:%M").to_string(),
+    };
+    if is_day {
+        day.to_string()
+    } else {
+        locale.at(day, &local_dt.format("%H:%M").to_string())
     }
 }
 
@@ -312,26 +537,177 @@ fn truncate_with_dots(s: &str, limit: usize) -> String {
     format!("{}...", truncated)
 }
 
-fn is_due_soon(due_date: &DateTime<Utc>) -> bool {
-    let now = Utc::now();
-    let diff = *due_date - now;
-    if diff.num_seconds() < 0 {
-        return false;
+/// Widths and status styles of listings and task details.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct DisplayOptions {
+    /// Column descriptions wrap at; with descriptions shown, dates line up
+    /// with it too.
+    pub wrap_column: usize,
+    /// How much of a task id listings show.
+    pub short_id_length: usize,
+    /// Longest name shown in prompts and summaries before it is cut off.
+    pub name_width: usize,
+    pub status: StatusStyles,
+}
+
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        DisplayOptions {
+            wrap_column: 80,
+            short_id_length: 8,
+            name_width: 50,
+            status: StatusStyles::default(),
+        }
     }
+}
 
-    if diff <= Duration::minutes(20) {
-        return true; // minute‑level tasks
+impl DisplayOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.wrap_column < 20 {
+            return Err("display.wrap_column must be at least 20".to_string());
+        }
+        if !(4..=36).contains(&self.short_id_length) {
+            return Err("display.short_id_length must be between 4 and 36".to_string());
+        }
+        if self.name_width < 10 {
+            return Err("display.name_width must be at least 10".to_string());
+        }
+        Ok(())
     }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct StatusStyles {
+    pub pending: StatusStyle,
+    pub done: StatusStyle,
+    pub standby: StatusStyle,
+}
+
+/// The symbol and colour a status is drawn with; unset ones keep the
+/// defaults, a coloured `●`.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct StatusStyle {
+    pub symbol: Option<String>,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub color: Option<Color>,
+}
+
+fn deserialize_color<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Color>, D::Error> {
+    let raw = String::deserialize(deserializer)?;
+    raw.parse()
+        .map(Some)
+        .map_err(|_| serde::de::Error::custom(format!("unknown colour '{}'", raw)))
+}
+
+static OPTIONS: OnceLock<DisplayOptions> = OnceLock::new();
+
+/// Use `options` instead of the defaults; only the first call counts.
+pub fn set_display_options(options: DisplayOptions) {
+    let _ = OPTIONS.set(options);
+}
+
+pub fn display_options() -> &'static DisplayOptions {
+    OPTIONS.get_or_init(DisplayOptions::default)
+}
+
+/// How early a task counts as due soon: `percent` of the time it was given,
+/// but never more than `max`.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct SoonWindow {
+    pub percent: f64,
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub max: Duration,
+}
+
+/// The `SoonWindow` for each horizon a task can be given, so that `*` means
+/// about as much for a 30 minute task as for a three month one.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct DueSoonThresholds {
+    /// Tasks given less than two hours.
+    pub short: SoonWindow,
+    /// Tasks given up to a day.
+    pub day: SoonWindow,
+    /// Tasks given up to a week.
+    pub week: SoonWindow,
+    /// Tasks given up to a month (31 days).
+    pub month: SoonWindow,
+    /// Anything longer.
+    pub longer: SoonWindow,
+}
 
-    if diff <= Duration::hours(24) {
-        return true; // "today" or specific‑date tasks (day‑before window)
+impl Default for DueSoonThresholds {
+    fn default() -> Self {
+        let window = |percent, max| SoonWindow { percent, max };
+        DueSoonThresholds {
+            short: window(50.0, Duration::hours(1)),
+            day: window(50.0, Duration::hours(2)),
+            week: window(50.0, Duration::days(1)),
+            month: window(25.0, Duration::days(3)),
+            longer: window(10.0, Duration::days(7)),
+        }
     }
+}
 
-    if diff <= Duration::days(7) {
-        return diff <= Duration::days(1); // week‑range tasks
+impl DueSoonThresholds {
+    /// How long before its due date a task given `horizon` becomes due soon.
+    pub fn warning_time(&self, horizon: Duration) -> Duration {
+        let window = if horizon < Duration::hours(2) {
+            self.short
+        } else if horizon <= Duration::days(1) {
+            self.day
+        } else if horizon <= Duration::days(7) {
+            self.week
+        } else if horizon <= Duration::days(31) {
+            self.month
+        } else {
+            self.longer
+        };
+        let share = horizon.num_seconds() as f64 * window.percent / 100.0;
+        Duration::seconds(share.round() as i64).min(window.max)
     }
+}
+
+fn deserialize_duration<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    let raw = String::deserialize(deserializer)?;
+    parse_duration(&raw).map_err(serde::de::Error::custom)
+}
+
+static DUE_SOON: OnceLock<DueSoonThresholds> = OnceLock::new();
 
-    diff <= Duration::days(3) // longer‑range tasks
+/// Use `thresholds` instead of the defaults; only the first call counts.
+pub fn set_due_soon_thresholds(thresholds: DueSoonThresholds) {
+    let _ = DUE_SOON.set(thresholds);
+}
+
+/// Measured to the task's deadline, so a whole day also gets the hours
+/// until it ends.
+fn is_due_soon(task: &Task) -> bool {
+    let (Some(due), Some(deadline)) = (task.due_date, task.deadline()) else {
+        return false;
+    };
+    let horizon = task.due_horizon.map(|h| h + (deadline - due));
+    let thresholds = DUE_SOON.get_or_init(DueSoonThresholds::default);
+    is_due_soon_at(&deadline, horizon, Utc::now(), thresholds)
+}
+
+/// Tasks without a known horizon are treated like same-day ones.
+fn is_due_soon_at(
+    due_date: &DateTime<Utc>,
+    horizon: Option<Duration>,
+    now: DateTime<Utc>,
+    thresholds: &DueSoonThresholds,
+) -> bool {
+    let left = *due_date - now;
+    if left < Duration::zero() {
+        return false;
+    }
+    left <= thresholds.warning_time(horizon.unwrap_or(Duration::days(1)))
 }
 
 fn term_width() -> usize {
@@ -351,7 +727,7 @@ mod tests {
         let local_midnight = Local.with_ymd_and_hms(2025, 8, 15, 0, 0, 0).unwrap();
         let utc_dt = local_midnight.with_timezone(&Utc);
         
-        let result = pretty_time(utc_dt);
+        let result = pretty_time_in(utc_dt, DuePrecision::Day, Locale::En);
         
         // Should show the original date entered by user (2025-08-15) without time
         assert!(result.contains("2025-08-15") && !result.contains(":"));
@@ -363,7 +739,7 @@ mod tests {
         let local_dt = Local.with_ymd_and_hms(2025, 8, 15, 15, 30, 0).unwrap();
         let utc_dt = local_dt.with_timezone(&Utc);
         
-        let result = pretty_time(utc_dt);
+        let result = pretty_time_in(utc_dt, DuePrecision::Time, Locale::En);
         
         // Should show the original local time (15:30), not UTC time
         assert!(result.contains("15:30"));
@@ -377,7 +753,7 @@ mod tests {
         let future_midnight = Local.from_local_datetime(&in_three_days.and_hms_opt(0, 0, 0).unwrap()).single().unwrap();
         let utc_dt = future_midnight.with_timezone(&Utc);
         
-        let result = pretty_time(utc_dt);
+        let result = pretty_time_in(utc_dt, DuePrecision::Day, Locale::En);
         
         // Should show weekday name without time for date-only tasks within week range
         let days = ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday"];
@@ -389,16 +765,20 @@ mod tests {
     }
 
     #[test]
-    fn test_pretty_time_end_of_day_shows_relative_time() {
-        // Test "today"/"tomorrow" tasks (saved as 23:59:59)
+    fn test_pretty_time_end_of_day_shows_no_countdown() {
+        // "today"/"tomorrow" tasks are saved as 23:59:59 and name the whole day
         let now = Local::now();
         let today_end = now.date_naive().and_hms_opt(23, 59, 59).unwrap()
             .and_local_timezone(Local).unwrap();
         let utc_dt = today_end.with_timezone(&Utc);
-        
-        let result = pretty_time(utc_dt);
-        
-        // Should show relative time format for today/tomorrow tasks
+
+        assert_eq!(
+            pretty_time_in(utc_dt, DuePrecision::Day, Locale::En),
+            "today"
+        );
+
+        // The same moment given as an exact time counts down
+        let result = pretty_time_in(utc_dt, DuePrecision::Time, Locale::En);
         assert!(result.contains("in ") || result.contains(" ago"));
     }
 
@@ -408,7 +788,7 @@ mod tests {
         let local_time = Local.with_ymd_and_hms(2025, 8, 15, 14, 30, 0).unwrap();
         let utc_time = local_time.with_timezone(&Utc);
         
-        let result = pretty_time(utc_time);
+        let result = pretty_time_in(utc_time, DuePrecision::Time, Locale::En);
         
         // The displayed time should match the original local time
         assert!(result.contains("14:30"));
@@ -421,7 +801,7 @@ mod tests {
         let future_date = Local.with_ymd_and_hms(2026, 12, 25, 0, 0, 0).unwrap();
         let utc_dt = future_date.with_timezone(&Utc);
         
-        let result = pretty_time(utc_dt);
+        let result = pretty_time_in(utc_dt, DuePrecision::Day, Locale::En);
         
         // Should show the original date entered by user without time
         assert!(result.contains("2026-12-25"));
@@ -430,14 +810,97 @@ mod tests {
 
     #[test]
     fn test_pretty_time_preserves_user_midnight() {
-        // Test that user-specified midnight (00:00) is treated as date-only
+        // A meeting entered at 00:00 keeps its time
         let user_midnight = Local.with_ymd_and_hms(2025, 8, 15, 0, 0, 0).unwrap();
         let utc_dt = user_midnight.with_timezone(&Utc);
-        
-        let result = pretty_time(utc_dt);
-        
-        // Should be treated as date-only (this is the limitation mentioned in TODO)
-        // but it's the current expected behavior
-        assert!(!result.contains(":") || result == "today" || result == "tomorrow");
+
+        let result = pretty_time_in(utc_dt, DuePrecision::Time, Locale::En);
+
+        assert_eq!(result, "2025-08-15 00:00");
+    }
+
+    #[test]
+    fn test_pretty_time_in_spanish() {
+        let now = Local::now();
+        let at_midnight = |days: i64| {
+            let date = now.date_naive() + chrono::Duration::days(days);
+            Local
+                .from_local_datetime(&date.and_hms_opt(0, 0, 0).unwrap())
+                .single()
+                .unwrap()
+                .with_timezone(&Utc)
+        };
+
+        let day = |days: i64| pretty_time_in(at_midnight(days), DuePrecision::Day, Locale::Es);
+        assert_eq!(day(0), "hoy");
+        assert_eq!(day(-1), "ayer");
+        assert_eq!(day(1), "mañana");
+        let weekday = Locale::Es.weekday((now + chrono::Duration::days(3)).weekday());
+        assert_eq!(day(3), weekday);
+
+        let soon = Utc::now() + chrono::Duration::minutes(90);
+        assert_eq!(
+            pretty_time_in(soon, DuePrecision::Time, Locale::Es),
+            "en 1h 30m"
+        );
+        let past = Utc::now() - chrono::Duration::minutes(5);
+        assert_eq!(
+            pretty_time_in(past, DuePrecision::Time, Locale::Es),
+            "hace 5m"
+        );
+    }
+
+    #[test]
+    fn test_list_time_shows_completion_of_done_tasks() {
+        let mut task = Task::new("Ship it".to_string(), None, None).unwrap();
+        task.date = "2025-08-15 09:00:00".to_string();
+        assert!(!list_time(&task).starts_with("done"));
+
+        task.status = Status::Done;
+        task.completed_at = Some(Utc::now() - Duration::hours(2));
+        assert!(list_time(&task).starts_with("done "));
+
+        // Done before completion times were recorded: fall back to creation
+        task.completed_at = None;
+        let pending = Task {
+            status: Status::Pending,
+            ..task.clone()
+        };
+        assert_eq!(list_time(&task), list_time(&pending));
+    }
+
+    #[test]
+    fn test_due_soon_scales_with_horizon() {
+        let thresholds = DueSoonThresholds::default();
+        let now = Utc::now();
+        let (m, h, d) = (Duration::minutes, Duration::hours, Duration::days);
+
+        // (time given, time left, due soon)
+        for (horizon, left, soon) in [
+            (m(30), m(15), true),
+            (m(30), m(16), false),
+            (h(10), h(2), true),
+            (h(10), h(3), false),
+            (h(3), m(90), true),
+            (h(3), m(91), false),
+            (d(5), d(1), true),
+            (d(5), h(25), false),
+            (d(8), d(2), true),
+            (d(8), h(49), false),
+            (d(28), d(3), true),
+            (d(28), h(73), false),
+            (d(40), d(4), true),
+            (d(40), h(97), false),
+            (d(365), d(7), true),
+            (d(365), d(8), false),
+            (d(5), -m(1), false),
+        ] {
+            let due = now + left;
+            assert_eq!(
+                is_due_soon_at(&due, Some(horizon), now, &thresholds),
+                soon,
+                "horizon {horizon}, left {left}"
+            );
+        }
     }
 }
@@ -1,4 +1,4 @@
-use crate::types::TaskError;
+use crate::types::{DuePrecision, TaskError};
 use serde::{Deserialize, Serialize};
 use std::io::Write as IoWrite;
 use std::process::Command;
@@ -6,12 +6,20 @@ use std::{fs, io};
 use tempfile::NamedTempFile;
 
 /// A serialisable representation of a task for full-file editing.
-/// `due` is a plain string, or None to clear the due date.
+/// `due` is a plain string, or None to clear the due date. A due date
+/// without a time of day is written as just the date. `every` works the
+/// same way for the recurrence rule, and `priority` for the priority letter.
 #[derive(Serialize, Deserialize)]
 pub struct EditableTask {
     pub name: String,
     pub description: String,
     pub due: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub every: Option<String>,
+    #[serde(default)]
+    pub priority: Option<String>,
 }
 
 impl EditableTask {
@@ -20,9 +28,16 @@ impl EditableTask {
         EditableTask {
             name: task.name.clone(),
             description: task.description.clone(),
-            due: task
-                .due_date
-                .map(|d| d.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string()),
+            due: task.due_date.map(|d| {
+                let format = match task.due_precision {
+                    DuePrecision::Day => "%Y-%m-%d",
+                    DuePrecision::Time => "%Y-%m-%d %H:%M:%S",
+                };
+                d.with_timezone(&chrono::Local).format(format).to_string()
+            }),
+            tags: task.tags.clone(),
+            every: task.recurrence.map(|r| r.to_string()),
+            priority: task.priority.map(|p| p.to_string()),
         }
     }
 }
@@ -59,6 +74,37 @@ pub fn edit_via_editor(task: &crate::types::Task) -> Result<EditableTask, TaskEr
         None => writeln!(tmp, "due = \"\"").map_err(TaskError::Io)?,
     }
 
+    writeln!(
+        tmp,
+        "# Tags without the leading '+', e.g. [\"infra\", \"urgent\"]"
+    )
+    .map_err(TaskError::Io)?;
+    writeln!(tmp, "tags = {:?}", editable.tags).map_err(TaskError::Io)?;
+
+    writeln!(
+        tmp,
+        "# Repeat when done, e.g. \"week\" or \"1st monday\". Leave empty for never."
+    )
+    .map_err(TaskError::Io)?;
+    writeln!(
+        tmp,
+        "every = {:?}",
+        editable.every.as_deref().unwrap_or_default()
+    )
+    .map_err(TaskError::Io)?;
+
+    writeln!(
+        tmp,
+        "# A letter from A (most urgent) to Z. Leave empty for none."
+    )
+    .map_err(TaskError::Io)?;
+    writeln!(
+        tmp,
+        "priority = {:?}",
+        editable.priority.as_deref().unwrap_or_default()
+    )
+    .map_err(TaskError::Io)?;
+
     tmp.flush().map_err(TaskError::Io)?;
 
     // Invoke editor
@@ -66,10 +112,9 @@ pub fn edit_via_editor(task: &crate::types::Task) -> Result<EditableTask, TaskEr
         .or_else(|_| std::env::var("EDITOR"))
         .unwrap_or_else(|_| "vi".to_string());
     if let Err(err) = Command::new(&editor).arg(tmp.path()).status() {
-        return Err(TaskError::Io(io::Error::new(
-            io::ErrorKind::Other,
-            format!("Failed to launch editor: {err}"),
-        )));
+        return Err(TaskError::Io(io::Error::other(format!(
+            "Failed to launch editor: {err}"
+        ))));
     }
 
     // Read and parse edited file, stripping comment lines
@@ -84,6 +129,8 @@ pub fn edit_via_editor(task: &crate::types::Task) -> Result<EditableTask, TaskEr
         .map_err(|e| TaskError::InvalidInput(format!("Failed to parse TOML: {e}")))?;
 
     edited.description = edited.description.trim().to_string();
+    edited.tags.sort();
+    edited.tags.dedup();
 
     Ok(edited)
 }
@@ -107,6 +154,12 @@ mod tests {
             description: "Test description".to_string(),
             status: crate::types::Status::Pending,
             due_date: Some(utc_time),
+            due_horizon: None,
+            due_precision: DuePrecision::Time,
+            completed_at: None,
+            tags: vec![],
+            recurrence: None,
+            priority: None,
         };
 
         let editable = EditableTask::from_task(&task);
@@ -126,6 +179,12 @@ mod tests {
             description: "Test description".to_string(),
             status: crate::types::Status::Pending,
             due_date: None,
+            due_horizon: None,
+            due_precision: DuePrecision::Time,
+            completed_at: None,
+            tags: vec![],
+            recurrence: None,
+            priority: None,
         };
 
         let editable = EditableTask::from_task(&task);
@@ -149,6 +208,12 @@ mod tests {
             description: "".to_string(),
             status: crate::types::Status::Pending,
             due_date: Some(stored_utc_time),
+            due_horizon: None,
+            due_precision: DuePrecision::Time,
+            completed_at: None,
+            tags: vec![],
+            recurrence: None,
+            priority: None,
         };
 
         let editable = EditableTask::from_task(&task);
@@ -156,4 +221,16 @@ mod tests {
         // Should show the original local time the user entered
         assert_eq!(editable.due, Some("2025-12-25 14:30:00".to_string()));
     }
+
+    #[test]
+    fn test_editable_task_shows_a_due_day_without_time() {
+        let due_day = Local.with_ymd_and_hms(2025, 8, 15, 23, 59, 59).unwrap();
+        let due = Some(due_day.with_timezone(&Utc));
+        let mut task = Task::new("Report".to_string(), None, due).unwrap();
+        task.due_precision = DuePrecision::Day;
+
+        let editable = EditableTask::from_task(&task);
+
+        assert_eq!(editable.due, Some("2025-08-15".to_string()));
+    }
 }
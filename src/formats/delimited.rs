@@ -0,0 +1,224 @@
+//! CSV and TSV, which only differ in their delimiter.
+
+use super::{ParsedRecord, TaskDraft};
+use crate::database::parse_db_datetime;
+use crate::types::{Status, Task, TaskError, TaskField};
+use crate::utils::parse_due_date;
+use chrono::{DateTime, Local, Utc};
+
+pub const CSV: u8 = b',';
+pub const TSV: u8 = b'\t';
+
+/// Timestamps are written in local time, which spreadsheets display as is
+/// and `parse_due_date` reads back.
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// A header row naming `columns` (or `TaskField::DEFAULT_COLUMNS`) followed
+/// by one row per task. Fields are quoted whenever they contain the
+/// delimiter, quotes or line breaks.
+pub fn export(tasks: &[Task], columns: &[TaskField], delimiter: u8) -> Result<String, TaskError> {
+    let columns = if columns.is_empty() {
+        &TaskField::DEFAULT_COLUMNS[..]
+    } else {
+        columns
+    };
+
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(Vec::new());
+
+    writer
+        .write_record(columns.iter().map(|c| c.to_string()))
+        .map_err(csv_error)?;
+    for task in tasks {
+        writer
+            .write_record(columns.iter().map(|c| field_value(task, *c)))
+            .map_err(csv_error)?;
+    }
+
+    let bytes = writer
+        .into_inner()
+        .map_err(|e| TaskError::Io(e.into_error()))?;
+    String::from_utf8(bytes).map_err(|e| TaskError::InvalidInput(e.to_string()))
+}
+
+/// Read rows under a header line. Each header is matched against `mapping`
+/// first and then against common spreadsheet names ("Title", "Due date",
+/// "Notes", …); columns that match neither are ignored. Rows are numbered
+/// as in a spreadsheet, the header being row 1.
+pub fn parse(
+    input: &str,
+    delimiter: u8,
+    mapping: &[(String, TaskField)],
+) -> Result<Vec<ParsedRecord>, TaskError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(input.as_bytes());
+
+    let fields: Vec<Option<TaskField>> = reader
+        .headers()
+        .map_err(csv_error)?
+        .iter()
+        .map(|header| field_for_header(header, mapping))
+        .collect();
+
+    if !fields.contains(&Some(TaskField::Name)) {
+        return Err(TaskError::InvalidInput(
+            "No column holds the task name; name it with --map HEADER=name".to_string(),
+        ));
+    }
+
+    Ok(reader
+        .records()
+        .enumerate()
+        .map(|(idx, row)| ParsedRecord {
+            number: idx + 2,
+            task: row
+                .map_err(csv_error)
+                .and_then(|row| read_row(&row, &fields))
+                .and_then(TaskDraft::into_task),
+            archived: false,
+        })
+        .collect())
+}
+
+fn read_row(row: &csv::StringRecord, fields: &[Option<TaskField>]) -> Result<TaskDraft, TaskError> {
+    let mut draft = TaskDraft::default();
+
+    for (value, field) in row.iter().zip(fields) {
+        let Some(field) = field else { continue };
+        if value.is_empty() {
+            continue;
+        }
+
+        match field {
+            TaskField::Id => draft.id = Some(value.to_string()),
+            TaskField::Name => draft.name = value.to_string(),
+            TaskField::Description => draft.description = value.to_string(),
+            TaskField::Status => {
+                draft.status = Some(value.parse::<Status>().map_err(TaskError::InvalidInput)?)
+            }
+            TaskField::Created => draft.created = Some(parse_time(value)?),
+            TaskField::Due => draft.due = Some(parse_time(value)?),
+            TaskField::Completed => draft.completed = Some(parse_time(value)?),
+            TaskField::Tags => {
+                draft.tags = value
+                    .split(|c: char| c.is_whitespace() || c == ',')
+                    .map(|tag| tag.trim_start_matches('+'))
+                    .filter(|tag| !tag.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            }
+        }
+    }
+    Ok(draft)
+}
+
+fn field_for_header(header: &str, mapping: &[(String, TaskField)]) -> Option<TaskField> {
+    if let Some((_, field)) = mapping
+        .iter()
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case(header))
+    {
+        return Some(*field);
+    }
+
+    let normalized = header.to_lowercase().replace(['_', '-'], " ");
+    match normalized.as_str() {
+        "id" | "uuid" | "task id" => Some(TaskField::Id),
+        "name" | "task" | "task name" | "title" | "summary" | "subject" => Some(TaskField::Name),
+        "description" | "desc" | "details" | "notes" | "note" => Some(TaskField::Description),
+        "status" | "state" => Some(TaskField::Status),
+        "created" | "created at" | "creation date" | "date created" => Some(TaskField::Created),
+        "due" | "due date" | "due at" | "deadline" => Some(TaskField::Due),
+        "completed" | "completed at" | "completion date" | "finished" => Some(TaskField::Completed),
+        "tags" | "tag" | "labels" | "label" => Some(TaskField::Tags),
+        _ => None,
+    }
+}
+
+fn field_value(task: &Task, field: TaskField) -> String {
+    match field {
+        TaskField::Id => task.id.clone(),
+        TaskField::Name => task.name.clone(),
+        TaskField::Description => task.description.clone(),
+        TaskField::Status => task.status.to_string(),
+        TaskField::Created => parse_db_datetime(&task.date)
+            .map(format_time)
+            .unwrap_or_else(|| task.date.clone()),
+        TaskField::Due => task.due_date.map(format_time).unwrap_or_default(),
+        TaskField::Completed => task.completed_at.map(format_time).unwrap_or_default(),
+        TaskField::Tags => task.tags.join(" "),
+    }
+}
+
+fn format_time(dt: DateTime<Utc>) -> String {
+    dt.with_timezone(&Local).format(TIME_FORMAT).to_string()
+}
+
+/// RFC 3339 timestamps, or anything `--due` accepts.
+fn parse_time(value: &str) -> Result<DateTime<Utc>, TaskError> {
+    match DateTime::parse_from_rfc3339(value) {
+        Ok(dt) => Ok(dt.with_timezone(&Utc)),
+        Err(_) => parse_due_date(value),
+    }
+}
+
+fn csv_error(err: csv::Error) -> TaskError {
+    TaskError::InvalidInput(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_escapes_and_round_trips() {
+        let mut task = Task::new("Buy milk, eggs".to_string(), None, None).unwrap();
+        task.description = "two \"large\" ones\nfrom the corner shop".to_string();
+        task.due_date = parse_db_datetime("2025-08-01 18:00:00");
+
+        for delimiter in [CSV, TSV] {
+            let out = export(std::slice::from_ref(&task), &[], delimiter).unwrap();
+            let records = parse(&out, delimiter, &[]).unwrap();
+            let parsed = records.into_iter().next().unwrap().task.unwrap();
+
+            assert_eq!(parsed.id, task.id);
+            assert_eq!(parsed.date, task.date);
+            assert_eq!(parsed.name, task.name);
+            assert_eq!(parsed.description, task.description);
+            assert_eq!(parsed.due_date, task.due_date);
+        }
+
+        let csv = export(&[task], &[TaskField::Name], CSV).unwrap();
+        assert_eq!(csv, "name\n\"Buy milk, eggs\"\n");
+    }
+
+    #[test]
+    fn test_parse_maps_headers_and_reports_bad_rows() {
+        let input = "Title,Deadline,Owner,Notes\n\
+                     Ship release,2025-08-01,ana,tag it\n\
+                     ,2025-08-02,bo,\n\
+                     Plan retro,someday,cy,\n";
+        let records = parse(input, CSV, &[]).unwrap();
+
+        let first = records[0].task.as_ref().unwrap();
+        assert_eq!(first.name, "Ship release");
+        assert_eq!(first.description, "tag it");
+        assert!(first.due_date.is_some());
+        assert!(records[1].task.is_err());
+        assert!(records[2].task.is_err());
+        assert_eq!(records[2].number, 4);
+    }
+
+    #[test]
+    fn test_parse_uses_explicit_mapping() {
+        let input = "Ticket\tPriority\nFix login\thigh\n";
+        assert!(parse(input, TSV, &[]).is_err());
+
+        let mapping = vec![("ticket".to_string(), TaskField::Name)];
+        let records = parse(input, TSV, &mapping).unwrap();
+        assert_eq!(records[0].task.as_ref().unwrap().name, "Fix login");
+    }
+}
@@ -0,0 +1,408 @@
+//! iCalendar (RFC 5545) VTODO components, so calendar clients can show
+//! tarea's due dates and tasks can be pulled in from them.
+//!
+//! A due date without a time of day is written as a `VALUE=DATE`, any other
+//! as a UTC date-time. Going the other way, plain days and floating times
+//! are local, and times with a `TZID` are taken as local too since tarea has
+//! no timezone database.
+
+use super::{ParsedRecord, TaskDraft};
+use crate::database::parse_db_datetime;
+use crate::types::{DuePrecision, Status, Task, TaskError};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+
+const UTC_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+const LOCAL_FORMAT: &str = "%Y%m%dT%H%M%S";
+const DATE_FORMAT: &str = "%Y%m%d";
+
+/// Lines longer than this many bytes are folded.
+const FOLD_WIDTH: usize = 75;
+
+/// iCalendar has no standby, so it is kept in a property of our own next to
+/// `STATUS:NEEDS-ACTION`.
+const STANDBY_PROPERTY: &str = "X-TAREA-STATUS";
+
+/// A VTODO for every task with a due date. Tasks without one have nothing
+/// to show on a calendar and are left out.
+pub fn export(tasks: &[Task]) -> String {
+    let stamp = Utc::now().format(UTC_FORMAT).to_string();
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//tarea//tarea//EN".to_string(),
+    ];
+
+    for task in tasks.iter().filter(|task| task.due_date.is_some()) {
+        lines.push("BEGIN:VTODO".to_string());
+        lines.push(format!("UID:{}", task.id));
+        lines.push(format!("DTSTAMP:{}", stamp));
+        if let Some(created) = parse_db_datetime(&task.date) {
+            lines.push(format!("CREATED:{}", created.format(UTC_FORMAT)));
+        }
+        lines.push(format!("SUMMARY:{}", escape(&task.name)));
+        if !task.description.is_empty() {
+            lines.push(format!("DESCRIPTION:{}", escape(&task.description)));
+        }
+        if let Some(due) = task.due_date {
+            lines.push(format!("DUE{}", format_due(due, task.due_precision)));
+        }
+        match task.status {
+            Status::Done => lines.push("STATUS:COMPLETED".to_string()),
+            Status::Pending => lines.push("STATUS:NEEDS-ACTION".to_string()),
+            Status::Standby => {
+                lines.push("STATUS:NEEDS-ACTION".to_string());
+                lines.push(format!("{}:STANDBY", STANDBY_PROPERTY));
+            }
+        }
+        if let Some(completed) = task.completed_at {
+            lines.push(format!("COMPLETED:{}", completed.format(UTC_FORMAT)));
+        }
+        if !task.tags.is_empty() {
+            let tags: Vec<String> = task.tags.iter().map(|t| escape(t)).collect();
+            lines.push(format!("CATEGORIES:{}", tags.join(",")));
+        }
+        lines.push("END:VTODO".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    lines.iter().map(|line| fold(line) + "\r\n").collect()
+}
+
+/// `;VALUE=DATE:20250801` for a plain day, `:20250801T140000Z` otherwise.
+fn format_due(due: DateTime<Utc>, precision: DuePrecision) -> String {
+    match precision {
+        DuePrecision::Day => {
+            let local = due.with_timezone(&Local);
+            format!(";VALUE=DATE:{}", local.format(DATE_FORMAT))
+        }
+        DuePrecision::Time => format!(":{}", due.format(UTC_FORMAT)),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Split a content line into lines of at most `FOLD_WIDTH` bytes, each
+/// continuation starting with a space.
+fn fold(line: &str) -> String {
+    let mut out = String::new();
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > FOLD_WIDTH {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out
+}
+
+/// Read every VTODO of a calendar, numbered from 1 in file order. Other
+/// components, and alarms nested in a VTODO, are skipped. A `CANCELLED`
+/// todo is imported into the archive.
+pub fn parse(input: &str) -> Result<Vec<ParsedRecord>, TaskError> {
+    let lines = unfold(input);
+    if !lines
+        .iter()
+        .any(|line| line.eq_ignore_ascii_case("BEGIN:VCALENDAR"))
+    {
+        return Err(TaskError::InvalidInput(
+            "Not an iCalendar file: no BEGIN:VCALENDAR".to_string(),
+        ));
+    }
+
+    let mut records = Vec::new();
+    let mut todo: Option<Vec<Property>> = None;
+    let mut nested = 0;
+
+    for line in &lines {
+        let Some(property) = Property::parse(line) else {
+            continue;
+        };
+        match (
+            property.name.as_str(),
+            property.value.to_uppercase().as_str(),
+        ) {
+            ("BEGIN", "VTODO") if todo.is_none() => todo = Some(Vec::new()),
+            ("BEGIN", _) if todo.is_some() => nested += 1,
+            ("END", "VTODO") if nested == 0 => {
+                if let Some(properties) = todo.take() {
+                    let (task, archived) = match read_todo(&properties) {
+                        Ok((task, archived)) => (Ok(task), archived),
+                        Err(e) => (Err(e), false),
+                    };
+                    records.push(ParsedRecord {
+                        number: records.len() + 1,
+                        task,
+                        archived,
+                    });
+                }
+            }
+            ("END", _) if todo.is_some() => nested -= 1,
+            _ => {
+                if let Some(properties) = todo.as_mut()
+                    && nested == 0
+                {
+                    properties.push(property);
+                }
+            }
+        }
+    }
+    Ok(records)
+}
+
+fn read_todo(properties: &[Property]) -> Result<(Task, bool), TaskError> {
+    let mut draft = TaskDraft::default();
+    let mut completed = false;
+    let mut cancelled = false;
+    let mut standby = false;
+
+    for property in properties {
+        let value = property.value.as_str();
+        match property.name.as_str() {
+            "UID" => draft.id = Some(value.to_string()),
+            "SUMMARY" => draft.name = unescape(value),
+            "DESCRIPTION" => draft.description = unescape(value),
+            "CREATED" => draft.created = Some(property.datetime()?),
+            "DUE" => {
+                draft.due = Some(property.datetime()?);
+                draft.due_precision = Some(if property.is_date() {
+                    DuePrecision::Day
+                } else {
+                    DuePrecision::Time
+                });
+            }
+            "COMPLETED" => draft.completed = Some(property.datetime()?),
+            "STATUS" => match value.to_uppercase().as_str() {
+                "COMPLETED" => completed = true,
+                "CANCELLED" => cancelled = true,
+                _ => {}
+            },
+            STANDBY_PROPERTY => standby = value.eq_ignore_ascii_case("STANDBY"),
+            "CATEGORIES" => draft.tags.extend(
+                split_list(value)
+                    .into_iter()
+                    .map(|tag| tag.split_whitespace().collect::<Vec<_>>().join("-"))
+                    .filter(|tag| !tag.is_empty()),
+            ),
+            _ => {}
+        }
+    }
+
+    draft.status = Some(if completed {
+        Status::Done
+    } else if standby {
+        Status::Standby
+    } else {
+        Status::Pending
+    });
+    Ok((draft.into_task()?, cancelled))
+}
+
+/// Join folded lines back up; accepts both CRLF and bare LF endings.
+fn unfold(input: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in input.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+/// One `NAME;PARAM=VALUE:value` content line.
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    fn parse(line: &str) -> Option<Self> {
+        // The value starts at the first colon outside a quoted parameter
+        let mut quoted = false;
+        let colon = line.char_indices().find_map(|(idx, c)| match c {
+            '"' => {
+                quoted = !quoted;
+                None
+            }
+            ':' if !quoted => Some(idx),
+            _ => None,
+        })?;
+
+        let mut head = line[..colon].split(';');
+        let name = head.next()?.trim().to_uppercase();
+        let params = head
+            .filter_map(|param| param.split_once('='))
+            .map(|(key, value)| (key.to_uppercase(), value.trim_matches('"').to_string()))
+            .collect();
+
+        Some(Property {
+            name,
+            params,
+            value: line[colon + 1..].to_string(),
+        })
+    }
+
+    fn param(&self, key: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Whether the value is a plain `DATE` rather than a `DATE-TIME`.
+    fn is_date(&self) -> bool {
+        self.param("VALUE") == Some("DATE") || self.value.trim().len() == 8
+    }
+
+    /// A `DATE` or `DATE-TIME` value as UTC.
+    fn datetime(&self) -> Result<DateTime<Utc>, TaskError> {
+        let value = self.value.trim();
+        let invalid = || TaskError::InvalidDate(format!("Invalid {} '{}'", self.name, value));
+
+        if let Ok(dt) = NaiveDateTime::parse_from_str(value, UTC_FORMAT) {
+            return Ok(dt.and_utc());
+        }
+        let naive = if self.is_date() {
+            NaiveDate::parse_from_str(value, DATE_FORMAT)
+                .map_err(|_| invalid())?
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+        } else {
+            NaiveDateTime::parse_from_str(value, LOCAL_FORMAT).map_err(|_| invalid())?
+        };
+        // Like `parse_due_date`, take the later instant when DST makes the
+        // local time ambiguous
+        Local
+            .from_local_datetime(&naive)
+            .latest()
+            .map(|dt| dt.with_timezone(&Utc))
+            .ok_or_else(invalid)
+    }
+}
+
+fn unescape(text: &str) -> String {
+    split_list(text).join(",")
+}
+
+/// Split a list value such as `CATEGORIES` on its unescaped commas,
+/// unescaping each item.
+fn split_list(value: &str) -> Vec<String> {
+    let mut items = vec![String::new()];
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        let item = items.last_mut().unwrap();
+        match c {
+            '\\' => match chars.next() {
+                Some('n') | Some('N') => item.push('\n'),
+                Some(escaped) => item.push(escaped),
+                None => {}
+            },
+            ',' => items.push(String::new()),
+            _ => item.push(c),
+        }
+    }
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::parse_due_date;
+
+    #[test]
+    fn test_export_vtodo() {
+        let mut task = Task::new("Book flights, hotel".to_string(), None, None).unwrap();
+        task.description = "window seat; no red-eye\n".to_string() + &"x".repeat(80);
+        task.due_date = parse_due_date("2025-08-01").ok();
+        task.due_precision = DuePrecision::Day;
+        task.tags = vec!["travel".to_string()];
+
+        let out = export(std::slice::from_ref(&task));
+        assert!(out.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(out.contains(&format!("\r\nUID:{}\r\n", task.id)));
+        assert!(out.contains("\r\nSUMMARY:Book flights\\, hotel\r\n"));
+        assert!(out.contains("\r\nDUE;VALUE=DATE:20250801\r\n"));
+        assert!(out.contains("\r\nSTATUS:NEEDS-ACTION\r\n"));
+        assert!(out.lines().all(|line| line.len() <= FOLD_WIDTH + 1));
+
+        let parsed = parse(&out).unwrap().remove(0).task.unwrap();
+        assert_eq!(parsed.id, task.id);
+        assert_eq!(parsed.date, task.date);
+        assert_eq!(parsed.name, task.name);
+        assert_eq!(parsed.description, task.description);
+        assert_eq!(parsed.due_date, task.due_date);
+        assert_eq!(parsed.due_precision, DuePrecision::Day);
+        assert_eq!(parsed.tags, task.tags);
+    }
+
+    #[test]
+    fn test_export_skips_tasks_without_a_due_date() {
+        let undated = Task::new("Someday".to_string(), None, None).unwrap();
+        let dated = Task::new(
+            "Pay rent".to_string(),
+            None,
+            parse_due_date("2025-08-01").ok(),
+        )
+        .unwrap();
+
+        let out = export(&[undated.clone(), dated.clone()]);
+        assert_eq!(out.matches("BEGIN:VTODO").count(), 1);
+        assert!(out.contains(&format!("\r\nUID:{}\r\n", dated.id)));
+        assert!(!out.contains(&undated.id));
+        assert!(export(&[undated]).ends_with("PRODID:-//tarea//tarea//EN\r\nEND:VCALENDAR\r\n"));
+    }
+
+    #[test]
+    fn test_export_keeps_a_midnight_time() {
+        let mut task = Task::new("Night deploy".to_string(), None, None).unwrap();
+        let (due, precision) = crate::utils::parse_due("2025-08-01 00:00").unwrap();
+        task.due_date = Some(due);
+        task.due_precision = precision;
+
+        let out = export(std::slice::from_ref(&task));
+        assert!(out.contains(&format!("\r\nDUE:{}\r\n", due.format(UTC_FORMAT))));
+
+        let parsed = parse(&out).unwrap().remove(0).task.unwrap();
+        assert_eq!(parsed.due_date, task.due_date);
+        assert_eq!(parsed.due_precision, DuePrecision::Time);
+    }
+
+    #[test]
+    fn test_parse_vtodos() {
+        let input = "BEGIN:VCALENDAR\n\
+                     BEGIN:VEVENT\nSUMMARY:Standup\nEND:VEVENT\n\
+                     BEGIN:VTODO\nUID:a\nSUMMARY:Pay rent\nDUE:20250801T090000Z\n\
+                     BEGIN:VALARM\nDESCRIPTION:reminder\nEND:VALARM\n\
+                     STATUS:COMPLETED\nCOMPLETED:20250731T180000Z\n\
+                     CATEGORIES:home,Big Bills\nEND:VTODO\n\
+                     BEGIN:VTODO\nUID:b\nSUMMARY:Old plan\nSTATUS:CANCELLED\n\
+                     DUE;TZID=Europe/Madrid:20250802T100000\nEND:VTODO\n\
+                     BEGIN:VTODO\nUID:c\nEND:VTODO\n\
+                     END:VCALENDAR\n";
+        let records = parse(input).unwrap();
+        assert_eq!(records.len(), 3);
+
+        let rent = records[0].task.as_ref().unwrap();
+        assert_eq!(rent.name, "Pay rent");
+        assert_eq!(rent.description, "");
+        assert_eq!(rent.status, Status::Done);
+        assert_eq!(rent.due_date, parse_db_datetime("2025-08-01 09:00:00"));
+        assert_eq!(rent.completed_at, parse_db_datetime("2025-07-31 18:00:00"));
+        assert_eq!(rent.tags, ["Big-Bills", "home"]);
+
+        let plan = records[1].task.as_ref().unwrap();
+        assert!(records[1].archived);
+        assert_eq!(plan.due_date, parse_due_date("2025-08-02 10:00").ok());
+
+        assert!(records[2].task.is_err());
+        assert!(parse("BEGIN:VTODO\nEND:VTODO\n").is_err());
+    }
+}
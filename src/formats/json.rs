@@ -0,0 +1,221 @@
+use super::{ParsedRecord, TaskDraft};
+use crate::database::parse_db_datetime;
+use crate::types::{ArchivedTask, DuePrecision, OutputFormat, Task, TaskError};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Stable JSON shape of a task for scripts and backups. Timestamps are
+/// RFC 3339 in UTC and absent ones are `null`. `due_precision` is `day` for
+/// a due date without a time of day, `time` otherwise, `every` is the
+/// recurrence rule as `--every` takes it and `priority` a letter from A to Z.
+/// Archived tasks also carry `deleted`, when they were archived, and go back
+/// to the archive on import. Only `name` is required when reading it back.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TaskRecord {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub status: String,
+    #[serde(default)]
+    pub created: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub due: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub due_precision: Option<DuePrecision>,
+    #[serde(default)]
+    pub completed: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub every: Option<String>,
+    #[serde(default)]
+    pub priority: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted: Option<DateTime<Utc>>,
+}
+
+impl From<&Task> for TaskRecord {
+    fn from(task: &Task) -> Self {
+        TaskRecord {
+            id: task.id.clone(),
+            name: task.name.clone(),
+            description: task.description.clone(),
+            status: task.status.to_string(),
+            created: parse_db_datetime(&task.date),
+            due: task.due_date,
+            due_precision: task.due_date.map(|_| task.due_precision),
+            completed: task.completed_at,
+            tags: task.tags.clone(),
+            every: task.recurrence.map(|r| r.to_string()),
+            priority: task.priority.map(|p| p.to_string()),
+            deleted: None,
+        }
+    }
+}
+
+impl From<&ArchivedTask> for TaskRecord {
+    fn from(archived: &ArchivedTask) -> Self {
+        TaskRecord {
+            deleted: Some(archived.deleted_at),
+            ..TaskRecord::from(&archived.task)
+        }
+    }
+}
+
+impl TaskRecord {
+    fn into_task(self) -> Result<Task, TaskError> {
+        let status = match self.status.as_str() {
+            "" => None,
+            s => Some(s.parse().map_err(TaskError::InvalidInput)?),
+        };
+        let recurrence = self
+            .every
+            .map(|rule| rule.parse().map_err(TaskError::InvalidInput))
+            .transpose()?;
+        let priority = self
+            .priority
+            .map(|letter| letter.parse().map_err(TaskError::InvalidInput))
+            .transpose()?;
+
+        TaskDraft {
+            id: Some(self.id),
+            name: self.name,
+            description: self.description,
+            status,
+            created: self.created,
+            due: self.due,
+            due_precision: self.due_precision,
+            completed: self.completed,
+            tags: self.tags,
+            recurrence,
+            priority,
+        }
+        .into_task()
+    }
+}
+
+/// The whole list as a pretty-printed array, the same shape `--json` prints,
+/// followed by the `archived` tasks.
+pub fn export(tasks: &[Task], archived: &[ArchivedTask]) -> Result<String, TaskError> {
+    let records: Vec<TaskRecord> = tasks
+        .iter()
+        .map(TaskRecord::from)
+        .chain(archived.iter().map(TaskRecord::from))
+        .collect();
+    to_json(&records, true)
+}
+
+/// Read an array of task objects, as written by `export` or `--json`.
+pub fn parse(input: &str) -> Result<Vec<ParsedRecord>, TaskError> {
+    let values: Vec<serde_json::Value> = serde_json::from_str(input)
+        .map_err(|e| TaskError::InvalidInput(format!("Invalid JSON: {e}")))?;
+
+    Ok(values
+        .into_iter()
+        .enumerate()
+        .map(|(idx, value)| {
+            let record = serde_json::from_value::<TaskRecord>(value)
+                .map_err(|e| TaskError::InvalidInput(e.to_string()));
+            ParsedRecord {
+                number: idx + 1,
+                archived: matches!(&record, Ok(record) if record.deleted.is_some()),
+                task: record.and_then(TaskRecord::into_task),
+            }
+        })
+        .collect())
+}
+
+/// Print `tasks` as one JSON array, or one object per line for JSON Lines.
+pub fn print_tasks(tasks: &[Task], format: OutputFormat) -> Result<(), TaskError> {
+    match format {
+        OutputFormat::Json => println!("{}", export(tasks, &[])?),
+        _ => {
+            for task in tasks {
+                println!("{}", to_json(&TaskRecord::from(task), false)?);
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn print_task(task: &Task, format: OutputFormat) -> Result<(), TaskError> {
+    let pretty = format == OutputFormat::Json;
+    println!("{}", to_json(&TaskRecord::from(task), pretty)?);
+    Ok(())
+}
+
+pub fn to_json<T: Serialize>(value: &T, pretty: bool) -> Result<String, TaskError> {
+    let json = if pretty {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    };
+    json.map_err(|e| TaskError::Io(e.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Status;
+
+    #[test]
+    fn test_task_record_uses_rfc3339_timestamps() {
+        let task = Task {
+            id: "2f3c1b9e-0000-4000-8000-000000000000".to_string(),
+            date: "2025-08-01 10:00:00".to_string(),
+            name: "Write report".to_string(),
+            description: String::new(),
+            status: Status::Done,
+            due_date: None,
+            due_horizon: None,
+            due_precision: DuePrecision::Time,
+            completed_at: parse_db_datetime("2025-08-02 09:30:00"),
+            tags: vec!["work".to_string()],
+            recurrence: None,
+            priority: None,
+        };
+
+        let json = serde_json::to_value(TaskRecord::from(&task)).unwrap();
+        assert_eq!(json["id"], task.id);
+        assert_eq!(json["status"], "done");
+        assert_eq!(json["created"], "2025-08-01T10:00:00Z");
+        assert_eq!(json["completed"], "2025-08-02T09:30:00Z");
+        assert!(json["due"].is_null());
+        assert!(json["due_precision"].is_null());
+        assert_eq!(json["tags"][0], "work");
+    }
+
+    #[test]
+    fn test_export_round_trips_through_parse() {
+        let mut task = Task::new("Renew passport".to_string(), None, None).unwrap();
+        task.description = "Needs photos".to_string();
+        task.due_date =
+            parse_db_datetime("2025-09-01 12:00:00").map(|due| DuePrecision::Day.normalize(due));
+        task.due_precision = DuePrecision::Day;
+        task.tags = vec!["admin".to_string()];
+
+        let records = parse(&export(std::slice::from_ref(&task), &[]).unwrap()).unwrap();
+        let parsed = records.into_iter().next().unwrap().task.unwrap();
+
+        assert_eq!(parsed.id, task.id);
+        assert_eq!(parsed.date, task.date);
+        assert_eq!(parsed.name, task.name);
+        assert_eq!(parsed.description, task.description);
+        assert_eq!(parsed.due_date, task.due_date);
+        assert_eq!(parsed.due_precision, DuePrecision::Day);
+        assert_eq!(parsed.tags, task.tags);
+    }
+
+    #[test]
+    fn test_parse_reports_bad_records_individually() {
+        let records = parse(r#"[{"name": "ok"}, {"name": "x", "status": "later"}, {}]"#).unwrap();
+
+        assert!(records[0].task.is_ok());
+        assert!(records[1].task.is_err());
+        assert!(records[2].task.is_err());
+        assert!(parse("not json").is_err());
+    }
+}
@@ -0,0 +1,284 @@
+//! GitHub-flavored Markdown checklists, for pasting into PR descriptions
+//! and meeting notes and for capturing checklists from them.
+
+use super::{ParsedRecord, TaskDraft};
+use crate::types::{DuePrecision, Status, Task};
+use crate::utils::parse_due;
+use chrono::{DateTime, Local, Utc};
+
+const STANDBY_MARK: &str = "(standby)";
+
+/// One `- [ ]` / `- [x]` item per task, with the description indented
+/// below it. `grouped` puts the items under a heading per status instead of
+/// a single list, where standby items are marked `(standby)`.
+pub fn export(tasks: &[Task], grouped: bool) -> String {
+    if !grouped {
+        return tasks.iter().map(|task| item(task, true)).collect();
+    }
+
+    let mut out = String::new();
+    for status in [Status::Pending, Status::Standby, Status::Done] {
+        let items: String = tasks
+            .iter()
+            .filter(|task| task.status == status)
+            .map(|task| item(task, false))
+            .collect();
+        if items.is_empty() {
+            continue;
+        }
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&format!("## {}\n\n{}", heading(&status), items));
+    }
+    out
+}
+
+fn item(task: &Task, mark_standby: bool) -> String {
+    let check = if task.status == Status::Done {
+        'x'
+    } else {
+        ' '
+    };
+    let mut line = format!("- [{}] {}", check, task.name);
+    if let Some(due) = task.due_date {
+        line.push_str(&format!(" (due {})", format_due(due, task.due_precision)));
+    }
+    if mark_standby && task.status == Status::Standby {
+        line.push_str(&format!(" {}", STANDBY_MARK));
+    }
+    line.push('\n');
+
+    for desc_line in task.description.lines() {
+        if desc_line.trim().is_empty() {
+            line.push('\n');
+        } else {
+            line.push_str(&format!("  {}\n", desc_line));
+        }
+    }
+    line
+}
+
+fn heading(status: &Status) -> &'static str {
+    match status {
+        Status::Pending => "Pending",
+        Status::Standby => "Standby",
+        Status::Done => "Done",
+    }
+}
+
+/// Local date, with the time unless the task is due on a whole day.
+fn format_due(due: DateTime<Utc>, precision: DuePrecision) -> String {
+    let local = due.with_timezone(&Local);
+    match precision {
+        DuePrecision::Day => local.format("%Y-%m-%d").to_string(),
+        DuePrecision::Time => local.format("%Y-%m-%d %H:%M").to_string(),
+    }
+}
+
+/// Read every checklist item, at any nesting depth, as a task: checked ones
+/// as done, unchecked ones as pending, or standby when marked `(standby)`
+/// or under a "Standby" heading. Indented text right below an item becomes
+/// its description and a trailing `(due …)` its due date. Everything else is
+/// ignored. Items are numbered by their line in the file.
+pub fn parse(input: &str) -> Vec<ParsedRecord> {
+    let mut records = Vec::new();
+    let mut current: Option<(usize, TaskDraft)> = None;
+    let mut section = Status::Pending;
+
+    for (idx, line) in input.lines().enumerate() {
+        if let Some((checked, text)) = checklist_item(line) {
+            records.extend(current.take().map(finish));
+            current = Some((idx + 1, draft(checked, text, &section)));
+            continue;
+        }
+
+        let indented = line.starts_with(' ') || line.starts_with('\t');
+        match current.as_mut() {
+            Some((_, draft)) if indented => {
+                if !draft.description.is_empty() {
+                    draft.description.push('\n');
+                }
+                draft.description.push_str(line.trim());
+            }
+            Some((_, draft)) if line.trim().is_empty() => {
+                if !draft.description.is_empty() {
+                    draft.description.push('\n');
+                }
+            }
+            _ => {
+                records.extend(current.take().map(finish));
+                if let Some(title) = line.trim_start().strip_prefix('#') {
+                    section = section_status(title);
+                }
+            }
+        }
+    }
+    records.extend(current.take().map(finish));
+    records
+}
+
+fn finish((number, mut draft): (usize, TaskDraft)) -> ParsedRecord {
+    // Blank lines between description paragraphs are kept, trailing ones not
+    draft.description = draft.description.trim_end().to_string();
+    ParsedRecord {
+        number,
+        task: draft.into_task(),
+        archived: false,
+    }
+}
+
+/// `(checked, text)` of a `- [ ] text` line; `*` and `+` bullets count too.
+fn checklist_item(line: &str) -> Option<(bool, &str)> {
+    let rest = line.trim_start().strip_prefix(['-', '*', '+'])?;
+    let rest = rest.strip_prefix(' ')?.trim_start();
+    let checked = match rest.get(..3)? {
+        "[ ]" => false,
+        "[x]" | "[X]" => true,
+        _ => return None,
+    };
+    Some((checked, rest[3..].trim()))
+}
+
+fn draft(checked: bool, text: &str, section: &Status) -> TaskDraft {
+    let (text, marked_standby) = match text.strip_suffix(STANDBY_MARK) {
+        Some(rest) => (rest.trim_end(), true),
+        None => (text, false),
+    };
+    let (name, due) = split_due(text);
+    let (due, due_precision) = due.unzip();
+    let status = if checked {
+        Status::Done
+    } else if marked_standby || *section == Status::Standby {
+        Status::Standby
+    } else {
+        Status::Pending
+    };
+
+    TaskDraft {
+        name: name.to_string(),
+        status: Some(status),
+        due,
+        due_precision,
+        ..Default::default()
+    }
+}
+
+/// Split off a trailing `(due …)`. One that doesn't parse as a date stays
+/// part of the name.
+fn split_due(text: &str) -> (&str, Option<(DateTime<Utc>, DuePrecision)>) {
+    if let Some(open) = text.rfind("(due ")
+        && let Some(date) = text[open + 5..].strip_suffix(')')
+        && let Ok(due) = parse_due(date)
+    {
+        return (text[..open].trim_end(), Some(due));
+    }
+    (text, None)
+}
+
+/// Unchecked items under a "Standby" heading are on standby; any other
+/// heading switches back to pending.
+fn section_status(title: &str) -> Status {
+    match title.trim_start_matches('#').trim().to_lowercase().as_str() {
+        "standby" => Status::Standby,
+        _ => Status::Pending,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::parse_due_date;
+
+    fn task(name: &str, status: Status, due: Option<&str>) -> Task {
+        let mut task = Task::new(name.to_string(), None, None).unwrap();
+        task.status = status;
+        if let Some((due, precision)) = due.map(|d| parse_due(d).unwrap()) {
+            task.due_date = Some(due);
+            task.due_precision = precision;
+        }
+        task
+    }
+
+    #[test]
+    fn test_export_checklist() {
+        let mut first = task("Write report", Status::Pending, Some("2025-08-01"));
+        first.description = "Q3 numbers\n\nAsk finance".to_string();
+        let tasks = [first, task("Buy milk", Status::Done, None)];
+
+        assert_eq!(
+            export(&tasks, false),
+            "- [ ] Write report (due 2025-08-01)\n  Q3 numbers\n\n  Ask finance\n- [x] Buy milk\n"
+        );
+        assert_eq!(
+            export(
+                &[task("Upgrade CI", Status::Standby, Some("2025-08-01"))],
+                false
+            ),
+            "- [ ] Upgrade CI (due 2025-08-01) (standby)\n"
+        );
+        assert_eq!(
+            export(&tasks, true),
+            "## Pending\n\n- [ ] Write report (due 2025-08-01)\n  Q3 numbers\n\n  Ask finance\n\
+             \n## Done\n\n- [x] Buy milk\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_checklist() {
+        let input = "# Standup notes\n\
+                     \n\
+                     - [ ] Review PR (due 2025-08-01 14:30)\n  \
+                       needs a second look\n\
+                     * [X] Deploy staging\n\
+                     - plain bullet, ignored\n\
+                     ## Standby\n\
+                     \x20 - [ ] Upgrade CI (due whenever)\n\
+                     - [ ]\n";
+        let records = parse(input);
+        assert_eq!(records.len(), 4);
+
+        let review = records[0].task.as_ref().unwrap();
+        assert_eq!(records[0].number, 3);
+        assert_eq!(review.name, "Review PR");
+        assert_eq!(review.description, "needs a second look");
+        assert_eq!(review.due_date, parse_due_date("2025-08-01 14:30").ok());
+        assert_eq!(review.status, Status::Pending);
+
+        assert_eq!(records[1].task.as_ref().unwrap().status, Status::Done);
+
+        let upgrade = records[2].task.as_ref().unwrap();
+        assert_eq!(upgrade.name, "Upgrade CI (due whenever)");
+        assert_eq!(upgrade.status, Status::Standby);
+
+        assert!(records[3].task.is_err());
+    }
+
+    #[test]
+    fn test_export_round_trips() {
+        let mut first = task("One", Status::Pending, Some("2025-08-01 09:15"));
+        first.description = "first paragraph\n\nsecond one".to_string();
+        let tasks = [
+            first,
+            task("Midnight call", Status::Pending, Some("2025-08-02 00:00")),
+            task("Two", Status::Standby, None),
+            task("Three", Status::Done, None),
+            task("Four", Status::Standby, Some("2025-08-03")),
+        ];
+        for grouped in [true, false] {
+            let parsed: Vec<Task> = parse(&export(&tasks, grouped))
+                .into_iter()
+                .map(|record| record.task.unwrap())
+                .collect();
+            assert_eq!(parsed.len(), tasks.len());
+
+            for before in &tasks {
+                let after = parsed.iter().find(|t| t.name == before.name).unwrap();
+                assert_eq!(before.description, after.description);
+                assert_eq!(before.status, after.status, "{}", before.name);
+                assert_eq!(before.due_date, after.due_date);
+                assert_eq!(before.due_precision, after.due_precision);
+            }
+        }
+    }
+}
@@ -0,0 +1,383 @@
+//! Machine-readable representations of tasks, and the shared plumbing
+//! behind `--export` and `--import`.
+
+pub mod delimited;
+pub mod ical;
+pub mod json;
+pub mod markdown;
+pub mod org;
+pub mod taskwarrior;
+pub mod todotxt;
+
+use crate::database::{TaskManager, format_db_datetime};
+use crate::types::{
+    ArchivedTask, ConflictStrategy, DataFormat, DuePrecision, ExportOptions, ImportOptions,
+    Priority, Recurrence, Status, Task, TaskError,
+};
+use crate::utils::{validate_tag_name, validate_task_id, validate_task_name};
+use chrono::{DateTime, Utc};
+use std::fmt;
+use uuid::Uuid;
+
+/// Render every task in `format`. Only JSON and Taskwarrior keep `archived`
+/// ones, the latter as deleted tasks.
+pub fn export(
+    format: DataFormat,
+    tasks: &[Task],
+    archived: &[ArchivedTask],
+    options: &ExportOptions,
+) -> Result<String, TaskError> {
+    match format {
+        DataFormat::Csv => delimited::export(tasks, &options.columns, delimited::CSV),
+        DataFormat::Ical => Ok(ical::export(tasks)),
+        DataFormat::Json => json::export(tasks, archived),
+        DataFormat::Markdown => Ok(markdown::export(tasks, options.group_by_status)),
+        DataFormat::Org => Ok(org::export(tasks)),
+        DataFormat::Taskwarrior => taskwarrior::export(tasks, archived),
+        DataFormat::TodoTxt => Ok(todotxt::export(tasks)),
+        DataFormat::Tsv => delimited::export(tasks, &options.columns, delimited::TSV),
+    }
+}
+
+/// Read the records of an import file. Errors in single records are kept
+/// per record; only an unreadable file as a whole fails.
+pub fn parse(
+    format: DataFormat,
+    input: &str,
+    options: &ImportOptions,
+) -> Result<Vec<ParsedRecord>, TaskError> {
+    match format {
+        DataFormat::Csv => delimited::parse(input, delimited::CSV, &options.mapping),
+        DataFormat::Ical => ical::parse(input),
+        DataFormat::Json => json::parse(input),
+        DataFormat::Markdown => Ok(markdown::parse(input)),
+        DataFormat::Org => Ok(org::parse(input)),
+        DataFormat::Taskwarrior => taskwarrior::parse(input),
+        DataFormat::TodoTxt => Ok(todotxt::parse(input)),
+        DataFormat::Tsv => delimited::parse(input, delimited::TSV, &options.mapping),
+    }
+}
+
+/// One record of an import file, numbered from 1 in file order.
+pub struct ParsedRecord {
+    pub number: usize,
+    pub task: Result<Task, TaskError>,
+    /// Deleted in the app it came from, so imported straight into the
+    /// archive.
+    pub archived: bool,
+}
+
+/// Task fields as an importer read them. `into_task` validates them and
+/// fills in whatever the file left out.
+#[derive(Debug, Default)]
+pub struct TaskDraft {
+    pub id: Option<String>,
+    pub name: String,
+    pub description: String,
+    pub status: Option<Status>,
+    pub created: Option<DateTime<Utc>>,
+    pub due: Option<DateTime<Utc>>,
+    /// Guessed from the due time of day when the file doesn't say.
+    pub due_precision: Option<DuePrecision>,
+    pub completed: Option<DateTime<Utc>>,
+    pub tags: Vec<String>,
+    pub recurrence: Option<Recurrence>,
+    pub priority: Option<Priority>,
+}
+
+impl TaskDraft {
+    pub fn into_task(self) -> Result<Task, TaskError> {
+        let name = self.name.trim().to_string();
+        validate_task_name(&name)?;
+        let id = self.id.filter(|id| !id.trim().is_empty());
+        if let Some(id) = &id {
+            validate_task_id(id)?;
+        }
+        for tag in &self.tags {
+            validate_tag_name(tag)?;
+        }
+
+        let status = self.status.unwrap_or(Status::Pending);
+        let completed_at = match status {
+            Status::Done => self.completed,
+            _ => None,
+        };
+
+        let mut tags = self.tags;
+        tags.sort();
+        tags.dedup();
+
+        let created = self.created.unwrap_or_else(Utc::now);
+        let due_precision = self
+            .due_precision
+            .or_else(|| self.due.map(DuePrecision::guess))
+            .unwrap_or_default();
+        let due = self.due.map(|due| due_precision.normalize(due));
+        Ok(Task {
+            id: id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+            date: format_db_datetime(created),
+            name,
+            description: self.description,
+            status,
+            due_date: due,
+            due_horizon: due.map(|due| due - created),
+            due_precision,
+            completed_at,
+            tags,
+            recurrence: self.recurrence,
+            priority: self.priority,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImportAction {
+    Add,
+    Skip,
+    Overwrite,
+    Rename,
+}
+
+impl fmt::Display for ImportAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            ImportAction::Add => "add",
+            ImportAction::Skip => "skip",
+            ImportAction::Overwrite => "overwrite",
+            ImportAction::Rename => "rename",
+        };
+        f.pad(s)
+    }
+}
+
+/// Checklist items carry no ids, so give each one the id of an existing task
+/// with the same name. Importing a file again then runs into the tasks it
+/// added the first time, and `--on-conflict` decides what happens to them.
+pub fn match_ids_by_name(
+    manager: &TaskManager,
+    tasks: &mut [(Task, bool)],
+) -> Result<(), TaskError> {
+    for (task, _) in tasks {
+        if let Some(id) = manager.task_id_by_name(&task.name)? {
+            task.id = id;
+        }
+    }
+    Ok(())
+}
+
+pub struct PlannedImport {
+    pub task: Task,
+    pub action: ImportAction,
+    pub archived: bool,
+}
+
+/// Decide what happens to each imported task. A task conflicts when its id
+/// already exists, live or archived, or appeared earlier in the same file.
+/// Renamed tasks get their new id here so a dry run shows it. Each task
+/// comes with whether it goes to the archive.
+pub fn plan_import(
+    manager: &TaskManager,
+    tasks: Vec<(Task, bool)>,
+    strategy: ConflictStrategy,
+) -> Result<Vec<PlannedImport>, TaskError> {
+    let mut seen: Vec<String> = Vec::new();
+    let mut plan = Vec::new();
+
+    for (mut task, archived) in tasks {
+        let in_file = seen.contains(&task.id);
+        let conflict = in_file || manager.has_task_id(&task.id)?;
+
+        let action = match (conflict, strategy) {
+            (false, _) => ImportAction::Add,
+            (true, ConflictStrategy::Skip) => ImportAction::Skip,
+            // Two records of one file can't both overwrite the same task
+            (true, ConflictStrategy::Overwrite) if in_file => ImportAction::Skip,
+            (true, ConflictStrategy::Overwrite) => ImportAction::Overwrite,
+            (true, ConflictStrategy::Rename) => {
+                task.id = Uuid::new_v4().to_string();
+                ImportAction::Rename
+            }
+        };
+
+        seen.push(task.id.clone());
+        plan.push(PlannedImport {
+            task,
+            action,
+            archived,
+        });
+    }
+    Ok(plan)
+}
+
+/// Carry out `plan` as a single undo step.
+pub fn apply_import(manager: &TaskManager, plan: &[PlannedImport]) -> Result<(), TaskError> {
+    manager.batch(|| {
+        for entry in plan {
+            match entry.action {
+                ImportAction::Add | ImportAction::Rename => manager.add_task(entry.task.clone())?,
+                ImportAction::Overwrite => manager.overwrite_task(&entry.task)?,
+                ImportAction::Skip => continue,
+            }
+            if entry.archived {
+                manager.delete_task_by_id(&entry.task.id)?;
+            }
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{StatusFilter, TaskFilter};
+    use rusqlite::Connection;
+
+    fn manager() -> TaskManager {
+        TaskManager::from_connection(Connection::open_in_memory().unwrap()).unwrap()
+    }
+
+    fn draft(id: &str, name: &str) -> Task {
+        TaskDraft {
+            id: Some(id.to_string()),
+            name: name.to_string(),
+            ..Default::default()
+        }
+        .into_task()
+        .unwrap()
+    }
+
+    fn names(manager: &TaskManager) -> Vec<String> {
+        let filter = TaskFilter::from(StatusFilter::All).sorted_by("name".parse().unwrap());
+        manager
+            .list_tasks(filter)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.name)
+            .collect()
+    }
+
+    #[test]
+    fn test_draft_validates_and_fills_defaults() {
+        let task = TaskDraft {
+            name: "  Water plants ".to_string(),
+            completed: Some(Utc::now()),
+            tags: vec!["home".to_string(), "home".to_string()],
+            ..Default::default()
+        }
+        .into_task()
+        .unwrap();
+
+        assert_eq!(task.name, "Water plants");
+        assert_eq!(task.status, Status::Pending);
+        assert_eq!(task.completed_at, None);
+        assert_eq!(task.tags, ["home"]);
+        assert!(!task.id.is_empty());
+
+        assert!(TaskDraft::default().into_task().is_err());
+    }
+
+    #[test]
+    fn test_draft_rejects_unusable_ids() {
+        let with_id = |id: &str| TaskDraft {
+            id: Some(id.to_string()),
+            name: "Task".to_string(),
+            ..Default::default()
+        };
+
+        assert!(with_id("4f2c@calendar.example.com").into_task().is_ok());
+        assert!(with_id("tâche-1").into_task().is_err());
+        assert!(with_id("two words").into_task().is_err());
+        assert!(with_id(&"a".repeat(200)).into_task().is_err());
+    }
+
+    #[test]
+    fn test_import_conflict_strategies() {
+        for (strategy, expected) in [
+            (ConflictStrategy::Skip, vec!["Local", "Other"]),
+            (ConflictStrategy::Overwrite, vec!["Imported", "Other"]),
+            (ConflictStrategy::Rename, vec!["Imported", "Local", "Other"]),
+        ] {
+            let manager = manager();
+            manager.add_task(draft("a", "Local")).unwrap();
+
+            let incoming = vec![
+                (draft("a", "Imported"), false),
+                (draft("b", "Other"), false),
+            ];
+            let plan = plan_import(&manager, incoming, strategy).unwrap();
+            apply_import(&manager, &plan).unwrap();
+
+            assert_eq!(names(&manager), expected, "{strategy:?}");
+        }
+    }
+
+    #[test]
+    fn test_import_is_one_undo_step() {
+        let manager = manager();
+        let plan = plan_import(
+            &manager,
+            vec![(draft("a", "One"), false), (draft("b", "Two"), true)],
+            ConflictStrategy::Skip,
+        )
+        .unwrap();
+        apply_import(&manager, &plan).unwrap();
+        assert_eq!(names(&manager), ["One"]);
+        assert_eq!(manager.list_archived().unwrap().len(), 1);
+
+        manager.undo().unwrap();
+        assert!(names(&manager).is_empty());
+    }
+
+    #[test]
+    fn test_json_backup_restores_the_archive() {
+        let source = manager();
+        source.add_task(draft("a", "Live")).unwrap();
+        source.add_task(draft("b", "Gone")).unwrap();
+        source.delete_task_by_id("b").unwrap();
+
+        let tasks = source.list_tasks(StatusFilter::All).unwrap();
+        let archived = source.list_archived().unwrap();
+        let out = export(
+            DataFormat::Json,
+            &tasks,
+            &archived,
+            &ExportOptions::default(),
+        )
+        .unwrap();
+
+        let restored = manager();
+        let incoming = json::parse(&out)
+            .unwrap()
+            .into_iter()
+            .map(|record| (record.task.unwrap(), record.archived))
+            .collect();
+        let plan = plan_import(&restored, incoming, ConflictStrategy::Skip).unwrap();
+        apply_import(&restored, &plan).unwrap();
+
+        assert_eq!(names(&restored), ["Live"]);
+        let archive = restored.list_archived().unwrap();
+        assert_eq!(archive.len(), 1);
+        assert_eq!(archive[0].task.id, "b");
+        assert_eq!(archive[0].task.name, "Gone");
+    }
+
+    #[test]
+    fn test_reimporting_a_checklist_adds_nothing() {
+        let manager = manager();
+        let checklist = "- [ ] Write report\n- [x] Buy milk\n";
+        let import = |strategy| {
+            let mut tasks: Vec<(Task, bool)> = markdown::parse(checklist)
+                .into_iter()
+                .map(|record| (record.task.unwrap(), record.archived))
+                .collect();
+            match_ids_by_name(&manager, &mut tasks).unwrap();
+            let plan = plan_import(&manager, tasks, strategy).unwrap();
+            apply_import(&manager, &plan).unwrap();
+        };
+
+        import(ConflictStrategy::Skip);
+        import(ConflictStrategy::Skip);
+        import(ConflictStrategy::Overwrite);
+        assert_eq!(names(&manager), ["Buy milk", "Write report"]);
+    }
+}
@@ -0,0 +1,436 @@
+//! Org-mode headlines, for keeping tasks in Emacs agenda files:
+//!
+//! ```text
+//! * TODO [#A] Write report                                   :work:
+//! DEADLINE: <2025-08-01 Fri>
+//! :PROPERTIES:
+//! :ID:       2f3c1b9e-…
+//! :CREATED:  [2025-07-28 Mon 10:00]
+//! :END:
+//! Q3 numbers
+//! ```
+//!
+//! Standby is the `WAIT` keyword, which the export declares in a `#+TODO:`
+//! line. Priorities are priority cookies such as `[#A]`. Times are local,
+//! to the minute, like Org writes them. Tags Org can't hold in a headline,
+//! such as `my-tag`, go in a `TAREA_TAGS` property instead, and description
+//! lines starting with `*` are escaped with a comma as Org does in blocks.
+
+use super::{ParsedRecord, TaskDraft};
+use crate::database::parse_db_datetime;
+use crate::types::{DuePrecision, Status, Task, TaskError};
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, TimeZone, Utc};
+
+const KEYWORDS_LINE: &str = "#+TODO: TODO WAIT | DONE CANCELLED";
+
+/// Property holding the tags that aren't valid Org tags, space separated.
+const TAGS_PROPERTY: &str = "TAREA_TAGS";
+
+pub fn export(tasks: &[Task]) -> String {
+    let mut out = format!("{}\n\n", KEYWORDS_LINE);
+    for task in tasks {
+        out.push_str(&headline(task));
+    }
+    out
+}
+
+fn headline(task: &Task) -> String {
+    let keyword = match task.status {
+        Status::Pending => "TODO",
+        Status::Standby => "WAIT",
+        Status::Done => "DONE",
+    };
+    let mut title = format!("* {} ", keyword);
+    if let Some(priority) = task.priority {
+        title.push_str(&format!("[#{}] ", priority));
+    }
+    title.push_str(&task.name);
+
+    let (tags, other_tags): (Vec<&String>, Vec<&String>) =
+        task.tags.iter().partition(|tag| is_org_tag(tag));
+    if !tags.is_empty() {
+        let tags: Vec<&str> = tags.iter().map(|tag| tag.as_str()).collect();
+        title.push_str(&format!(" :{}:", tags.join(":")));
+    }
+
+    let mut lines = vec![title];
+    let mut planning = Vec::new();
+    if let Some(completed) = task.completed_at {
+        planning.push(format!(
+            "CLOSED: [{}]",
+            format_timestamp(completed, DuePrecision::Time)
+        ));
+    }
+    if let Some(due) = task.due_date {
+        planning.push(format!(
+            "DEADLINE: <{}>",
+            format_timestamp(due, task.due_precision)
+        ));
+    }
+    if !planning.is_empty() {
+        lines.push(planning.join(" "));
+    }
+
+    lines.push(":PROPERTIES:".to_string());
+    lines.push(format!(":ID:       {}", task.id));
+    if let Some(created) = parse_db_datetime(&task.date) {
+        lines.push(format!(
+            ":CREATED:  [{}]",
+            format_timestamp(created, DuePrecision::Time)
+        ));
+    }
+    if !other_tags.is_empty() {
+        let other_tags: Vec<&str> = other_tags.iter().map(|tag| tag.as_str()).collect();
+        lines.push(format!(":{}: {}", TAGS_PROPERTY, other_tags.join(" ")));
+    }
+    lines.push(":END:".to_string());
+    lines.extend(task.description.lines().map(|line| {
+        if is_escaped_star(line) || line.starts_with('*') {
+            format!(",{}", line)
+        } else {
+            line.to_string()
+        }
+    }));
+
+    lines.iter().map(|line| format!("{}\n", line)).collect()
+}
+
+/// Org tags only allow letters, digits, `_`, `@`, `#` and `%`.
+fn is_org_tag(tag: &str) -> bool {
+    tag.chars()
+        .all(|c| c.is_alphanumeric() || "_@#%".contains(c))
+}
+
+/// A line like `,* text` or `,,* text`: a description line that starts with
+/// `*`, with one comma more than it had.
+fn is_escaped_star(line: &str) -> bool {
+    line.starts_with(',') && line.trim_start_matches(',').starts_with('*')
+}
+
+/// `2025-08-01 Fri`, with the time unless it names a whole day.
+fn format_timestamp(dt: DateTime<Utc>, precision: DuePrecision) -> String {
+    let local = dt.with_timezone(&Local);
+    match precision {
+        DuePrecision::Day => local.format("%Y-%m-%d %a").to_string(),
+        DuePrecision::Time => local.format("%Y-%m-%d %a %H:%M").to_string(),
+    }
+}
+
+/// Read every headline with a TODO keyword, at any level, numbered by its
+/// line. `TODO` is pending, `WAIT`/`WAITING` standby and `DONE` done;
+/// `CANCELLED` headlines go to the archive. Headlines without a keyword are
+/// ignored along with their text.
+pub fn parse(input: &str) -> Vec<ParsedRecord> {
+    let mut records = Vec::new();
+    let mut current: Option<Entry> = None;
+
+    for (idx, line) in input.lines().enumerate() {
+        if let Some(stars) = line.strip_prefix('*')
+            && (stars.starts_with(' ') || stars.starts_with('*'))
+        {
+            records.extend(current.take().map(Entry::finish));
+            current = Entry::start(idx + 1, line.trim_start_matches('*'));
+            continue;
+        }
+        if let Some(entry) = current.as_mut() {
+            entry.body.push(line.to_string());
+        }
+    }
+    records.extend(current.take().map(Entry::finish));
+    records
+}
+
+/// A headline being read, with the lines below it.
+struct Entry {
+    number: usize,
+    draft: TaskDraft,
+    cancelled: bool,
+    body: Vec<String>,
+}
+
+impl Entry {
+    /// `None` for a headline without a TODO keyword.
+    fn start(number: usize, headline: &str) -> Option<Self> {
+        let headline = headline.trim();
+        let (keyword, mut title) = headline.split_once(' ').unwrap_or((headline, ""));
+        let (status, cancelled) = match keyword {
+            "TODO" => (Status::Pending, false),
+            "WAIT" | "WAITING" => (Status::Standby, false),
+            "DONE" => (Status::Done, false),
+            "CANCELLED" | "CANCELED" => (Status::Pending, true),
+            _ => return None,
+        };
+
+        let mut draft = TaskDraft {
+            status: Some(status),
+            ..Default::default()
+        };
+
+        title = title.trim();
+        if let Some(rest) = title.strip_prefix("[#")
+            && let Some((priority, rest)) = rest.split_once(']')
+            && let Ok(priority) = priority.parse()
+        {
+            draft.priority = Some(priority);
+            title = rest.trim_start();
+        }
+        if let Some((rest, tags)) = title.rsplit_once(char::is_whitespace)
+            && tags.len() > 2
+            && tags.starts_with(':')
+            && tags.ends_with(':')
+        {
+            draft.tags.extend(
+                tags.split(':')
+                    .filter(|t| !t.is_empty())
+                    .map(str::to_string),
+            );
+            title = rest.trim_end();
+        }
+        draft.name = title.to_string();
+
+        Some(Entry {
+            number,
+            draft,
+            cancelled,
+            body: Vec::new(),
+        })
+    }
+
+    fn finish(self) -> ParsedRecord {
+        let Entry {
+            number,
+            mut draft,
+            cancelled,
+            body,
+        } = self;
+
+        let task = read_body(&mut draft, &body).and_then(|_| draft.into_task());
+        ParsedRecord {
+            number,
+            task,
+            archived: cancelled,
+        }
+    }
+}
+
+/// Take the planning line and drawers off the start of `body`; what is left
+/// is the description. Its lines keep their indentation, less what they all
+/// share, which is how Org indents text under deeper headlines.
+fn read_body(draft: &mut TaskDraft, body: &[String]) -> Result<(), TaskError> {
+    let mut lines = body.iter().map(String::as_str).peekable();
+
+    if let Some(planning) = lines.next_if(|line| is_planning(line.trim())) {
+        let planning = planning.trim();
+        (draft.due, draft.due_precision) = keyword_timestamp(planning, "DEADLINE:")?.unzip();
+        draft.completed = keyword_timestamp(planning, "CLOSED:")?.map(|(dt, _)| dt);
+    }
+
+    let mut description = Vec::new();
+    let mut drawer: Option<&str> = None;
+    for line in lines {
+        let trimmed = line.trim();
+        if let Some(name) = drawer {
+            if trimmed.eq_ignore_ascii_case(":END:") {
+                drawer = None;
+            } else if name == "PROPERTIES" {
+                read_property(draft, trimmed)?;
+            }
+            continue;
+        }
+        if description.is_empty()
+            && let Some(name) = trimmed
+                .strip_prefix(':')
+                .and_then(|rest| rest.strip_suffix(':'))
+                .filter(|name| !name.is_empty() && !name.contains(char::is_whitespace))
+        {
+            drawer = Some(name);
+            continue;
+        }
+        if description.is_empty() && trimmed.is_empty() {
+            continue;
+        }
+        description.push(line.trim_end());
+    }
+
+    while description.last().is_some_and(|line| line.is_empty()) {
+        description.pop();
+    }
+    let indent = description
+        .iter()
+        .filter(|line| !line.is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    draft.description = description
+        .iter()
+        .map(|line| {
+            let line = line.get(indent..).unwrap_or(line.trim_start());
+            line.strip_prefix(',')
+                .filter(|_| is_escaped_star(line))
+                .unwrap_or(line)
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    Ok(())
+}
+
+fn is_planning(line: &str) -> bool {
+    ["DEADLINE:", "SCHEDULED:", "CLOSED:"]
+        .iter()
+        .any(|keyword| line.starts_with(keyword))
+}
+
+fn read_property(draft: &mut TaskDraft, line: &str) -> Result<(), TaskError> {
+    let Some((key, value)) = line.strip_prefix(':').and_then(|l| l.split_once(':')) else {
+        return Ok(());
+    };
+    match key.to_uppercase().as_str() {
+        "ID" => draft.id = Some(value.trim().to_string()),
+        "CREATED" => draft.created = Some(parse_timestamp(value.trim())?.0),
+        TAGS_PROPERTY => draft
+            .tags
+            .extend(value.split_whitespace().map(str::to_string)),
+        _ => {}
+    }
+    Ok(())
+}
+
+/// The timestamp following `keyword` on a planning line, if any.
+fn keyword_timestamp(
+    line: &str,
+    keyword: &str,
+) -> Result<Option<(DateTime<Utc>, DuePrecision)>, TaskError> {
+    let Some(start) = line.find(keyword) else {
+        return Ok(None);
+    };
+    let rest = line[start + keyword.len()..].trim_start();
+    let close = match rest.chars().next() {
+        Some('<') => '>',
+        Some('[') => ']',
+        _ => return Ok(None),
+    };
+    let end = rest.find(close).map_or(rest.len(), |end| end + 1);
+    parse_timestamp(&rest[..end]).map(Some)
+}
+
+/// `<2025-08-01 Fri>`, `[2025-08-01 Fri 14:30]` or with a repeater, in
+/// local time, and whether it had a time of day.
+fn parse_timestamp(stamp: &str) -> Result<(DateTime<Utc>, DuePrecision), TaskError> {
+    let invalid = || TaskError::InvalidDate(format!("Invalid Org timestamp '{}'", stamp));
+    let inner = stamp.trim_matches(|c| matches!(c, '<' | '>' | '[' | ']'));
+    let mut parts = inner.split_whitespace();
+
+    let date = parts
+        .next()
+        .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        .ok_or_else(invalid)?;
+    let time = parts.find_map(|part| NaiveTime::parse_from_str(part, "%H:%M").ok());
+    let precision = match time {
+        Some(_) => DuePrecision::Time,
+        None => DuePrecision::Day,
+    };
+
+    Local
+        .from_local_datetime(&date.and_time(time.unwrap_or(NaiveTime::MIN)))
+        .latest()
+        .map(|dt| (dt.with_timezone(&Utc), precision))
+        .ok_or_else(invalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::parse_due_date;
+
+    #[test]
+    fn test_export_headlines() {
+        let mut task = Task::new("Write report".to_string(), None, None).unwrap();
+        task.date = "2025-07-28 10:00:00".to_string();
+        task.description = "Q3 numbers".to_string();
+        task.due_date = parse_due_date("2025-08-01").ok();
+        task.due_precision = DuePrecision::Day;
+        task.tags = vec!["work".to_string()];
+        task.priority = "A".parse().ok();
+
+        let mut waiting = Task::new("Paint shed".to_string(), None, None).unwrap();
+        waiting.status = Status::Standby;
+        waiting.due_date = parse_due_date("2025-08-02 14:30").ok();
+
+        let out = export(&[task.clone(), waiting]);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0], KEYWORDS_LINE);
+        assert_eq!(lines[2], "* TODO [#A] Write report :work:");
+        assert_eq!(lines[3], "DEADLINE: <2025-08-01 Fri>");
+        assert_eq!(lines[5], format!(":ID:       {}", task.id));
+        assert_eq!(lines[8], "Q3 numbers");
+        assert_eq!(lines[9], "* WAIT Paint shed");
+        assert_eq!(lines[10], "DEADLINE: <2025-08-02 Sat 14:30>");
+    }
+
+    #[test]
+    fn test_parse_agenda_file() {
+        let input = "#+TITLE: Agenda\n\
+                     * Projects\n\
+                     Some notes, ignored\n\
+                     ** DONE Ship v2 :release:work:\n   \
+                        CLOSED: [2025-07-30 Wed 18:00] DEADLINE: <2025-08-01 Fri>\n   \
+                        :LOGBOOK:\n   \
+                        - State \"DONE\" from \"TODO\"\n   \
+                        :END:\n   \
+                        Went out on time.\n\n   \
+                        Thanks all.\n\n\
+                     ** WAITING [#B] Hear back from legal\n\
+                     *** CANCELLED Old idea\n\
+                     * TODO\n";
+        let records = parse(input);
+        assert_eq!(records.len(), 4);
+
+        let ship = records[0].task.as_ref().unwrap();
+        assert_eq!(records[0].number, 4);
+        assert_eq!(ship.name, "Ship v2");
+        assert_eq!(ship.status, Status::Done);
+        assert_eq!(ship.tags, ["release", "work"]);
+        assert_eq!(ship.due_date, parse_due_date("2025-08-01").ok());
+        assert_eq!(ship.due_precision, DuePrecision::Day);
+        assert_eq!(ship.completed_at, parse_due_date("2025-07-30 18:00").ok());
+        assert_eq!(ship.description, "Went out on time.\n\nThanks all.");
+
+        let legal = records[1].task.as_ref().unwrap();
+        assert_eq!(legal.status, Status::Standby);
+        assert!(legal.tags.is_empty());
+        assert_eq!(legal.priority, "B".parse().ok());
+
+        assert!(records[2].archived);
+        assert!(records[3].task.is_err());
+    }
+
+    #[test]
+    fn test_export_round_trips() {
+        let mut done = Task::new("Buy milk".to_string(), None, None).unwrap();
+        done.status = Status::Done;
+        done.completed_at = parse_due_date("2025-07-30 18:00").ok();
+        done.description =
+            "two litres:\n  * semi-skimmed\n* not a headline\n,* nor this\n\n\tthanks".to_string();
+        done.date = "2025-07-28 10:00:00".to_string();
+        done.tags = vec![
+            "home".to_string(),
+            "my-tag".to_string(),
+            "project:errands".to_string(),
+        ];
+
+        let out = export(std::slice::from_ref(&done));
+        assert!(out.contains("* DONE Buy milk :home:\n"));
+        assert!(out.contains("\n:TAREA_TAGS: my-tag project:errands\n"));
+        assert!(out.contains("\n,* not a headline\n,,* nor this\n"));
+
+        let records = parse(&out);
+        assert_eq!(records.len(), 1);
+        let parsed = records.into_iter().next().unwrap().task.unwrap();
+        assert_eq!(parsed.id, done.id);
+        assert_eq!(parsed.date, done.date);
+        assert_eq!(parsed.status, done.status);
+        assert_eq!(parsed.completed_at, done.completed_at);
+        assert_eq!(parsed.description, done.description);
+        assert_eq!(parsed.tags, done.tags);
+    }
+}
@@ -0,0 +1,319 @@
+//! Taskwarrior's `task export` JSON, for migrating from Taskwarrior and
+//! back. Its `description` is the task name and its annotations, one line
+//! each, the description. The project is kept as a `project:NAME` tag, and
+//! priorities keep their H, M and L letters. Archived tasks are exported as
+//! deleted ones.
+
+use super::json::to_json;
+use super::{ParsedRecord, TaskDraft};
+use crate::database::parse_db_datetime;
+use crate::types::{ArchivedTask, Priority, Status, Task, TaskError};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Taskwarrior's compact UTC timestamp, e.g. `20250801T220000Z`.
+const DATE_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// How Taskwarrior spells `wait:someday`. Standby tasks have no end date.
+const SOMEDAY: &str = "99991230T000000Z";
+
+/// Prefix of the tag that holds a task's Taskwarrior project.
+const PROJECT_TAG: &str = "project:";
+
+/// The fields of a Taskwarrior task tarea knows about. Others, like
+/// `urgency` or `recur`, are ignored when reading.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct TaskwarriorTask {
+    uuid: String,
+    description: String,
+    status: String,
+    entry: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    due: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    wait: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    project: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    priority: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    annotations: Vec<Annotation>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Annotation {
+    entry: Option<String>,
+    description: String,
+}
+
+impl From<&Task> for TaskwarriorTask {
+    fn from(task: &Task) -> Self {
+        let entry = parse_db_datetime(&task.date).map(format_date);
+        let (status, wait) = match task.status {
+            Status::Pending => ("pending", None),
+            Status::Done => ("completed", None),
+            Status::Standby => ("waiting", Some(SOMEDAY.to_string())),
+        };
+        let project = task
+            .tags
+            .iter()
+            .find_map(|tag| tag.strip_prefix(PROJECT_TAG));
+
+        TaskwarriorTask {
+            uuid: task.id.clone(),
+            description: task.name.clone(),
+            status: status.to_string(),
+            entry: entry.clone(),
+            due: task.due_date.map(format_date),
+            end: task.completed_at.map(format_date),
+            wait,
+            project: project.map(str::to_string),
+            priority: task.priority.map(|p| p.to_string()),
+            tags: task
+                .tags
+                .iter()
+                .filter(|tag| !tag.starts_with(PROJECT_TAG))
+                .cloned()
+                .collect(),
+            annotations: task
+                .description
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| Annotation {
+                    entry: entry.clone(),
+                    description: line.to_string(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl TaskwarriorTask {
+    /// The task, and whether it was deleted in Taskwarrior and so belongs in
+    /// the archive.
+    fn into_task(self) -> Result<(Task, bool), TaskError> {
+        let wait = self.wait.as_deref().map(parse_date).transpose()?;
+        let waiting = wait.is_some_and(|wait| wait > Utc::now());
+
+        let (status, archived) = match self.status.as_str() {
+            "pending" if waiting => (Status::Standby, false),
+            "pending" | "" => (Status::Pending, false),
+            "waiting" => (Status::Standby, false),
+            "completed" => (Status::Done, false),
+            "deleted" => (Status::Pending, true),
+            "recurring" => {
+                return Err(TaskError::InvalidInput(
+                    "Recurring templates are not imported, only their instances".to_string(),
+                ));
+            }
+            other => {
+                return Err(TaskError::InvalidInput(format!(
+                    "Unknown Taskwarrior status: {}",
+                    other
+                )));
+            }
+        };
+
+        let mut tags = self.tags;
+        tags.extend(
+            self.project
+                .filter(|p| !p.is_empty())
+                .map(|p| format!("{}{}", PROJECT_TAG, p)),
+        );
+        let priority = self
+            .priority
+            .filter(|p| !p.is_empty())
+            .map(|p| p.parse::<Priority>().map_err(TaskError::InvalidInput))
+            .transpose()?;
+
+        let task = TaskDraft {
+            id: Some(self.uuid),
+            name: self.description,
+            description: self
+                .annotations
+                .into_iter()
+                .map(|a| a.description)
+                .collect::<Vec<_>>()
+                .join("\n"),
+            status: Some(status),
+            created: self.entry.as_deref().map(parse_date).transpose()?,
+            due: self.due.as_deref().map(parse_date).transpose()?,
+            due_precision: None,
+            completed: self.end.as_deref().map(parse_date).transpose()?,
+            tags,
+            recurrence: None,
+            priority,
+        }
+        .into_task()?;
+        Ok((task, archived))
+    }
+}
+
+fn format_date(dt: DateTime<Utc>) -> String {
+    dt.format(DATE_FORMAT).to_string()
+}
+
+fn parse_date(s: &str) -> Result<DateTime<Utc>, TaskError> {
+    NaiveDateTime::parse_from_str(s, DATE_FORMAT)
+        .map(|naive| naive.and_utc())
+        .map_err(|_| TaskError::InvalidDate(format!("Invalid Taskwarrior date '{}'", s)))
+}
+
+/// Deleted at the time it was archived, whatever its status was.
+fn deleted(archived: &ArchivedTask) -> TaskwarriorTask {
+    TaskwarriorTask {
+        status: "deleted".to_string(),
+        end: Some(format_date(archived.deleted_at)),
+        wait: None,
+        ..TaskwarriorTask::from(&archived.task)
+    }
+}
+
+/// One object per line inside an array, like `task export` prints it.
+pub fn export(tasks: &[Task], archived: &[ArchivedTask]) -> Result<String, TaskError> {
+    let lines = tasks
+        .iter()
+        .map(TaskwarriorTask::from)
+        .chain(archived.iter().map(deleted))
+        .map(|task| to_json(&task, false))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(format!("[\n{}\n]\n", lines.join(",\n")))
+}
+
+/// Read `task export` output: a JSON array, or one object per line as older
+/// versions wrote it.
+pub fn parse(input: &str) -> Result<Vec<ParsedRecord>, TaskError> {
+    let values: Vec<serde_json::Value> = if input.trim_start().starts_with('[') {
+        serde_json::from_str(input)
+            .map_err(|e| TaskError::InvalidInput(format!("Invalid JSON: {e}")))?
+    } else {
+        input
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()
+            .map_err(|e| TaskError::InvalidInput(format!("Invalid JSON: {e}")))?
+    };
+
+    Ok(values
+        .into_iter()
+        .enumerate()
+        .map(|(idx, value)| {
+            let parsed = serde_json::from_value::<TaskwarriorTask>(value)
+                .map_err(|e| TaskError::InvalidInput(e.to_string()))
+                .and_then(TaskwarriorTask::into_task);
+            let archived = matches!(parsed, Ok((_, true)));
+            ParsedRecord {
+                number: idx + 1,
+                task: parsed.map(|(task, _)| task),
+                archived,
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = include_str!("fixtures/taskwarrior.json");
+
+    fn by_name<'a>(records: &'a [ParsedRecord], name: &str) -> &'a ParsedRecord {
+        records
+            .iter()
+            .find(|r| r.task.as_ref().is_ok_and(|t| t.name == name))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_taskwarrior_export() {
+        let records = parse(FIXTURE).unwrap();
+        assert_eq!(records.len(), 6);
+
+        let passport = by_name(&records, "Renew passport").task.as_ref().unwrap();
+        assert_eq!(passport.id, "5f1c0e2a-3b7e-4f5a-9c21-6d0e8a4b1f31");
+        assert_eq!(passport.date, "2025-07-02 08:15:00");
+        assert_eq!(passport.due_date, parse_db_datetime("2025-08-01 22:00:00"));
+        assert_eq!(passport.tags, ["errand", "project:admin"]);
+        assert_eq!(passport.priority, "H".parse().ok());
+
+        let taxes = by_name(&records, "File tax return").task.as_ref().unwrap();
+        assert_eq!(taxes.status, Status::Done);
+        assert_eq!(taxes.completed_at, parse_db_datetime("2025-04-15 17:00:00"));
+        assert_eq!(
+            taxes.description,
+            "receipts are in the blue folder\naccountant says April 10 at the latest"
+        );
+
+        let shed = by_name(&records, "Paint the shed").task.as_ref().unwrap();
+        assert_eq!(shed.status, Status::Standby);
+        let dentist = by_name(&records, "Book dentist").task.as_ref().unwrap();
+        assert_eq!(dentist.status, Status::Standby);
+
+        assert!(by_name(&records, "Old idea").archived);
+        assert!(records[5].task.is_err());
+    }
+
+    #[test]
+    fn test_export_round_trips_fixture() {
+        let (archived, tasks): (Vec<ParsedRecord>, Vec<ParsedRecord>) = parse(FIXTURE)
+            .unwrap()
+            .into_iter()
+            .filter(|r| r.task.is_ok())
+            .partition(|r| r.archived);
+        let tasks: Vec<Task> = tasks.into_iter().map(|r| r.task.unwrap()).collect();
+        let archived: Vec<ArchivedTask> = archived
+            .into_iter()
+            .map(|r| ArchivedTask {
+                task: r.task.unwrap(),
+                deleted_at: parse_db_datetime("2025-01-05 10:00:00").unwrap(),
+            })
+            .collect();
+
+        let out = export(&tasks, &archived).unwrap();
+        assert!(out.contains(r#""status":"waiting","entry":"20250601T100000Z","wait":"#));
+        assert!(out.contains(r#""project":"admin","priority":"H","tags":["errand"]"#));
+        assert!(
+            out.contains(
+                r#""status":"deleted","entry":"20250101T100000Z","end":"20250105T100000Z""#
+            )
+        );
+
+        let records = parse(&out).unwrap();
+        assert!(records.last().unwrap().archived);
+        let again: Vec<Task> = records
+            .into_iter()
+            .filter(|r| !r.archived)
+            .map(|r| r.task.unwrap())
+            .collect();
+        assert_eq!(again.len(), tasks.len());
+        for (before, after) in tasks.iter().zip(&again) {
+            assert_eq!(before.id, after.id);
+            assert_eq!(before.date, after.date);
+            assert_eq!(before.name, after.name);
+            assert_eq!(before.description, after.description);
+            assert_eq!(before.status, after.status);
+            assert_eq!(before.due_date, after.due_date);
+            assert_eq!(before.completed_at, after.completed_at);
+            assert_eq!(before.tags, after.tags);
+            assert_eq!(before.priority, after.priority);
+        }
+    }
+
+    #[test]
+    fn test_parse_one_object_per_line() {
+        let records = parse(include_str!("fixtures/taskwarrior_lines.json")).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].task.as_ref().unwrap().status, Status::Pending);
+
+        let books = records[1].task.as_ref().unwrap();
+        assert_eq!(books.status, Status::Done);
+        assert_eq!(books.tags, ["errand"]);
+    }
+}
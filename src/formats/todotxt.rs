@@ -0,0 +1,272 @@
+//! The todo.txt format (<https://github.com/todotxt/todo.txt>), one task per
+//! line:
+//!
+//! ```text
+//! (A) 2025-07-28 Call the bank +finance @phone due:2025-08-01 id:…
+//! x 2025-07-30 2025-07-28 Renew passport +admin id:…
+//! ```
+//!
+//! `+project` words become tags and `@context` words tags starting with `@`.
+//! `(A)` is the task priority, written as `pri:A` on completed tasks like
+//! todo.txt apps do. Standby tasks carry `status:standby`. Descriptions go
+//! in a `desc:` value with spaces, newlines and `%` percent-encoded, and due
+//! dates keep only their day.
+
+use super::{ParsedRecord, TaskDraft};
+use crate::database::parse_db_datetime;
+use crate::types::{Priority, Status, Task, TaskError};
+use crate::utils::{parse_due, parse_due_date};
+use chrono::{DateTime, Local, NaiveDate, Utc};
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+pub fn export(tasks: &[Task]) -> String {
+    tasks.iter().map(|task| line(task) + "\n").collect()
+}
+
+fn line(task: &Task) -> String {
+    let mut words: Vec<String> = Vec::new();
+
+    if task.status == Status::Done {
+        words.push("x".to_string());
+        if let Some(completed) = task.completed_at {
+            words.push(format_date(completed));
+        }
+    } else if let Some(priority) = task.priority {
+        words.push(format!("({})", priority));
+    }
+    // A done task without a completion date can't carry a creation date
+    if task.status != Status::Done || task.completed_at.is_some() {
+        words.extend(parse_db_datetime(&task.date).map(format_date));
+    }
+
+    words.push(task.name.clone());
+    for tag in &task.tags {
+        if tag.starts_with('@') {
+            words.push(tag.clone());
+        } else {
+            words.push(format!("+{}", tag));
+        }
+    }
+
+    if let Some(due) = task.due_date {
+        words.push(format!("due:{}", format_date(due)));
+    }
+    // Open tasks already show it as `(A)`
+    if task.status == Status::Done
+        && let Some(priority) = task.priority
+    {
+        words.push(format!("pri:{}", priority));
+    }
+    if task.status == Status::Standby {
+        words.push("status:standby".to_string());
+    }
+    if !task.description.is_empty() {
+        words.push(format!("desc:{}", encode(&task.description)));
+    }
+    words.push(format!("id:{}", task.id));
+    words.join(" ")
+}
+
+/// Percent-encode whatever would end or break a `key:value` word.
+fn encode(s: &str) -> String {
+    let mut out = String::new();
+    for c in s.chars() {
+        match c {
+            '%' => out.push_str("%25"),
+            ' ' => out.push_str("%20"),
+            '\t' => out.push_str("%09"),
+            '\n' => out.push_str("%0A"),
+            '\r' => out.push_str("%0D"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Undo `encode`. A `%` that doesn't start a valid escape is kept as is.
+fn decode(s: &str) -> String {
+    let mut bytes = Vec::new();
+    let mut rest = s.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        let escaped = tail
+            .get(..2)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) if b == b'%' => {
+                bytes.push(byte);
+                rest = &tail[2..];
+            }
+            _ => {
+                bytes.push(b);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+fn format_date(dt: DateTime<Utc>) -> String {
+    dt.with_timezone(&Local).format(DATE_FORMAT).to_string()
+}
+
+/// Read one task per non-blank line, numbered by line. Dates are taken as
+/// local midnight. `key:value` pairs other than `due`, `pri`, `status`,
+/// `desc` and `id` stay in the name.
+pub fn parse(input: &str) -> Vec<ParsedRecord> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(idx, line)| ParsedRecord {
+            number: idx + 1,
+            task: parse_line(line).and_then(TaskDraft::into_task),
+            archived: false,
+        })
+        .collect()
+}
+
+fn parse_line(line: &str) -> Result<TaskDraft, TaskError> {
+    let mut draft = TaskDraft::default();
+    let mut words = line.split_whitespace().peekable();
+
+    if words.peek() == Some(&"x") {
+        words.next();
+        draft.status = Some(Status::Done);
+        // Completion date, then creation date, both optional
+        if let Some(date) = words.next_if(|w| is_date(w)) {
+            draft.completed = Some(parse_due_date(date)?);
+            if let Some(date) = words.next_if(|w| is_date(w)) {
+                draft.created = Some(parse_due_date(date)?);
+            }
+        }
+    } else {
+        if let Some(priority) = words.peek().and_then(|w| priority_prefix(w)) {
+            words.next();
+            draft.priority = Some(priority);
+        }
+        if let Some(date) = words.next_if(|w| is_date(w)) {
+            draft.created = Some(parse_due_date(date)?);
+        }
+    }
+
+    let mut name = Vec::new();
+    for word in words {
+        if let Some(project) = word.strip_prefix('+').filter(|p| !p.is_empty()) {
+            draft.tags.push(project.to_string());
+        } else if word.len() > 1 && word.starts_with('@') {
+            draft.tags.push(word.to_string());
+        } else if let Some(date) = word.strip_prefix("due:") {
+            let (due, precision) = parse_due(date)?;
+            draft.due = Some(due);
+            draft.due_precision = Some(precision);
+        } else if let Some(priority) = word.strip_prefix("pri:").and_then(priority_letter) {
+            draft.priority = Some(priority);
+        } else if word == "status:standby" {
+            draft.status = Some(Status::Standby);
+        } else if let Some(desc) = word.strip_prefix("desc:") {
+            draft.description = decode(desc);
+        } else if let Some(id) = word.strip_prefix("id:") {
+            draft.id = Some(id.to_string());
+        } else {
+            name.push(word);
+        }
+    }
+    draft.name = name.join(" ");
+    Ok(draft)
+}
+
+fn is_date(word: &str) -> bool {
+    NaiveDate::parse_from_str(word, DATE_FORMAT).is_ok()
+}
+
+/// The priority of a leading `(A)`.
+fn priority_prefix(word: &str) -> Option<Priority> {
+    priority_letter(word.strip_prefix('(')?.strip_suffix(')')?)
+}
+
+/// todo.txt priorities are single capital letters.
+fn priority_letter(s: &str) -> Option<Priority> {
+    if s.len() == 1 && s.starts_with(|c: char| c.is_ascii_uppercase()) {
+        s.parse().ok()
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::format_db_datetime;
+    use crate::types::DuePrecision;
+
+    fn parse_one(line: &str) -> Task {
+        parse(line).remove(0).task.unwrap()
+    }
+
+    #[test]
+    fn test_parse_todotxt_line() {
+        let task =
+            parse_one("(A) 2025-07-28 Call the bank +finance @phone due:2025-08-01 t:2025-07-30");
+        assert_eq!(task.name, "Call the bank t:2025-07-30");
+        assert_eq!(task.status, Status::Pending);
+        assert_eq!(task.tags, ["@phone", "finance"]);
+        assert_eq!(task.priority, "A".parse().ok());
+        assert_eq!(task.due_date, parse_due_date("2025-08-01").ok());
+        assert_eq!(task.due_precision, DuePrecision::Day);
+        assert_eq!(
+            Some(task.date),
+            parse_due_date("2025-07-28").ok().map(format_db_datetime)
+        );
+
+        let done = parse_one("x 2025-07-30 2025-07-28 Renew passport pri:B id:abc");
+        assert_eq!(done.status, Status::Done);
+        assert_eq!(done.completed_at, parse_due_date("2025-07-30").ok());
+        assert!(done.tags.is_empty());
+        assert_eq!(done.priority, "B".parse().ok());
+        assert_eq!(done.id, "abc");
+
+        let records = parse("\nx\n");
+        assert_eq!(records[0].number, 2);
+        assert!(records[0].task.is_err());
+    }
+
+    #[test]
+    fn test_export_round_trips() {
+        let mut pending = Task::new("Call the bank".to_string(), None, None).unwrap();
+        pending.tags = vec!["@phone".to_string(), "finance".to_string()];
+        pending.priority = "A".parse().ok();
+        pending.due_date = parse_due_date("2025-08-01").ok();
+        pending.description = "Ask about the 50% fee\n\nbring the letter".to_string();
+
+        let mut done = Task::new("Renew passport".to_string(), None, None).unwrap();
+        done.status = Status::Done;
+        done.completed_at = parse_due_date("2025-07-30").ok();
+        done.priority = "B".parse().ok();
+
+        let mut standby = Task::new("Paint the shed".to_string(), None, None).unwrap();
+        standby.status = Status::Standby;
+
+        let out = export(&[pending.clone(), done.clone(), standby.clone()]);
+        let first = out.lines().next().unwrap();
+        assert!(first.starts_with("(A) "), "{first}");
+        assert!(first.contains(
+            "Call the bank @phone +finance due:2025-08-01 \
+             desc:Ask%20about%20the%2050%25%20fee%0A%0Abring%20the%20letter id:"
+        ));
+        assert!(out.lines().nth(1).unwrap().contains(" pri:B id:"));
+
+        let parsed: Vec<Task> = parse(&out).into_iter().map(|r| r.task.unwrap()).collect();
+        for (before, after) in [pending, done, standby].iter().zip(&parsed) {
+            assert_eq!(before.id, after.id);
+            assert_eq!(before.name, after.name);
+            assert_eq!(before.description, after.description);
+            assert_eq!(before.status, after.status);
+            assert_eq!(before.tags, after.tags);
+            assert_eq!(before.priority, after.priority);
+            assert_eq!(before.due_date, after.due_date);
+            assert_eq!(before.completed_at, after.completed_at);
+        }
+    }
+}
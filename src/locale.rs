@@ -0,0 +1,398 @@
+//! The language of due-date phrases and of relative times. English is
+//! always understood when parsing; a Spanish locale adds `hoy`, `mañana`,
+//! `el viernes`, `en 3 días` and so on, and shows times as `hoy a las 14:00`.
+
+use chrono::Weekday;
+use std::env;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    En,
+    Es,
+}
+
+static CONFIGURED: OnceLock<Locale> = OnceLock::new();
+
+/// Use `locale` unless `TAREA_LANG` says otherwise; only the first call
+/// counts, and it must come before the first `Locale::current`.
+pub fn set_language(locale: Locale) {
+    let _ = CONFIGURED.set(locale);
+}
+
+impl Locale {
+    /// The current locale, read once: `TAREA_LANG`, then the configured
+    /// language, then the first of `LC_ALL`, `LC_MESSAGES` and `LANG` that
+    /// is set.
+    pub fn current() -> Locale {
+        static CURRENT: OnceLock<Locale> = OnceLock::new();
+        *CURRENT.get_or_init(|| Locale::choose(CONFIGURED.get().copied(), |var| env::var(var).ok()))
+    }
+
+    /// Pick a locale from `configured` and the variables `var` looks up.
+    /// Unknown languages fall back to English.
+    fn choose(configured: Option<Locale>, var: impl Fn(&str) -> Option<String>) -> Locale {
+        let first_set = |vars: &[&str]| {
+            vars.iter()
+                .filter_map(|name| var(name))
+                .find(|value| !value.is_empty())
+        };
+        match first_set(&["TAREA_LANG"]) {
+            Some(tag) => Locale::from_tag(&tag),
+            None => configured.or_else(|| {
+                first_set(&["LC_ALL", "LC_MESSAGES", "LANG"]).and_then(|tag| Locale::from_tag(&tag))
+            }),
+        }
+        .unwrap_or(Locale::En)
+    }
+
+    /// Read a language tag such as `es`, `es_ES.UTF-8` or `en-GB`.
+    pub fn from_tag(tag: &str) -> Option<Locale> {
+        let language = tag.split(['_', '-', '.', '@']).next()?.to_ascii_lowercase();
+        match language.as_str() {
+            "en" | "c" | "posix" => Some(Locale::En),
+            "es" => Some(Locale::Es),
+            _ => None,
+        }
+    }
+
+    /// The words to look for in due dates: this locale's, then English.
+    pub fn date_words(self) -> Vec<&'static DateWords> {
+        match self {
+            Locale::En => vec![&EN_DATES],
+            Locale::Es => vec![&ES_DATES, &EN_DATES],
+        }
+    }
+
+    pub fn today(self) -> &'static str {
+        match self {
+            Locale::En => "today",
+            Locale::Es => "hoy",
+        }
+    }
+
+    pub fn yesterday(self) -> &'static str {
+        match self {
+            Locale::En => "yesterday",
+            Locale::Es => "ayer",
+        }
+    }
+
+    pub fn tomorrow(self) -> &'static str {
+        match self {
+            Locale::En => "tomorrow",
+            Locale::Es => "mañana",
+        }
+    }
+
+    pub fn weekday(self, day: Weekday) -> &'static str {
+        let names = match self {
+            Locale::En => [
+                "Monday",
+                "Tuesday",
+                "Wednesday",
+                "Thursday",
+                "Friday",
+                "Saturday",
+                "Sunday",
+            ],
+            Locale::Es => [
+                "lunes",
+                "martes",
+                "miércoles",
+                "jueves",
+                "viernes",
+                "sábado",
+                "domingo",
+            ],
+        };
+        names[day.num_days_from_monday() as usize]
+    }
+
+    /// `day` at a time of day, e.g. `today at 14:00`.
+    pub fn at(self, day: &str, time: &str) -> String {
+        match self {
+            Locale::En => format!("{} at {}", day, time),
+            Locale::Es => format!("{} a las {}", day, time),
+        }
+    }
+
+    /// A span such as `2h 5m` from now, ahead or behind.
+    pub fn relative(self, span: &str, future: bool) -> String {
+        match (self, future) {
+            (Locale::En, true) => format!("in {}", span),
+            (Locale::En, false) => format!("{} ago", span),
+            (Locale::Es, true) => format!("en {}", span),
+            (Locale::Es, false) => format!("hace {}", span),
+        }
+    }
+}
+
+impl FromStr for Locale {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Locale::from_tag(s).ok_or_else(|| format!("Unknown language '{}'. Use en or es", s))
+    }
+}
+
+/// Which day a time of day like `this morning` or `in the evening` falls on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DayPartDay {
+    Today,
+    Tomorrow,
+    /// Today if it hasn't started yet, otherwise tomorrow.
+    Upcoming,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Period {
+    Week,
+    Month,
+    Year,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateUnit {
+    Minutes,
+    Hours,
+    Days,
+    Weeks,
+    Months,
+    Quarters,
+    Years,
+    BusinessDays,
+}
+
+/// The words of one language in due-date phrases. Phrases are lowercased
+/// and stripped of accents before they are looked up, so the words here
+/// are written without them: `manana` matches `mañana` and `Mañana`.
+pub struct DateWords {
+    /// Whole phrases naming a day, with its offset from today.
+    pub days: &'static [(&'static str, i64)],
+    /// Whole phrases for the start of the next week, month or year.
+    pub next_periods: &'static [(&'static str, Period)],
+    /// Words before a weekday for the coming one, which may be today.
+    pub this: &'static [&'static str],
+    /// Words before a weekday for the coming one after today.
+    pub next: &'static [&'static str],
+    pub weekdays: &'static [(&'static str, Weekday)],
+    /// Times of day with their hour.
+    pub day_parts: &'static [(&'static str, u32)],
+    /// Words before a time of day saying which day it is on.
+    pub day_part_days: &'static [(&'static str, DayPartDay)],
+    /// Whole phrases for a time of day, like `tonight`.
+    pub day_part_phrases: &'static [(&'static str, DayPartDay, u32)],
+    /// Words before an amount, like `in` in `in 3 days`.
+    pub ahead: &'static [&'static str],
+    /// Words counting as an amount of one.
+    pub one: &'static [&'static str],
+    /// Words allowed between amounts.
+    pub and: &'static [&'static str],
+    pub units: &'static [(&'static str, DateUnit)],
+}
+
+static EN_DATES: DateWords = DateWords {
+    days: &[("today", 0), ("tomorrow", 1)],
+    next_periods: &[
+        ("next week", Period::Week),
+        ("next month", Period::Month),
+        ("next year", Period::Year),
+    ],
+    this: &["this"],
+    next: &["next"],
+    weekdays: &[
+        ("monday", Weekday::Mon),
+        ("mon", Weekday::Mon),
+        ("tuesday", Weekday::Tue),
+        ("tue", Weekday::Tue),
+        ("tues", Weekday::Tue),
+        ("wednesday", Weekday::Wed),
+        ("wed", Weekday::Wed),
+        ("thursday", Weekday::Thu),
+        ("thu", Weekday::Thu),
+        ("thur", Weekday::Thu),
+        ("thurs", Weekday::Thu),
+        ("friday", Weekday::Fri),
+        ("fri", Weekday::Fri),
+        ("saturday", Weekday::Sat),
+        ("sat", Weekday::Sat),
+        ("sunday", Weekday::Sun),
+        ("sun", Weekday::Sun),
+    ],
+    day_parts: &[
+        ("morning", 9),
+        ("afternoon", 15),
+        ("evening", 19),
+        ("night", 21),
+    ],
+    day_part_days: &[
+        ("this", DayPartDay::Today),
+        ("tomorrow", DayPartDay::Tomorrow),
+        ("in the", DayPartDay::Upcoming),
+    ],
+    day_part_phrases: &[("tonight", DayPartDay::Today, 21)],
+    ahead: &["in"],
+    one: &["a", "an"],
+    and: &["and"],
+    units: &[
+        ("m", DateUnit::Minutes),
+        ("min", DateUnit::Minutes),
+        ("mins", DateUnit::Minutes),
+        ("minute", DateUnit::Minutes),
+        ("minutes", DateUnit::Minutes),
+        ("h", DateUnit::Hours),
+        ("hr", DateUnit::Hours),
+        ("hrs", DateUnit::Hours),
+        ("hour", DateUnit::Hours),
+        ("hours", DateUnit::Hours),
+        ("d", DateUnit::Days),
+        ("day", DateUnit::Days),
+        ("days", DateUnit::Days),
+        ("w", DateUnit::Weeks),
+        ("wk", DateUnit::Weeks),
+        ("wks", DateUnit::Weeks),
+        ("week", DateUnit::Weeks),
+        ("weeks", DateUnit::Weeks),
+        ("mon", DateUnit::Months),
+        ("mons", DateUnit::Months),
+        ("month", DateUnit::Months),
+        ("months", DateUnit::Months),
+        ("q", DateUnit::Quarters),
+        ("qtr", DateUnit::Quarters),
+        ("qtrs", DateUnit::Quarters),
+        ("quarter", DateUnit::Quarters),
+        ("quarters", DateUnit::Quarters),
+        ("y", DateUnit::Years),
+        ("yr", DateUnit::Years),
+        ("yrs", DateUnit::Years),
+        ("year", DateUnit::Years),
+        ("years", DateUnit::Years),
+        ("bd", DateUnit::BusinessDays),
+        ("business day", DateUnit::BusinessDays),
+        ("business days", DateUnit::BusinessDays),
+    ],
+};
+
+static ES_DATES: DateWords = DateWords {
+    days: &[("hoy", 0), ("manana", 1), ("pasado manana", 2)],
+    next_periods: &[
+        ("proxima semana", Period::Week),
+        ("la proxima semana", Period::Week),
+        ("la semana que viene", Period::Week),
+        ("proximo mes", Period::Month),
+        ("el proximo mes", Period::Month),
+        ("el mes que viene", Period::Month),
+        ("proximo ano", Period::Year),
+        ("el proximo ano", Period::Year),
+        ("el ano que viene", Period::Year),
+    ],
+    this: &["el", "este"],
+    next: &["el proximo", "proximo"],
+    weekdays: &[
+        ("lunes", Weekday::Mon),
+        ("lun", Weekday::Mon),
+        ("martes", Weekday::Tue),
+        ("mar", Weekday::Tue),
+        ("miercoles", Weekday::Wed),
+        ("mie", Weekday::Wed),
+        ("jueves", Weekday::Thu),
+        ("jue", Weekday::Thu),
+        ("viernes", Weekday::Fri),
+        ("vie", Weekday::Fri),
+        ("sabado", Weekday::Sat),
+        ("sab", Weekday::Sat),
+        ("domingo", Weekday::Sun),
+        ("dom", Weekday::Sun),
+    ],
+    day_parts: &[("manana", 9), ("tarde", 15), ("noche", 21)],
+    day_part_days: &[
+        ("esta", DayPartDay::Today),
+        ("manana por la", DayPartDay::Tomorrow),
+        ("por la", DayPartDay::Upcoming),
+    ],
+    day_part_phrases: &[],
+    ahead: &["en", "dentro de"],
+    one: &["un", "una"],
+    and: &["y"],
+    units: &[
+        ("minuto", DateUnit::Minutes),
+        ("minutos", DateUnit::Minutes),
+        ("hora", DateUnit::Hours),
+        ("horas", DateUnit::Hours),
+        ("dia", DateUnit::Days),
+        ("dias", DateUnit::Days),
+        ("semana", DateUnit::Weeks),
+        ("semanas", DateUnit::Weeks),
+        ("mes", DateUnit::Months),
+        ("meses", DateUnit::Months),
+        ("trimestre", DateUnit::Quarters),
+        ("trimestres", DateUnit::Quarters),
+        ("ano", DateUnit::Years),
+        ("anos", DateUnit::Years),
+        ("dia habil", DateUnit::BusinessDays),
+        ("dias habiles", DateUnit::BusinessDays),
+    ],
+};
+
+/// Lowercase `s` and drop Spanish accents, for looking it up in
+/// [`DateWords`].
+pub fn fold(s: &str) -> String {
+    s.to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'á' => 'a',
+            'é' => 'e',
+            'í' | 'ï' => 'i',
+            'ó' => 'o',
+            'ú' | 'ü' => 'u',
+            'ñ' => 'n',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locale_from_tag() {
+        assert_eq!(Locale::from_tag("es_ES.UTF-8"), Some(Locale::Es));
+        assert_eq!(Locale::from_tag("es"), Some(Locale::Es));
+        assert_eq!(Locale::from_tag("en-GB"), Some(Locale::En));
+        assert_eq!(Locale::from_tag("C.UTF-8"), Some(Locale::En));
+        assert_eq!(Locale::from_tag("fr_FR"), None);
+    }
+
+    #[test]
+    fn test_configured_language_beats_the_system_locale() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+        let system = env(&[("LC_ALL", ""), ("LANG", "es_ES.UTF-8")]);
+        assert_eq!(Locale::choose(None, system), Locale::Es);
+        assert_eq!(Locale::choose(Some(Locale::En), system), Locale::En);
+
+        let english = env(&[("LC_ALL", "en_US.UTF-8")]);
+        assert_eq!(Locale::choose(Some(Locale::Es), english), Locale::Es);
+
+        let explicit = env(&[("TAREA_LANG", "en"), ("LANG", "es_ES.UTF-8")]);
+        assert_eq!(Locale::choose(Some(Locale::Es), explicit), Locale::En);
+
+        assert_eq!(Locale::choose(None, env(&[("LANG", "fr_FR")])), Locale::En);
+        assert_eq!(Locale::choose(None, env(&[])), Locale::En);
+    }
+
+    #[test]
+    fn test_fold_drops_accents() {
+        assert_eq!(fold("Pasado Mañana"), "pasado manana");
+        assert_eq!(fold("en 3 días"), "en 3 dias");
+        assert_eq!(fold("PRÓXIMA semana"), "proxima semana");
+    }
+}
@@ -2,25 +2,75 @@ use std::io;
 
 mod cli;
 mod commands;
+mod config;
 mod database;
 mod display;
 mod editor;
+mod formats;
 mod help;
+mod locale;
+mod migrations;
 mod paging;
+mod profile;
+mod snapshot;
 mod types;
 mod utils;
 
-use commands::{estimated_lines, execute_command};
+use commands::{estimated_lines, execute_command, execute_profile_command};
+use config::Config;
 use database::TaskManager;
 use paging::{init as pager_init, PagerConfig};
+use profile::Profile;
 pub use types::*;
 
 fn main() -> io::Result<()> {
     help::handle_flag_help()?;
 
-    let command = cli::parse_command();
+    let config = Config::load().unwrap_or_else(|e| {
+        eprintln!("{}; using the defaults", e);
+        Config::default()
+    });
+    display::set_due_soon_thresholds(config.due_soon);
+    display::set_display_options(config.display.clone());
+    if let Some(language) = config.language {
+        locale::set_language(language);
+    }
+
+    let matches = cli::build_cli().get_matches();
+    let command = cli::parse_command(&matches, &config);
+    if command.output() != OutputFormat::Text {
+        colored::control::set_override(false);
+    }
+
+    let profile = match Profile::chosen(cli::get_profile(&matches)) {
+        Ok(profile) => profile,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    // Managing profiles opens no database, so it works from one that is gone
+    if command.manages_profiles() {
+        profile::set_current(profile);
+        if let Err(e) = execute_profile_command(command) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if !profile.exists().unwrap_or(false) {
+        eprintln!(
+            "Profile '{}' does not exist, create it with --create-profile {}",
+            profile.name, profile.name
+        );
+        std::process::exit(1);
+    }
+    profile::set_current(profile.clone());
 
-    let manager = match TaskManager::new() {
+    let manager = match profile
+        .db_path(&config)
+        .and_then(|path| TaskManager::open(&path))
+    {
         Ok(m) => m,
         Err(e) => {
             eprintln!("Failed to initialize task manager: {}", e);
@@ -33,10 +83,12 @@ fn main() -> io::Result<()> {
     pager_init(PagerConfig {
         lines: line_estimate,
         needs_color: true,
+        command: config.pager.clone(),
     })?;
 
-    if let Err(e) = execute_command(&manager, command) {
+    if let Err(e) = execute_command(&manager, &config, command) {
         eprintln!("{}", e);
+        std::process::exit(1);
     }
 
     Ok(())
@@ -0,0 +1,431 @@
+use crate::types::TaskError;
+use rusqlite::{Connection, Transaction};
+
+type MigrationStep = fn(&Transaction) -> rusqlite::Result<()>;
+
+/// Ordered schema migrations. The position of a step in this list (1-based)
+/// is the `PRAGMA user_version` the database reports once it has run, so
+/// steps must only ever be appended, never reordered or edited.
+const MIGRATIONS: &[MigrationStep] = &[
+    create_tasks_table,
+    add_due_date_column,
+    create_tag_tables,
+    create_archived_tasks_table,
+    add_completed_at_column,
+    create_task_events_table,
+    add_task_events_undo_columns,
+    add_due_horizon_columns,
+    add_due_precision_columns,
+    add_recurrence_columns,
+    add_priority_columns,
+    move_whole_day_due_dates_to_end_of_day,
+];
+
+/// Schema version produced by running every migration in `MIGRATIONS`.
+pub const SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// Bring `conn` up to `SCHEMA_VERSION`, running each pending step in its own
+/// transaction so a failure never leaves a half-upgraded database behind.
+pub fn migrate(conn: &mut Connection) -> Result<(), TaskError> {
+    let current = schema_version(conn)?;
+
+    if current > SCHEMA_VERSION {
+        return Err(TaskError::SchemaTooNew {
+            found: current,
+            supported: SCHEMA_VERSION,
+        });
+    }
+
+    for (idx, step) in MIGRATIONS.iter().enumerate().skip(current as usize) {
+        let tx = conn.transaction()?;
+        step(&tx)?;
+        tx.pragma_update(None, "user_version", idx as u32 + 1)?;
+        tx.commit()?;
+    }
+
+    Ok(())
+}
+
+pub fn schema_version(conn: &Connection) -> Result<u32, TaskError> {
+    Ok(conn.pragma_query_value(None, "user_version", |row| row.get(0))?)
+}
+
+fn has_column(tx: &Transaction, table: &str, column: &str) -> rusqlite::Result<bool> {
+    let mut stmt = tx.prepare(&format!("PRAGMA table_info({table})"))?;
+    let names = stmt.query_map([], |row| row.get::<_, String>(1))?;
+
+    for name in names {
+        if name? == column {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+// Databases created before versioning already have this table, hence
+// `IF NOT EXISTS`.
+fn create_tasks_table(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS tasks (
+            id TEXT PRIMARY KEY,
+            date TEXT NOT NULL,
+            name TEXT NOT NULL,
+            description TEXT,
+            status TEXT NOT NULL DEFAULT 'pending'
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn add_due_date_column(tx: &Transaction) -> rusqlite::Result<()> {
+    if !has_column(tx, "tasks", "due_date")? {
+        tx.execute("ALTER TABLE tasks ADD COLUMN due_date TEXT", [])?;
+    }
+    Ok(())
+}
+
+fn create_tag_tables(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE tags (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE
+        );
+        CREATE TABLE task_tags (
+            task_id TEXT NOT NULL,
+            tag_id INTEGER NOT NULL REFERENCES tags(id),
+            PRIMARY KEY (task_id, tag_id)
+        );
+        CREATE INDEX task_tags_tag_id ON task_tags(tag_id);",
+    )
+}
+
+fn create_archived_tasks_table(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute(
+        "CREATE TABLE archived_tasks (
+            id TEXT PRIMARY KEY,
+            date TEXT NOT NULL,
+            name TEXT NOT NULL,
+            description TEXT,
+            status TEXT NOT NULL DEFAULT 'pending',
+            due_date TEXT,
+            deleted_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn add_completed_at_column(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "ALTER TABLE tasks ADD COLUMN completed_at TEXT;
+        ALTER TABLE archived_tasks ADD COLUMN completed_at TEXT;",
+    )
+}
+
+fn create_task_events_table(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "CREATE TABLE task_events (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            task_id TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            old_value TEXT,
+            new_value TEXT,
+            at TEXT NOT NULL
+        );
+        CREATE INDEX task_events_task_id ON task_events(task_id);",
+    )
+}
+
+// Each existing event becomes its own undo step.
+fn add_task_events_undo_columns(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "ALTER TABLE task_events ADD COLUMN batch INTEGER;
+        ALTER TABLE task_events ADD COLUMN undone INTEGER NOT NULL DEFAULT 0;
+        UPDATE task_events SET batch = id;
+        CREATE INDEX task_events_batch ON task_events(batch);",
+    )
+}
+
+// Existing tasks get the time from their creation to their due date.
+fn add_due_horizon_columns(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "ALTER TABLE tasks ADD COLUMN due_horizon INTEGER;
+        ALTER TABLE archived_tasks ADD COLUMN due_horizon INTEGER;
+        UPDATE tasks SET due_horizon =
+            CAST(round((julianday(due_date) - julianday(date)) * 86400) AS INTEGER)
+            WHERE due_date IS NOT NULL AND due_date != '';",
+    )
+}
+
+/// Dates without a time of day used to be stored at local midnight or at
+/// 23:59:59, so those are taken as whole days.
+fn add_due_precision_columns(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "ALTER TABLE tasks ADD COLUMN due_precision TEXT;
+        ALTER TABLE archived_tasks ADD COLUMN due_precision TEXT;",
+    )?;
+    for table in ["tasks", "archived_tasks"] {
+        tx.execute_batch(&format!(
+            "UPDATE {table} SET due_precision =
+                CASE WHEN time(due_date, 'localtime') IN ('00:00:00', '23:59:59')
+                THEN 'day' ELSE 'time' END
+                WHERE due_date IS NOT NULL AND due_date != '';"
+        ))?;
+    }
+    Ok(())
+}
+
+fn add_recurrence_columns(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "ALTER TABLE tasks ADD COLUMN recurrence TEXT;
+        ALTER TABLE archived_tasks ADD COLUMN recurrence TEXT;",
+    )
+}
+
+/// Imports used to keep priorities as `pri:A` tags, so those move over.
+fn add_priority_columns(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "ALTER TABLE tasks ADD COLUMN priority TEXT;
+        ALTER TABLE archived_tasks ADD COLUMN priority TEXT;",
+    )?;
+    for table in ["tasks", "archived_tasks"] {
+        tx.execute_batch(&format!(
+            "UPDATE {table} SET priority =
+                (SELECT substr(tg.name, 5) FROM task_tags tt JOIN tags tg ON tg.id = tt.tag_id
+                 WHERE tt.task_id = {table}.id AND tg.name GLOB 'pri:[A-Z]' LIMIT 1);"
+        ))?;
+    }
+    tx.execute_batch(
+        "DELETE FROM task_tags WHERE tag_id IN (SELECT id FROM tags WHERE name GLOB 'pri:[A-Z]');
+        DELETE FROM tags WHERE name GLOB 'pri:[A-Z]';",
+    )
+}
+
+/// Whole days used to be stored at local midnight when given as a date and
+/// at 23:59:59 when given as a phrase; they are all kept at 23:59:59 now.
+fn move_whole_day_due_dates_to_end_of_day(tx: &Transaction) -> rusqlite::Result<()> {
+    for table in ["tasks", "archived_tasks"] {
+        tx.execute_batch(&format!(
+            "UPDATE {table} SET due_date = datetime(due_date, 'localtime', 'start of day',
+                '+1 day', '-1 second', 'utc')
+                WHERE due_precision = 'day' AND due_date != '';"
+        ))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn columns(conn: &Connection) -> Vec<String> {
+        let mut stmt = conn.prepare("PRAGMA table_info(tasks)").unwrap();
+        stmt.query_map([], |row| row.get::<_, String>(1))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    #[test]
+    fn test_migrate_fresh_database() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+
+        assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION);
+        assert!(columns(&conn).contains(&"due_date".to_string()));
+    }
+
+    #[test]
+    fn test_migrate_is_idempotent() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        migrate(&mut conn).unwrap();
+
+        assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_migrate_unversioned_database_without_due_date() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE tasks (
+                id TEXT PRIMARY KEY,
+                date TEXT NOT NULL,
+                name TEXT NOT NULL,
+                description TEXT,
+                status TEXT NOT NULL DEFAULT 'pending'
+            )",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO tasks (id, date, name, description, status)
+             VALUES ('abc', '2025-08-01 10:00:00', 'Old task', '', 'pending')",
+            [],
+        )
+        .unwrap();
+
+        migrate(&mut conn).unwrap();
+
+        assert!(columns(&conn).contains(&"due_date".to_string()));
+        let name: String = conn
+            .query_row("SELECT name FROM tasks WHERE id = 'abc'", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(name, "Old task");
+    }
+
+    #[test]
+    fn test_migrate_unversioned_database_with_due_date() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE tasks (
+                id TEXT PRIMARY KEY,
+                date TEXT NOT NULL,
+                name TEXT NOT NULL,
+                description TEXT,
+                status TEXT NOT NULL DEFAULT 'pending',
+                due_date TEXT
+            )",
+            [],
+        )
+        .unwrap();
+
+        migrate(&mut conn).unwrap();
+
+        assert_eq!(schema_version(&conn).unwrap(), SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_migrate_rejects_newer_schema() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION + 1)
+            .unwrap();
+
+        assert!(matches!(
+            migrate(&mut conn),
+            Err(TaskError::SchemaTooNew { found, supported })
+                if found == SCHEMA_VERSION + 1 && supported == SCHEMA_VERSION
+        ));
+    }
+
+    #[test]
+    fn test_migrate_backfills_due_horizon() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE tasks (
+                id TEXT PRIMARY KEY,
+                date TEXT NOT NULL,
+                name TEXT NOT NULL,
+                description TEXT,
+                status TEXT NOT NULL DEFAULT 'pending',
+                due_date TEXT
+            );
+            INSERT INTO tasks (id, date, name, due_date)
+            VALUES ('abc', '2025-08-01 10:00:00', 'Old task', '2025-08-03 12:30:00'),
+                   ('def', '2025-08-01 10:00:00', 'No due date', '');",
+        )
+        .unwrap();
+
+        migrate(&mut conn).unwrap();
+
+        let horizon = |id: &str| -> Option<i64> {
+            conn.query_row("SELECT due_horizon FROM tasks WHERE id = ?1", [id], |row| {
+                row.get(0)
+            })
+            .unwrap()
+        };
+        assert_eq!(horizon("abc"), Some(2 * 86_400 + 2 * 3600 + 1800));
+        assert_eq!(horizon("def"), None);
+    }
+
+    #[test]
+    fn test_migrate_guesses_due_precision() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE tasks (
+                id TEXT PRIMARY KEY,
+                date TEXT NOT NULL,
+                name TEXT NOT NULL,
+                description TEXT,
+                status TEXT NOT NULL DEFAULT 'pending',
+                due_date TEXT
+            );",
+        )
+        .unwrap();
+        let local = |date: &str| {
+            let naive = chrono::NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S").unwrap();
+            let utc = naive.and_local_timezone(chrono::Local).unwrap().to_utc();
+            utc.format("%Y-%m-%d %H:%M:%S").to_string()
+        };
+        for (id, due) in [
+            ("midnight", local("2025-08-03 00:00:00")),
+            ("end", local("2025-08-03 23:59:59")),
+            ("timed", local("2025-08-03 14:30:00")),
+        ] {
+            conn.execute(
+                "INSERT INTO tasks (id, date, name, due_date)
+                 VALUES (?1, '2025-08-01 10:00:00', ?1, ?2)",
+                [id, &due],
+            )
+            .unwrap();
+        }
+
+        migrate(&mut conn).unwrap();
+
+        let due = |id: &str| -> (String, String) {
+            conn.query_row(
+                "SELECT due_date, due_precision FROM tasks WHERE id = ?1",
+                [id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap()
+        };
+        // Whole days end up at their last second either way
+        let end_of_day = local("2025-08-03 23:59:59");
+        assert_eq!(due("midnight"), (end_of_day.clone(), "day".to_string()));
+        assert_eq!(due("end"), (end_of_day, "day".to_string()));
+        assert_eq!(
+            due("timed"),
+            (local("2025-08-03 14:30:00"), "time".to_string())
+        );
+    }
+
+    #[test]
+    fn test_migrate_moves_priority_tags() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let before_priority = MIGRATIONS.len() - 2;
+        for step in &MIGRATIONS[..before_priority] {
+            let tx = conn.transaction().unwrap();
+            step(&tx).unwrap();
+            tx.commit().unwrap();
+        }
+        conn.pragma_update(None, "user_version", before_priority as u32)
+            .unwrap();
+        conn.execute_batch(
+            "INSERT INTO tasks (id, date, name) VALUES
+                ('abc', '2025-08-01 10:00:00', 'Call the bank'),
+                ('def', '2025-08-01 10:00:00', 'Buy milk');
+            INSERT INTO tags (id, name) VALUES (1, 'pri:A'), (2, 'finance');
+            INSERT INTO task_tags (task_id, tag_id) VALUES ('abc', 1), ('abc', 2);",
+        )
+        .unwrap();
+
+        migrate(&mut conn).unwrap();
+
+        let priority = |id: &str| -> Option<String> {
+            conn.query_row("SELECT priority FROM tasks WHERE id = ?1", [id], |row| {
+                row.get(0)
+            })
+            .unwrap()
+        };
+        assert_eq!(priority("abc").as_deref(), Some("A"));
+        assert_eq!(priority("def"), None);
+        let tags: i64 = conn
+            .query_row("SELECT COUNT(*) FROM task_tags", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(tags, 1);
+    }
+}
@@ -9,10 +9,12 @@ pub struct PagerConfig {
     pub lines: usize,
     /// Set to `true` when the output contains ANSI colour escapes.
     pub needs_color: bool,
+    /// Pager to use unless `$PAGER` is set. Empty disables paging.
+    pub command: String,
 }
 
 pub fn init(cfg: PagerConfig) -> io::Result<()> {
-    if !atty::is(Stream::Stdout) {
+    if !atty::is(Stream::Stdout) || cfg.command.is_empty() {
         return Ok(());
     }
 
@@ -29,7 +31,7 @@ pub fn init(cfg: PagerConfig) -> io::Result<()> {
         control::set_override(true);
     }
 
-    Pager::with_default_pager("less -FRX").setup();
+    Pager::with_default_pager(cfg.command).setup();
 
     Ok(())
 }
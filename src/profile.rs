@@ -0,0 +1,190 @@
+//! Named profiles keep unrelated task lists strictly apart, e.g. personal
+//! and work tasks. Each profile has its own database and its own last list.
+//! The `default` profile lives directly in `~/.tarea`, the others in
+//! `~/.tarea/profiles/<name>`.
+
+use crate::config::Config;
+use crate::types::TaskError;
+use crate::utils::get_tarea_dir;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::{env, fs};
+
+pub const DEFAULT_PROFILE: &str = "default";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    pub name: String,
+}
+
+impl Profile {
+    /// A profile called `name`, which may not exist yet. Names are kept to
+    /// letters, digits, `-` and `_` since they become directory names.
+    pub fn new(name: &str) -> Result<Profile, TaskError> {
+        let valid = !name.is_empty()
+            && name.len() <= 64
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(TaskError::InvalidInput(format!(
+                "Invalid profile name '{}': use letters, digits, '-' and '_'",
+                name
+            )));
+        }
+        Ok(Profile {
+            name: name.to_string(),
+        })
+    }
+
+    /// The profile given with `--profile`, else `TAREA_PROFILE`, else the
+    /// default one.
+    pub fn chosen(flag: Option<&str>) -> Result<Profile, TaskError> {
+        let from_env = env::var("TAREA_PROFILE")
+            .ok()
+            .filter(|name| !name.is_empty());
+        Profile::new(flag.or(from_env.as_deref()).unwrap_or(DEFAULT_PROFILE))
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.name == DEFAULT_PROFILE
+    }
+
+    /// The directory holding this profile's database and last list.
+    pub fn dir(&self) -> Result<PathBuf, TaskError> {
+        let tarea_dir = get_tarea_dir()?;
+        if self.is_default() {
+            Ok(tarea_dir)
+        } else {
+            Ok(tarea_dir.join("profiles").join(&self.name))
+        }
+    }
+
+    /// The database file. Only the default profile honours the `database`
+    /// setting.
+    pub fn db_path(&self, config: &Config) -> Result<PathBuf, TaskError> {
+        if self.is_default() {
+            config.db_path()
+        } else {
+            Ok(self.dir()?.join("tasks.db"))
+        }
+    }
+
+    pub fn exists(&self) -> Result<bool, TaskError> {
+        Ok(self.is_default() || self.dir()?.is_dir())
+    }
+
+    /// Every profile, the default one first and the rest by name.
+    pub fn list() -> Result<Vec<Profile>, TaskError> {
+        let mut names = Vec::new();
+        match fs::read_dir(get_tarea_dir()?.join("profiles")) {
+            Ok(entries) => {
+                for entry in entries {
+                    let entry = entry?;
+                    if entry.file_type()?.is_dir()
+                        && let Some(name) = entry.file_name().to_str()
+                        && let Ok(profile) = Profile::new(name)
+                        && !profile.is_default()
+                    {
+                        names.push(profile);
+                    }
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        names.sort_by(|a, b| a.name.cmp(&b.name));
+        names.insert(0, Profile::new(DEFAULT_PROFILE)?);
+        Ok(names)
+    }
+
+    pub fn create(&self) -> Result<(), TaskError> {
+        if self.exists()? {
+            return Err(TaskError::InvalidInput(format!(
+                "Profile '{}' already exists",
+                self.name
+            )));
+        }
+        fs::create_dir_all(self.dir()?)?;
+        Ok(())
+    }
+
+    pub fn rename(&self, to: &Profile) -> Result<(), TaskError> {
+        self.ensure_named("renamed")?;
+        if to.exists()? {
+            return Err(TaskError::InvalidInput(format!(
+                "Profile '{}' already exists",
+                to.name
+            )));
+        }
+        fs::rename(self.dir()?, to.dir()?)?;
+        Ok(())
+    }
+
+    /// Remove the profile together with its database.
+    pub fn delete(&self) -> Result<(), TaskError> {
+        self.ensure_named("deleted")?;
+        fs::remove_dir_all(self.dir()?)?;
+        Ok(())
+    }
+
+    /// Fail unless this is an existing profile other than the default one.
+    pub fn ensure_named(&self, action: &str) -> Result<(), TaskError> {
+        if self.is_default() {
+            return Err(TaskError::InvalidInput(format!(
+                "The default profile cannot be {}",
+                action
+            )));
+        }
+        if !self.exists()? {
+            return Err(TaskError::InvalidInput(format!(
+                "Profile '{}' does not exist",
+                self.name
+            )));
+        }
+        Ok(())
+    }
+}
+
+static CURRENT: OnceLock<Profile> = OnceLock::new();
+
+/// Work in `profile` for the rest of the run; only the first call counts.
+pub fn set_current(profile: Profile) {
+    let _ = CURRENT.set(profile);
+}
+
+/// The profile this run works in.
+pub fn current() -> &'static Profile {
+    CURRENT.get_or_init(|| Profile {
+        name: DEFAULT_PROFILE.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_names_are_checked() {
+        assert!(Profile::new("work").is_ok());
+        assert!(Profile::new("side-project_2").is_ok());
+        assert!(Profile::new("").is_err());
+        assert!(Profile::new("../work").is_err());
+        assert!(Profile::new("my work").is_err());
+    }
+
+    #[test]
+    fn test_default_profile_uses_the_database_setting() {
+        let config = Config {
+            database: Some(PathBuf::from("/srv/tasks.db")),
+            ..Config::default()
+        };
+
+        let default = Profile::chosen(Some(DEFAULT_PROFILE)).unwrap();
+        assert!(default.is_default());
+        assert_eq!(
+            default.db_path(&config).unwrap(),
+            PathBuf::from("/srv/tasks.db")
+        );
+    }
+}
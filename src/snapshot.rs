@@ -0,0 +1,169 @@
+use crate::database::TaskManager;
+use crate::profile;
+use crate::types::{DueFilter, Status, TagFilter, Task, TaskError, TaskFilter, TaskSort};
+use crate::utils::status_filter_from_params;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// The list `handle_list` last printed: the query behind it and the ids in
+/// the order they were shown. List indices given to later commands resolve
+/// against it instead of re-running the query, so a task added in between
+/// can't shift them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ListSnapshot {
+    pub show_all: bool,
+    pub status: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub no_tags: Vec<String>,
+    pub sort: String,
+    pub ids: Vec<String>,
+    /// The bounds `--due-before` and friends resolved to when the list was
+    /// printed, so re-running the query doesn't move them.
+    #[serde(default)]
+    pub due: DueFilter,
+}
+
+impl ListSnapshot {
+    pub fn new(
+        show_all: bool,
+        status: Option<&Status>,
+        tags: &TagFilter,
+        due: &DueFilter,
+        sort: &TaskSort,
+        tasks: &[Task],
+    ) -> Self {
+        ListSnapshot {
+            show_all,
+            status: status.map(|s| s.to_string()),
+            tags: tags.include.clone(),
+            no_tags: tags.exclude.clone(),
+            sort: sort.to_string(),
+            ids: tasks.iter().map(|t| t.id.clone()).collect(),
+            due: due.clone(),
+        }
+    }
+
+    pub fn status(&self) -> Option<Status> {
+        self.status
+            .as_deref()
+            .and_then(|s| Status::from_str(s).ok())
+    }
+
+    /// Full id of the task shown at 1-based `index`.
+    pub fn id_at(&self, index: usize) -> Option<&str> {
+        index
+            .checked_sub(1)
+            .and_then(|idx| self.ids.get(idx))
+            .map(String::as_str)
+    }
+
+    /// The query that produced this list.
+    pub fn filter(&self) -> TaskFilter {
+        let tags = TagFilter {
+            include: self.tags.clone(),
+            exclude: self.no_tags.clone(),
+        };
+        let sort = self.sort.parse().unwrap_or_default();
+
+        TaskFilter::new(
+            status_filter_from_params(self.status(), self.show_all),
+            tags,
+        )
+        .due_within(self.due.clone())
+        .sorted_by(sort)
+    }
+
+    /// Whether running the same query now would list different tasks or
+    /// list them in a different order.
+    pub fn is_stale(&self, manager: &TaskManager) -> Result<bool, TaskError> {
+        let current = manager.list_tasks(self.filter())?;
+        Ok(!current.iter().map(|t| &t.id).eq(self.ids.iter()))
+    }
+
+    /// Where the current profile keeps its last listing.
+    pub fn path() -> Result<PathBuf, TaskError> {
+        Ok(profile::current().dir()?.join("last_list.toml"))
+    }
+
+    pub fn load() -> Option<Self> {
+        Self::load_from(&Self::path().ok()?)
+    }
+
+    pub fn load_from(path: &Path) -> Option<Self> {
+        let raw = fs::read_to_string(path).ok()?;
+        toml::from_str(&raw).ok()
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<(), TaskError> {
+        let raw = toml::to_string(self).map_err(|e| TaskError::InvalidInput(e.to_string()))?;
+        fs::write(path, raw)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::parse_db_datetime;
+    use rusqlite::Connection;
+
+    fn manager_with(names: &[&str]) -> (TaskManager, Vec<Task>) {
+        let manager = TaskManager::from_connection(Connection::open_in_memory().unwrap()).unwrap();
+        let tasks = names
+            .iter()
+            .map(|name| {
+                let task = Task::new(name.to_string(), None, None).unwrap();
+                manager.add_task(task.clone()).unwrap();
+                task
+            })
+            .collect();
+        (manager, tasks)
+    }
+
+    #[test]
+    fn test_snapshot_round_trips_through_toml() {
+        let (_, tasks) = manager_with(&["a", "b"]);
+        let tags = TagFilter {
+            include: vec!["work".to_string()],
+            exclude: vec![],
+        };
+        let sort: TaskSort = "due,-name".parse().unwrap();
+        let due = DueFilter {
+            by: parse_db_datetime("2025-08-08 21:59:59"),
+            ..Default::default()
+        };
+        let snapshot = ListSnapshot::new(false, Some(&Status::Done), &tags, &due, &sort, &tasks);
+
+        let parsed: ListSnapshot = toml::from_str(&toml::to_string(&snapshot).unwrap()).unwrap();
+        assert_eq!(parsed, snapshot);
+        assert_eq!(parsed.status(), Some(Status::Done));
+        assert_eq!(parsed.id_at(2), Some(tasks[1].id.as_str()));
+        assert_eq!(parsed.id_at(0), None);
+    }
+
+    #[test]
+    fn test_snapshot_goes_stale_when_the_list_changes() {
+        let (manager, _) = manager_with(&["a", "b"]);
+        let sort = TaskSort::default();
+        let shown = manager
+            .list_tasks(TaskFilter::from(status_filter_from_params(None, false)))
+            .unwrap();
+        let snapshot = ListSnapshot::new(
+            false,
+            None,
+            &TagFilter::default(),
+            &DueFilter::default(),
+            &sort,
+            &shown,
+        );
+        assert!(!snapshot.is_stale(&manager).unwrap());
+
+        let task = Task::new("c".to_string(), None, None).unwrap();
+        manager.add_task(task).unwrap();
+        assert!(snapshot.is_stale(&manager).unwrap());
+    }
+}
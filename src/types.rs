@@ -1,5 +1,11 @@
-use chrono::{DateTime, Utc};
+use crate::database::format_db_datetime;
+use crate::locale::Locale;
+use chrono::{
+    DateTime, Datelike, Duration, Local, Months, NaiveDate, NaiveDateTime, NaiveTime, Timelike,
+    Utc, Weekday,
+};
 use colored::*;
+use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 use std::{fmt, io};
 use uuid::Uuid;
@@ -11,6 +17,7 @@ pub enum TaskError {
     InvalidId(String),
     InvalidInput(String),
     Io(io::Error),
+    SchemaTooNew { found: u32, supported: u32 },
 }
 
 impl From<rusqlite::Error> for TaskError {
@@ -33,6 +40,13 @@ impl fmt::Display for TaskError {
             TaskError::InvalidId(e) => write!(f, "{} {}", "Invalid ID:".bright_yellow(), e),
             TaskError::InvalidDate(e) => write!(f, "{} {}", "Invalid date:".bright_yellow(), e),
             TaskError::InvalidInput(e) => write!(f, "{} {}", "Invalid input:".bright_yellow(), e),
+            TaskError::SchemaTooNew { found, supported } => write!(
+                f,
+                "{} database schema version {} is newer than this tarea supports ({}); upgrade tarea to open it",
+                "Database error:".bright_red(),
+                found,
+                supported
+            ),
         }
     }
 }
@@ -67,6 +81,285 @@ impl FromStr for Status {
     }
 }
 
+/// Whether a due date names a whole day or an exact time. A day is only
+/// over once it has ended, and is shown without a time or countdown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DuePrecision {
+    Day,
+    #[default]
+    Time,
+}
+
+impl DuePrecision {
+    /// For due dates stored before precision was: local midnight and
+    /// 23:59:59 were how dates without a time were written.
+    pub fn guess(due: DateTime<Utc>) -> DuePrecision {
+        let time = due.with_timezone(&Local).time();
+        if time == NaiveTime::MIN || (time.hour(), time.minute(), time.second()) == (23, 59, 59) {
+            DuePrecision::Day
+        } else {
+            DuePrecision::Time
+        }
+    }
+
+    /// When a task due at `due` becomes late.
+    pub fn deadline(self, due: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            DuePrecision::Day => start_of_local_day(due) + Duration::days(1),
+            DuePrecision::Time => due,
+        }
+    }
+
+    /// How `due` is stored: a whole day as its last second, 23:59:59 local
+    /// time, whatever time of that day it came with.
+    pub fn normalize(self, due: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            DuePrecision::Day => self.deadline(due) - Duration::seconds(1),
+            DuePrecision::Time => due,
+        }
+    }
+}
+
+impl fmt::Display for DuePrecision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DuePrecision::Day => write!(f, "day"),
+            DuePrecision::Time => write!(f, "time"),
+        }
+    }
+}
+
+impl FromStr for DuePrecision {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "day" => Ok(DuePrecision::Day),
+            "time" => Ok(DuePrecision::Time),
+            _ => Err(format!("Invalid due precision: {}", s)),
+        }
+    }
+}
+
+/// How urgent a task is, as a letter from `A` (most) to `Z`, the way todo.txt
+/// and Org write priorities. Taskwarrior's H, M and L are kept as they are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Priority(char);
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for Priority {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.trim().chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) if c.is_ascii_alphabetic() => Ok(Priority(c.to_ascii_uppercase())),
+            _ => Err(format!(
+                "Invalid priority '{}'. Use a letter from A to Z",
+                s
+            )),
+        }
+    }
+}
+
+/// How a recurring task repeats, as given to `--every`: `day`, `weekday`,
+/// `week`, `2w`, `month`, `1st monday`, `last friday`, optionally followed by
+/// `after done`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Recurrence {
+    pub cadence: Cadence,
+    /// Count the next due date from when the task was done instead of from
+    /// its due date.
+    pub after_done: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cadence {
+    Days(u32),
+    Weekdays,
+    Weeks(u32),
+    Months(u32),
+    Years(u32),
+    /// The nth weekday of every month, or the last one for `-1`.
+    MonthlyWeekday(i8, Weekday),
+}
+
+/// The longest a recurrence may take to come round, in years.
+const MAX_RECURRENCE_YEARS: u32 = 100;
+
+impl Cadence {
+    /// The first occurrence after `from`, at the same time of day, or `None`
+    /// past the last date there is.
+    pub fn next(self, from: NaiveDateTime) -> Option<NaiveDateTime> {
+        match self {
+            Cadence::Days(n) => from.checked_add_signed(Duration::try_days(n as i64)?),
+            Cadence::Weekdays => crate::utils::add_business_days(from, 1),
+            Cadence::Weeks(n) => from.checked_add_signed(Duration::try_weeks(n as i64)?),
+            Cadence::Months(n) => add_months(from, n),
+            Cadence::Years(n) => add_months(from, n.checked_mul(12)?),
+            Cadence::MonthlyWeekday(nth, weekday) => {
+                // Every month has the first to fourth and the last of each
+                // weekday, so it is this month or the next
+                let first_of_month = from.date().with_day(1)?;
+                (0..2)
+                    .filter_map(|ahead| first_of_month.checked_add_months(Months::new(ahead)))
+                    .filter_map(|month| nth_weekday_of_month(month, nth, weekday))
+                    .find(|date| *date > from.date())
+                    .map(|date| date.and_time(from.time()))
+            }
+        }
+    }
+
+    /// Whether it comes round within [`MAX_RECURRENCE_YEARS`].
+    fn is_in_range(self) -> bool {
+        let years = MAX_RECURRENCE_YEARS;
+        match self {
+            Cadence::Days(n) => n <= years * 366,
+            Cadence::Weeks(n) => n <= years * 53,
+            Cadence::Months(n) => n <= years * 12,
+            Cadence::Years(n) => n <= years,
+            Cadence::Weekdays | Cadence::MonthlyWeekday(..) => true,
+        }
+    }
+}
+
+/// `n` calendar months on, on the month's last day when it is shorter.
+fn add_months(from: NaiveDateTime, n: u32) -> Option<NaiveDateTime> {
+    from.checked_add_months(Months::new(n))
+}
+
+fn nth_weekday_of_month(first: NaiveDate, nth: i8, weekday: Weekday) -> Option<NaiveDate> {
+    if nth < 0 {
+        let last = first.checked_add_months(Months::new(1))?.pred_opt()?;
+        let back = (last.weekday().num_days_from_monday() + 7 - weekday.num_days_from_monday()) % 7;
+        return Some(last - Duration::days(back as i64));
+    }
+    let date =
+        NaiveDate::from_weekday_of_month_opt(first.year(), first.month(), weekday, nth as u8)?;
+    Some(date)
+}
+
+const ORDINALS: [(&str, i8); 9] = [
+    ("1st", 1),
+    ("first", 1),
+    ("2nd", 2),
+    ("second", 2),
+    ("3rd", 3),
+    ("third", 3),
+    ("4th", 4),
+    ("fourth", 4),
+    ("last", -1),
+];
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.cadence {
+            Cadence::Days(1) => write!(f, "day")?,
+            Cadence::Days(n) => write!(f, "{} days", n)?,
+            Cadence::Weekdays => write!(f, "weekday")?,
+            Cadence::Weeks(1) => write!(f, "week")?,
+            Cadence::Weeks(n) => write!(f, "{} weeks", n)?,
+            Cadence::Months(1) => write!(f, "month")?,
+            Cadence::Months(n) => write!(f, "{} months", n)?,
+            Cadence::Years(1) => write!(f, "year")?,
+            Cadence::Years(n) => write!(f, "{} years", n)?,
+            Cadence::MonthlyWeekday(nth, weekday) => {
+                let ordinal = ORDINALS
+                    .iter()
+                    .find(|(_, n)| *n == nth)
+                    .map_or("", |(w, _)| w);
+                let day = Locale::En.weekday(weekday).to_lowercase();
+                write!(f, "{} {}", ordinal, day)?
+            }
+        }
+        if self.after_done {
+            write!(f, " after done")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "Invalid recurrence '{}'. Use day, weekday, week, month, year, an amount \
+                 like 2w or 3 months, or a weekday of the month like '1st monday' or \
+                 'last friday', optionally followed by 'after done'",
+                s
+            )
+        };
+        let lowered = s.trim().to_lowercase();
+        let words: Vec<&str> = lowered.split_whitespace().collect();
+        let (words, after_done) = match words.as_slice() {
+            [rest @ .., "after", "done"] => (rest, true),
+            _ => (words.as_slice(), false),
+        };
+
+        let ordinal = |word: &str| ORDINALS.iter().find(|(w, _)| *w == word).map(|(_, n)| *n);
+
+        let cadence = match words {
+            ["day" | "daily"] => Cadence::Days(1),
+            ["weekday" | "weekdays"] => Cadence::Weekdays,
+            ["week" | "weekly"] => Cadence::Weeks(1),
+            ["month" | "monthly"] => Cadence::Months(1),
+            ["year" | "yearly"] => Cadence::Years(1),
+            [nth, day] if ordinal(nth).is_some() => {
+                let weekday = day.parse::<Weekday>().map_err(|_| invalid())?;
+                Cadence::MonthlyWeekday(ordinal(nth).unwrap_or(1), weekday)
+            }
+            [amount] | [amount, _] => {
+                let joined = words.concat();
+                let digits = joined.chars().take_while(char::is_ascii_digit).count();
+                let (count, unit) = joined.split_at(digits);
+                if words.len() == 2 && amount.len() != digits {
+                    return Err(invalid());
+                }
+                let count: u32 = count.parse().ok().filter(|n| *n > 0).ok_or_else(invalid)?;
+                match unit {
+                    "d" | "day" | "days" => Cadence::Days(count),
+                    "w" | "week" | "weeks" => Cadence::Weeks(count),
+                    "mon" | "month" | "months" => Cadence::Months(count),
+                    "y" | "year" | "years" => Cadence::Years(count),
+                    _ => return Err(invalid()),
+                }
+            }
+            _ => return Err(invalid()),
+        };
+        if !cadence.is_in_range() {
+            return Err(format!(
+                "Recurrence '{}' is too long. Repeat at least every {} years",
+                s.trim(),
+                MAX_RECURRENCE_YEARS
+            ));
+        }
+        Ok(Recurrence {
+            cadence,
+            after_done,
+        })
+    }
+}
+
+/// The local midnight starting the day `dt` falls on.
+fn start_of_local_day(dt: DateTime<Utc>) -> DateTime<Utc> {
+    let midnight = dt
+        .with_timezone(&Local)
+        .date_naive()
+        .and_time(NaiveTime::MIN);
+    midnight
+        .and_local_timezone(Local)
+        .earliest()
+        .map_or(dt, |local| local.with_timezone(&Utc))
+}
+
 pub enum StatusFilter {
     All,
     AnyOf(Vec<Status>),
@@ -74,14 +367,12 @@ pub enum StatusFilter {
 }
 
 impl StatusFilter {
+    /// SQL condition (without `WHERE`) and its positional parameters.
     pub fn to_sql(&self) -> (String, Vec<String>) {
         match self {
             StatusFilter::All => (String::new(), vec![]),
 
-            StatusFilter::PendingOnly => (
-                "WHERE status = ?1".into(),
-                vec![Status::Pending.to_string()],
-            ),
+            StatusFilter::PendingOnly => ("status = ?".into(), vec![Status::Pending.to_string()]),
 
             StatusFilter::AnyOf(status) if status.is_empty() => {
                 // fallback to pending-only if empty
@@ -89,11 +380,10 @@ impl StatusFilter {
             }
 
             StatusFilter::AnyOf(status) => {
-                let placeholders = std::iter::repeat("?")
-                    .take(status.len())
+                let placeholders = std::iter::repeat_n("?", status.len())
                     .collect::<Vec<_>>()
                     .join(", ");
-                let clause = format!("WHERE status IN ({})", placeholders);
+                let clause = format!("status IN ({})", placeholders);
                 let params = status.iter().map(|status| status.to_string()).collect();
                 (clause, params)
             }
@@ -101,6 +391,269 @@ impl StatusFilter {
     }
 }
 
+/// Tags a task must carry (`include`) or must not carry (`exclude`).
+#[derive(Debug, Clone, Default)]
+pub struct TagFilter {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+impl TagFilter {
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    pub fn to_sql(&self) -> (Vec<String>, Vec<String>) {
+        const TAGGED: &str = "SELECT tt.task_id FROM task_tags tt \
+                              JOIN tags tg ON tg.id = tt.tag_id WHERE tg.name = ?";
+
+        let included = self.include.iter().map(|_| format!("id IN ({TAGGED})"));
+        let excluded = self.exclude.iter().map(|_| format!("id NOT IN ({TAGGED})"));
+        let clauses = included.chain(excluded).collect();
+        let params = self.include.iter().chain(&self.exclude).cloned().collect();
+        (clauses, params)
+    }
+}
+
+/// The due-date window a task must fall in. `by` is inclusive and `after`
+/// exclusive, so two end-of-day bounds such as `today` and `friday` select
+/// the days after the first, up to and including the second.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DueFilter {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub by: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<DateTime<Utc>>,
+    /// Only tasks without a due date.
+    #[serde(default)]
+    pub missing: bool,
+    /// Only open tasks past their deadline: the due time, or the end of
+    /// the due day.
+    #[serde(default)]
+    pub overdue: bool,
+}
+
+impl DueFilter {
+    pub fn is_empty(&self) -> bool {
+        *self == DueFilter::default()
+    }
+
+    pub fn to_sql(&self) -> (Vec<String>, Vec<String>) {
+        let mut clauses = Vec::new();
+        let mut params = Vec::new();
+
+        if self.missing {
+            clauses.push("(due_date IS NULL OR due_date = '')".to_string());
+        }
+        if let Some(by) = self.by {
+            clauses.push("due_date <= ?".to_string());
+            params.push(format_db_datetime(by));
+        }
+        if let Some(after) = self.after {
+            clauses.push("due_date > ?".to_string());
+            params.push(format_db_datetime(after));
+        }
+        // Empty due dates would otherwise compare below any bound
+        if self.by.is_some() || self.after.is_some() {
+            clauses.push("due_date != ''".to_string());
+        }
+        if self.overdue {
+            let now = Utc::now();
+            clauses.push(
+                "due_date != '' AND status != ? AND CASE due_precision \
+                 WHEN 'day' THEN due_date < ? ELSE due_date <= ? END"
+                    .to_string(),
+            );
+            params.push(Status::Done.to_string());
+            params.push(format_db_datetime(start_of_local_day(now)));
+            params.push(format_db_datetime(now));
+        }
+        (clauses, params)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortField {
+    Created,
+    Due,
+    Name,
+    Status,
+    Urgency,
+}
+
+/// One `--sort` key; a leading `-` reverses it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SortKey {
+    pub field: SortField,
+    pub descending: bool,
+}
+
+impl SortKey {
+    fn to_sql(self) -> String {
+        let dir = if self.descending { "DESC" } else { "ASC" };
+        let no_due = "(due_date IS NULL OR due_date = '')";
+
+        match self.field {
+            SortField::Created => format!("date {dir}"),
+            // Tasks without a due date go last whichever way we sort
+            SortField::Due => format!("{no_due} ASC, due_date {dir}"),
+            SortField::Name => format!("name COLLATE NOCASE {dir}"),
+            SortField::Status => {
+                format!("CASE status WHEN 'pending' THEN 0 WHEN 'standby' THEN 1 ELSE 2 END {dir}")
+            }
+            // Open tasks before done ones, then the nearest due date first
+            SortField::Urgency => {
+                format!("status = 'done' {dir}, {no_due} {dir}, due_date {dir}")
+            }
+        }
+    }
+}
+
+impl fmt::Display for SortKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self.field {
+            SortField::Created => "created",
+            SortField::Due => "due",
+            SortField::Name => "name",
+            SortField::Status => "status",
+            SortField::Urgency => "urgency",
+        };
+        write!(f, "{}{}", if self.descending { "-" } else { "" }, name)
+    }
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (descending, name) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+        let field = match name.to_lowercase().as_str() {
+            "created" | "date" => SortField::Created,
+            "due" => SortField::Due,
+            "name" => SortField::Name,
+            "status" => SortField::Status,
+            "urgency" => SortField::Urgency,
+            _ => {
+                return Err(format!(
+                    "Invalid sort key: {s} (expected created, due, name, status or urgency)"
+                ));
+            }
+        };
+        Ok(SortKey { field, descending })
+    }
+}
+
+/// Comma separated sort keys, applied left to right.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskSort(pub Vec<SortKey>);
+
+impl TaskSort {
+    /// `ORDER BY` clause. Creation date and id break any remaining ties so
+    /// the order, and therefore list indices, is stable.
+    pub fn to_sql(&self) -> String {
+        let keys: Vec<String> = self
+            .0
+            .iter()
+            .map(|key| key.to_sql())
+            .chain(["date DESC".to_string(), "id".to_string()])
+            .collect();
+        format!("ORDER BY {}", keys.join(", "))
+    }
+}
+
+impl Default for TaskSort {
+    fn default() -> Self {
+        TaskSort(vec![SortKey {
+            field: SortField::Created,
+            descending: true,
+        }])
+    }
+}
+
+impl fmt::Display for TaskSort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let keys: Vec<String> = self.0.iter().map(|key| key.to_string()).collect();
+        write!(f, "{}", keys.join(","))
+    }
+}
+
+impl FromStr for TaskSort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let keys = s
+            .split(',')
+            .filter(|key| !key.trim().is_empty())
+            .map(SortKey::from_str)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if keys.is_empty() {
+            return Err("Sort keys cannot be empty".to_string());
+        }
+        Ok(TaskSort(keys))
+    }
+}
+
+/// Every condition a listing can be narrowed by; all of them are ANDed.
+/// `sort` decides the order the matching tasks come back in.
+pub struct TaskFilter {
+    pub status: StatusFilter,
+    pub tags: TagFilter,
+    pub due: DueFilter,
+    pub sort: TaskSort,
+}
+
+impl TaskFilter {
+    pub fn new(status: StatusFilter, tags: TagFilter) -> Self {
+        TaskFilter {
+            status,
+            tags,
+            due: DueFilter::default(),
+            sort: TaskSort::default(),
+        }
+    }
+
+    pub fn due_within(mut self, due: DueFilter) -> Self {
+        self.due = due;
+        self
+    }
+
+    pub fn sorted_by(mut self, sort: TaskSort) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    pub fn to_sql(&self) -> (String, Vec<String>) {
+        let (status_clause, mut params) = self.status.to_sql();
+        let (tag_clauses, tag_params) = self.tags.to_sql();
+        params.extend(tag_params);
+        let (due_clauses, due_params) = self.due.to_sql();
+        params.extend(due_params);
+
+        let clauses: Vec<String> = std::iter::once(status_clause)
+            .filter(|clause| !clause.is_empty())
+            .chain(tag_clauses)
+            .chain(due_clauses)
+            .collect();
+
+        if clauses.is_empty() {
+            (String::new(), params)
+        } else {
+            (format!("WHERE {}", clauses.join(" AND ")), params)
+        }
+    }
+}
+
+impl From<StatusFilter> for TaskFilter {
+    fn from(status: StatusFilter) -> Self {
+        TaskFilter::new(status, TagFilter::default())
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Task {
     pub id: String,
@@ -109,8 +662,21 @@ pub struct Task {
     pub description: String,
     pub status: Status,
     pub due_date: Option<DateTime<Utc>>,
+    /// How long the task was given when its due date was set, the yardstick
+    /// for when it counts as due soon.
+    pub due_horizon: Option<Duration>,
+    /// Meaningful only with a due date.
+    pub due_precision: DuePrecision,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub tags: Vec<String>,
+    pub recurrence: Option<Recurrence>,
+    pub priority: Option<Priority>,
 }
 
+/// How many missed occurrences of a late recurring task are stepped over
+/// before the next one is counted from when it was done.
+const MAX_SKIPPED_OCCURRENCES: u32 = 10_000;
+
 impl Task {
     pub fn new(
         name: String,
@@ -119,15 +685,347 @@ impl Task {
     ) -> Result<Self, TaskError> {
         crate::utils::validate_task_name(&name)?;
 
+        let now = Utc::now();
         Ok(Task {
             id: Uuid::new_v4().to_string(),
-            date: Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            date: now.format("%Y-%m-%d %H:%M:%S").to_string(),
             name,
             description: description.unwrap_or_default(),
             status: Status::Pending,
             due_date,
+            due_horizon: due_date.map(|due| due - now),
+            due_precision: DuePrecision::default(),
+            completed_at: None,
+            tags: Vec::new(),
+            recurrence: None,
+            priority: None,
         })
     }
+
+    /// When the task becomes late, if it has a due date.
+    pub fn deadline(&self) -> Option<DateTime<Utc>> {
+        self.due_date.map(|due| self.due_precision.deadline(due))
+    }
+
+    /// The pending task that follows this one when it recurs and is done at
+    /// `done_at`. Occurrences a late task already missed are skipped, and one
+    /// without a due date gets a whole day counted from when it was done.
+    pub fn next_occurrence(&self, done_at: DateTime<Utc>) -> Option<Task> {
+        let recurrence = self.recurrence?;
+        let to_local = |dt: DateTime<Utc>| dt.with_timezone(&Local).naive_local();
+        let done_local = to_local(done_at);
+
+        let (from, precision) = match self.due_date {
+            Some(due) if recurrence.after_done => (
+                done_local.date().and_time(to_local(due).time()),
+                self.due_precision,
+            ),
+            Some(due) => (to_local(due), self.due_precision),
+            None => (
+                done_local.date().and_hms_opt(23, 59, 59)?,
+                DuePrecision::Day,
+            ),
+        };
+
+        let to_utc = |naive: NaiveDateTime| {
+            naive
+                .and_local_timezone(Local)
+                .latest()
+                .map_or_else(|| naive.and_utc(), |local| local.with_timezone(&Utc))
+        };
+        let cadence = recurrence.cadence;
+        let mut next = to_utc(cadence.next(from)?);
+        let mut skipped = 0;
+        while precision.deadline(next) <= done_at {
+            // Far behind, count from the day it was done instead
+            let from = if skipped < MAX_SKIPPED_OCCURRENCES {
+                to_local(next)
+            } else {
+                done_local.date().and_time(to_local(next).time())
+            };
+            let after = to_utc(cadence.next(from)?);
+            if after <= next {
+                return None;
+            }
+            next = after;
+            skipped += 1;
+        }
+
+        let mut task = Task::new(
+            self.name.clone(),
+            Some(self.description.clone()),
+            Some(next),
+        )
+        .ok()?;
+        task.due_precision = precision;
+        task.tags = self.tags.clone();
+        task.recurrence = Some(recurrence);
+        task.priority = self.priority;
+        Some(task)
+    }
+}
+
+/// What a `TaskEvent` records. The string form is what gets stored in the
+/// `task_events.kind` column.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventKind {
+    Added,
+    Name,
+    Description,
+    Due,
+    Status,
+    Completed,
+    Tags,
+    Recurrence,
+    Priority,
+    Deleted,
+    Restored,
+    Purged,
+    /// Moved to another profile, named by the new value.
+    Moved,
+}
+
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            EventKind::Added => "added",
+            EventKind::Name => "name",
+            EventKind::Description => "details",
+            EventKind::Due => "due",
+            EventKind::Status => "status",
+            EventKind::Completed => "completed",
+            EventKind::Tags => "tags",
+            EventKind::Recurrence => "repeats",
+            EventKind::Priority => "priority",
+            EventKind::Deleted => "deleted",
+            EventKind::Restored => "restored",
+            EventKind::Purged => "purged",
+            EventKind::Moved => "moved",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for EventKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "added" => Ok(EventKind::Added),
+            "name" => Ok(EventKind::Name),
+            "details" => Ok(EventKind::Description),
+            "due" => Ok(EventKind::Due),
+            "status" => Ok(EventKind::Status),
+            "completed" => Ok(EventKind::Completed),
+            "tags" => Ok(EventKind::Tags),
+            "repeats" => Ok(EventKind::Recurrence),
+            "priority" => Ok(EventKind::Priority),
+            "deleted" => Ok(EventKind::Deleted),
+            "restored" => Ok(EventKind::Restored),
+            "purged" => Ok(EventKind::Purged),
+            "moved" => Ok(EventKind::Moved),
+            _ => Err(format!("Invalid event kind: {}", s)),
+        }
+    }
+}
+
+/// One recorded mutation of a task. Values are stored as they are kept in
+/// the `tasks` table (due dates in UTC, tags space-separated).
+#[derive(Clone, Debug)]
+pub struct TaskEvent {
+    pub task_id: String,
+    pub task_name: Option<String>,
+    pub kind: EventKind,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    pub at: DateTime<Utc>,
+    /// Reverted by `--undo` (and possibly redoable).
+    pub undone: bool,
+}
+
+/// A task moved out of `tasks` by a delete, kept until it is purged.
+#[derive(Clone, Debug)]
+pub struct ArchivedTask {
+    pub task: Task,
+    pub deleted_at: DateTime<Utc>,
+}
+
+/// File formats understood by `--export` and `--import`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DataFormat {
+    Csv,
+    Ical,
+    Json,
+    Markdown,
+    Org,
+    Taskwarrior,
+    TodoTxt,
+    Tsv,
+}
+
+impl DataFormat {
+    /// Guess the format of an import file from its extension.
+    pub fn from_path(path: &str) -> Option<Self> {
+        let ext = std::path::Path::new(path).extension()?.to_str()?;
+        ext.parse().ok()
+    }
+}
+
+impl fmt::Display for DataFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataFormat::Csv => write!(f, "csv"),
+            DataFormat::Ical => write!(f, "ical"),
+            DataFormat::Json => write!(f, "json"),
+            DataFormat::Markdown => write!(f, "markdown"),
+            DataFormat::Org => write!(f, "org"),
+            DataFormat::Taskwarrior => write!(f, "taskwarrior"),
+            DataFormat::TodoTxt => write!(f, "todotxt"),
+            DataFormat::Tsv => write!(f, "tsv"),
+        }
+    }
+}
+
+impl FromStr for DataFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(DataFormat::Csv),
+            "ical" | "ics" | "icalendar" => Ok(DataFormat::Ical),
+            "json" => Ok(DataFormat::Json),
+            "markdown" | "md" => Ok(DataFormat::Markdown),
+            "org" => Ok(DataFormat::Org),
+            "taskwarrior" | "tw" => Ok(DataFormat::Taskwarrior),
+            "todotxt" | "todo.txt" | "txt" => Ok(DataFormat::TodoTxt),
+            "tsv" => Ok(DataFormat::Tsv),
+            _ => Err(format!(
+                "Unsupported format: {} (expected csv, ical, json, markdown, org, taskwarrior, todotxt or tsv)",
+                s
+            )),
+        }
+    }
+}
+
+/// A task field as named in export columns and import header mappings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TaskField {
+    Id,
+    Name,
+    Description,
+    Status,
+    Created,
+    Due,
+    Completed,
+    Tags,
+}
+
+impl TaskField {
+    /// Columns written by a CSV/TSV export unless `--columns` says otherwise.
+    pub const DEFAULT_COLUMNS: [TaskField; 6] = [
+        TaskField::Id,
+        TaskField::Name,
+        TaskField::Status,
+        TaskField::Created,
+        TaskField::Due,
+        TaskField::Description,
+    ];
+
+    /// Parse a comma separated field list such as `id,name,due`.
+    pub fn parse_list(s: &str) -> Result<Vec<TaskField>, String> {
+        s.split(',')
+            .filter(|field| !field.trim().is_empty())
+            .map(TaskField::from_str)
+            .collect()
+    }
+}
+
+impl fmt::Display for TaskField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            TaskField::Id => "id",
+            TaskField::Name => "name",
+            TaskField::Description => "description",
+            TaskField::Status => "status",
+            TaskField::Created => "created",
+            TaskField::Due => "due",
+            TaskField::Completed => "completed",
+            TaskField::Tags => "tags",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for TaskField {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "id" => Ok(TaskField::Id),
+            "name" => Ok(TaskField::Name),
+            "description" => Ok(TaskField::Description),
+            "status" => Ok(TaskField::Status),
+            "created" => Ok(TaskField::Created),
+            "due" => Ok(TaskField::Due),
+            "completed" => Ok(TaskField::Completed),
+            "tags" => Ok(TaskField::Tags),
+            _ => Err(format!(
+                "Invalid field: {} (expected id, name, description, status, created, due, completed or tags)",
+                s
+            )),
+        }
+    }
+}
+
+/// Format specific settings for `--export`.
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    /// CSV/TSV columns, in order; empty means `TaskField::DEFAULT_COLUMNS`.
+    pub columns: Vec<TaskField>,
+    /// Markdown: a heading and list per status instead of one list.
+    pub group_by_status: bool,
+}
+
+/// Format specific settings for `--import`.
+#[derive(Debug, Clone, Default)]
+pub struct ImportOptions {
+    /// CSV/TSV headers to read as the given field, on top of the built-in
+    /// header names.
+    pub mapping: Vec<(String, TaskField)>,
+}
+
+/// What `--import` does with a task whose id already exists.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ConflictStrategy {
+    #[default]
+    Skip,
+    Overwrite,
+    Rename,
+}
+
+impl FromStr for ConflictStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "skip" => Ok(ConflictStrategy::Skip),
+            "overwrite" => Ok(ConflictStrategy::Overwrite),
+            "rename" => Ok(ConflictStrategy::Rename),
+            _ => Err(format!(
+                "Invalid conflict strategy: {} (expected skip, overwrite or rename)",
+                s
+            )),
+        }
+    }
+}
+
+/// How read commands print tasks.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    JsonLines,
 }
 
 #[derive(Debug)]
@@ -135,54 +1033,254 @@ pub enum TaskCommand {
     Add {
         name: String,
         description: Option<String>,
-        due_date: Option<DateTime<Utc>>,
+        due_date: Option<(DateTime<Utc>, DuePrecision)>,
+        tags: Vec<String>,
+        recurrence: Option<Recurrence>,
+        priority: Option<Priority>,
     },
+    Archived,
     Completions {
         shell: String,
         dynamic_bash: String,
         dynamic_fish: String,
     },
-    DeleteDatabase,
+    DeleteDatabase {
+        assume_yes: bool,
+    },
+    Profiles,
+    CreateProfile {
+        name: String,
+    },
+    RenameProfile {
+        from: String,
+        to: String,
+    },
+    DeleteProfile {
+        name: String,
+        assume_yes: bool,
+    },
+    Move {
+        ids_or_indices: Vec<String>,
+        profile: String,
+        assume_yes: bool,
+    },
+    Export {
+        format: DataFormat,
+        options: ExportOptions,
+    },
+    Import {
+        path: String,
+        format: Option<DataFormat>,
+        on_conflict: ConflictStrategy,
+        dry_run: bool,
+        options: ImportOptions,
+    },
+    Purge {
+        older_than: Option<Duration>,
+        assume_yes: bool,
+    },
+    Restore {
+        id_or_index: String,
+    },
     Edit {
         id_or_index: String,
         field: EditField,
     },
+    History {
+        id_or_index: String,
+    },
     List {
         status: Option<Status>,
         show_all: bool,
         show_descriptions: bool,
+        tags: TagFilter,
+        due: DueFilter,
+        sort: TaskSort,
+        output: OutputFormat,
     },
+    Log,
+    Redo,
+    Undo,
     ListNames {
         show_all: bool,
         status: Option<Status>,
+        tags: TagFilter,
+        due: DueFilter,
+        sort: TaskSort,
+        output: OutputFormat,
     },
     Show {
         id: String,
+        output: OutputFormat,
     },
     ShowName {
         id_or_index: String,
         status: Option<Status>,
     },
     UpdateStatus {
-        id: String,
+        ids: Vec<String>,
         status: Status,
+        assume_yes: bool,
     },
     Ids {
         short_only: bool,
         filter: Vec<Status>,
+        tags: TagFilter,
+        due: DueFilter,
+        sort: TaskSort,
+        output: OutputFormat,
     },
     Delete {
-        id_or_index: String,
+        ids_or_indices: Vec<String>,
         status: Option<Status>,
+        assume_yes: bool,
     },
     EditWithEditor {
         id_or_index: String,
     },
 }
 
+impl TaskCommand {
+    pub fn output(&self) -> OutputFormat {
+        match self {
+            TaskCommand::List { output, .. }
+            | TaskCommand::ListNames { output, .. }
+            | TaskCommand::Show { output, .. }
+            | TaskCommand::Ids { output, .. } => *output,
+            _ => OutputFormat::Text,
+        }
+    }
+
+    /// Whether this lists, creates, renames or deletes profiles, which works
+    /// without a task database and from a profile that doesn't exist.
+    pub fn manages_profiles(&self) -> bool {
+        matches!(
+            self,
+            TaskCommand::Profiles
+                | TaskCommand::CreateProfile { .. }
+                | TaskCommand::RenameProfile { .. }
+                | TaskCommand::DeleteProfile { .. }
+        )
+    }
+}
+
 #[derive(Debug)]
 pub enum EditField {
     Name(String),
     Description(String),
-    DueDate(DateTime<Utc>),
+    DueDate(DateTime<Utc>, DuePrecision),
+    /// `None` stops the task repeating.
+    Recurrence(Option<Recurrence>),
+    /// `None` clears the priority.
+    Priority(Option<Priority>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recurrence_parses_and_displays() {
+        for (input, shown) in [
+            ("daily", "day"),
+            ("weekdays", "weekday"),
+            ("2w", "2 weeks"),
+            ("3 months", "3 months"),
+            ("1st Monday", "1st monday"),
+            ("last fri", "last friday"),
+            ("week after done", "week after done"),
+        ] {
+            let recurrence: Recurrence = input.parse().unwrap();
+            assert_eq!(recurrence.to_string(), shown);
+            assert_eq!(shown.parse::<Recurrence>(), Ok(recurrence));
+        }
+
+        assert!("0d".parse::<Recurrence>().is_err());
+        assert!("5th monday".parse::<Recurrence>().is_err());
+        assert!("fortnightly".parse::<Recurrence>().is_err());
+
+        assert!("100 years".parse::<Recurrence>().is_ok());
+        for too_long in [
+            "101 years",
+            "999999999d",
+            "300000 years",
+            "4294967295 months",
+        ] {
+            assert!(too_long.parse::<Recurrence>().is_err(), "{too_long}");
+        }
+    }
+
+    #[test]
+    fn test_cadence_next_occurrence() {
+        let at = |date: &str| {
+            NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .unwrap()
+                .and_hms_opt(9, 0, 0)
+                .unwrap()
+        };
+
+        // 2025-08-01 is a Friday
+        assert_eq!(
+            Cadence::Weekdays.next(at("2025-08-01")),
+            Some(at("2025-08-04"))
+        );
+        assert_eq!(
+            Cadence::Weeks(2).next(at("2025-08-01")),
+            Some(at("2025-08-15"))
+        );
+        assert_eq!(
+            Cadence::Months(1).next(at("2025-01-31")),
+            Some(at("2025-02-28"))
+        );
+        assert_eq!(
+            Cadence::MonthlyWeekday(1, Weekday::Mon).next(at("2025-08-01")),
+            Some(at("2025-08-04"))
+        );
+        assert_eq!(
+            Cadence::MonthlyWeekday(1, Weekday::Mon).next(at("2025-08-04")),
+            Some(at("2025-09-01"))
+        );
+        assert_eq!(
+            Cadence::MonthlyWeekday(-1, Weekday::Fri).next(at("2025-08-01")),
+            Some(at("2025-08-29"))
+        );
+
+        // Past the last date there is
+        let end = NaiveDateTime::MAX - Duration::days(1);
+        assert_eq!(Cadence::Days(36_600).next(end), None);
+        assert_eq!(Cadence::Weekdays.next(NaiveDateTime::MAX), None);
+        assert_eq!(Cadence::Years(100).next(end), None);
+        assert_eq!(Cadence::Years(u32::MAX).next(at("2025-08-01")), None);
+        assert_eq!(Cadence::Days(u32::MAX).next(end), None);
+        assert_eq!(
+            Cadence::MonthlyWeekday(-1, Weekday::Fri).next(NaiveDateTime::MAX),
+            None
+        );
+    }
+
+    #[test]
+    fn test_next_occurrence_catches_up_with_a_late_task() {
+        let done_at = Utc::now();
+        let mut task = Task::new(
+            "Stretch".to_string(),
+            None,
+            Some(done_at - Duration::days(3 * 365)),
+        )
+        .unwrap();
+        task.recurrence = Some("day".parse().unwrap());
+
+        // Past the missed occurrences, on the first one still ahead
+        let next = task.next_occurrence(done_at).unwrap().due_date.unwrap();
+        assert!(next > done_at && next <= done_at + Duration::days(1) + Duration::hours(1));
+
+        // Decades behind, it is counted from the day it was done
+        task.due_date = Some(done_at - Duration::days(40 * 366));
+        let next = task.next_occurrence(done_at).unwrap().due_date.unwrap();
+        assert!(next > done_at && next <= done_at + Duration::days(1) + Duration::hours(1));
+
+        // Nothing after the last date there is
+        task.due_date = Some(DateTime::<Utc>::MAX_UTC - Duration::days(2));
+        task.recurrence = Some("year".parse().unwrap());
+        assert!(task.next_occurrence(done_at).is_none());
+    }
 }
@@ -1,12 +1,19 @@
 use crate::database::TaskManager;
-use crate::types::{Status, StatusFilter, Task, TaskError};
-use chrono::{DateTime, Duration, Local, NaiveDateTime, TimeZone, Utc};
+use crate::locale::{DateUnit, DateWords, DayPartDay, Locale, Period, fold};
+use crate::snapshot::ListSnapshot;
+use crate::types::{
+    DueFilter, DuePrecision, Status, StatusFilter, TagFilter, Task, TaskError, TaskSort,
+};
+use chrono::{
+    DateTime, Datelike, Duration, Local, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc,
+};
 use colored::*;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::{env, fs};
 
 const MAX_TASK_NAME_LENGTH: usize = 120;
+const MAX_TASK_ID_LENGTH: usize = 128;
 
 pub fn validate_task_name(name: &str) -> Result<(), TaskError> {
     let trimmed = name.trim();
@@ -25,108 +32,489 @@ pub fn validate_task_name(name: &str) -> Result<(), TaskError> {
     Ok(())
 }
 
+/// Ids from other apps are kept as long as they are printable ASCII
+/// without spaces, so they can be typed and shortened like our own UUIDs.
+pub fn validate_task_id(id: &str) -> Result<(), TaskError> {
+    if id.is_empty() || id.len() > MAX_TASK_ID_LENGTH || !id.chars().all(|c| c.is_ascii_graphic()) {
+        return Err(TaskError::InvalidId(format!(
+            "'{id}' is not a valid task ID (use up to {MAX_TASK_ID_LENGTH} ASCII characters without spaces)"
+        )));
+    }
+
+    Ok(())
+}
+
+pub fn validate_tag_name(tag: &str) -> Result<(), TaskError> {
+    if tag.is_empty() {
+        return Err(TaskError::InvalidInput("Tag cannot be empty".to_string()));
+    }
+    if tag.contains(|c: char| c.is_whitespace() || c == ',') {
+        return Err(TaskError::InvalidInput(format!(
+            "Invalid tag '{tag}': tags cannot contain spaces or commas"
+        )));
+    }
+
+    Ok(())
+}
+
+/// Pull `+tag` words out of a task name, returning the remaining name and
+/// the tags without their `+` prefix.
+pub fn split_name_and_tags(input: &str) -> (String, Vec<String>) {
+    let is_tag = |word: &str| word.len() > 1 && word.starts_with('+');
+
+    if !input.split_whitespace().any(is_tag) {
+        return (input.to_string(), Vec::new());
+    }
+
+    let (tags, words): (Vec<&str>, Vec<&str>) = input.split_whitespace().partition(|w| is_tag(w));
+    let tags = tags.iter().map(|t| t[1..].to_string()).collect();
+    (words.join(" "), tags)
+}
+
+/// Turn a `--due` phrase into a point in time. Understood, case-insensitively:
+///
+/// - `today`, `tomorrow`, weekdays (`friday`, `this fri`, `next friday`),
+///   `next week` (its Monday), `next month` and `next year` (their first day)
+/// - `tonight`, `this morning`, `in the evening`, `tomorrow afternoon`
+/// - amounts of `m`, `h`, `d`, `w`, `mon`, `q`, `y` and `bd` (business
+///   days), combined and with decimals (`1.5h`, `1w 2d`), optionally as
+///   words after `in` (`in 5 days`, `in an hour`)
+/// - `YYYY-MM-DD [HH:MM[:SS]]`
+///
+/// With a Spanish locale the same phrases are also read in Spanish (`hoy`,
+/// `pasado mañana`, `el viernes`, `en 3 días`, `próxima semana`).
+///
+/// Phrases naming a day without a time of day are due at its end, 23:59:59.
+/// Only minutes, hours and fractional days or weeks give an exact time.
 pub fn parse_due_date(input: &str) -> Result<DateTime<Utc>, TaskError> {
-    let trimmed = input.trim().to_lowercase();
-
-    match trimmed.as_str() {
-        "today" => {
-            let date = Local::now().date_naive();
-            return Ok(date
-                .and_hms_opt(23, 59, 59)
-                .unwrap()
-                .and_local_timezone(Local)
-                .unwrap()
-                .with_timezone(&Utc));
-        }
-        "tomorrow" => {
-            let date = (Local::now() + Duration::days(1)).date_naive();
-            return Ok(date
-                .and_hms_opt(23, 59, 59)
-                .unwrap()
-                .and_local_timezone(Local)
-                .unwrap()
-                .with_timezone(&Utc));
-        }
-        _ => {}
+    parse_due_date_at(input, Local::now())
+}
+
+/// [`parse_due_date`] with phrases counted from `now`.
+pub fn parse_due_date_at(input: &str, now: DateTime<Local>) -> Result<DateTime<Utc>, TaskError> {
+    parse_due_date_from(input, now, Locale::current()).map(|(due, _)| due)
+}
+
+/// [`parse_due_date`], also telling whether the phrase named a whole day or
+/// an exact time.
+pub fn parse_due(input: &str) -> Result<(DateTime<Utc>, DuePrecision), TaskError> {
+    parse_due_date_from(input, Local::now(), Locale::current())
+}
+
+fn parse_due_date_from(
+    input: &str,
+    now: DateTime<Local>,
+    locale: Locale,
+) -> Result<(DateTime<Utc>, DuePrecision), TaskError> {
+    let phrase = fold(input.trim())
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let words = locale.date_words();
+
+    if let Some(date) = parse_day_phrase(&phrase, now, &words) {
+        return Ok((local_to_utc(end_of_day(date), input)?, DuePrecision::Day));
+    }
+    if let Some(when) = parse_day_part_phrase(&phrase, now, &words) {
+        return Ok((local_to_utc(when, input)?, DuePrecision::Time));
+    }
+    if let Some(due) = parse_absolute_date(&phrase, input)? {
+        return Ok(due);
+    }
+    if let Some(due) = parse_relative_date(&phrase, input, now, &words)? {
+        return Ok(due);
     }
 
-    let cleaned = trimmed.replace(' ', "");
+    Err(TaskError::InvalidDate(format!(
+        "Unable to parse '{}'. Use natural language like 'today', '2h 30m', 'in 3 days', \
+         'next friday', or an absolute date 'YYYY-MM-DD [HH:MM[:SS]]'",
+        input
+    )))
+}
 
-    if let Some(h_pos) = cleaned.find('h') {
-        let (hours_part, rest) = cleaned.split_at(h_pos);
-        let rest = &rest[1..]; // drop 'h'
+/// The meaning of `word` in the first table that has it.
+fn lookup<T: Copy>(
+    words: &[&DateWords],
+    table: fn(&DateWords) -> &[(&'static str, T)],
+    word: &str,
+) -> Option<T> {
+    words
+        .iter()
+        .flat_map(|w| table(w))
+        .find(|(w, _)| *w == word)
+        .map(|(_, meaning)| *meaning)
+}
 
-        if let Ok(h) = hours_part.parse::<i64>() {
-            let mut duration = Duration::hours(h);
-            if !rest.is_empty() {
-                if let Some(mins_str) = rest.strip_suffix('m') {
-                    if !mins_str.is_empty() {
-                        if let Ok(m) = mins_str.parse::<i64>() {
-                            duration += Duration::minutes(m);
-                        }
-                    }
-                } else {
-                    return Err(TaskError::InvalidDate(format!(
-                        "Unable to parse '{}'. Expected minutes after hours, e.g. '4h30m'",
-                        input
-                    )));
-                }
-            }
-            return Ok((Local::now() + duration).with_timezone(&Utc));
+/// What follows one of `prefixes` and a space at the start of `phrase`.
+fn after_prefix(
+    phrase: &str,
+    prefixes: impl IntoIterator<Item = &'static str>,
+) -> impl Iterator<Item = &str> {
+    prefixes.into_iter().filter_map(move |prefix| {
+        phrase
+            .strip_prefix(prefix)
+            .and_then(|rest| rest.strip_prefix(' '))
+    })
+}
+
+/// `today`, `tomorrow`, weekdays and `next week/month/year`.
+fn parse_day_phrase(phrase: &str, now: DateTime<Local>, words: &[&DateWords]) -> Option<NaiveDate> {
+    let today = now.date_naive();
+    if let Some(offset) = lookup(words, |w| w.days, phrase) {
+        return Some(today + Duration::days(offset));
+    }
+    match lookup(words, |w| w.next_periods, phrase) {
+        Some(Period::Week) => {
+            let days_to_monday = 7 - today.weekday().num_days_from_monday() as i64;
+            return Some(today + Duration::days(days_to_monday));
         }
+        Some(Period::Month) => return today.with_day(1)?.checked_add_months(Months::new(1)),
+        Some(Period::Year) => return NaiveDate::from_ymd_opt(today.year() + 1, 1, 1),
+        None => {}
     }
 
-    if let Some(mins_str) = cleaned.strip_suffix('m') {
-        if let Ok(m) = mins_str.parse::<i64>() {
-            return Ok((Local::now() + Duration::minutes(m)).with_timezone(&Utc));
+    // A bare or `this` weekday may be today, a `next` one is always ahead
+    let next = words.iter().flat_map(|w| w.next.iter().copied());
+    let this = words.iter().flat_map(|w| w.this.iter().copied());
+    let (weekday, skip_today) = after_prefix(phrase, next)
+        .find_map(|day| lookup(words, |w| w.weekdays, day).map(|wd| (wd, true)))
+        .or_else(|| {
+            after_prefix(phrase, this)
+                .chain([phrase])
+                .find_map(|day| lookup(words, |w| w.weekdays, day).map(|wd| (wd, false)))
+        })?;
+    let mut ahead = (weekday.num_days_from_monday() as i64
+        - today.weekday().num_days_from_monday() as i64)
+        .rem_euclid(7);
+    if ahead == 0 && skip_today {
+        ahead = 7;
+    }
+    Some(today + Duration::days(ahead))
+}
+
+/// `tonight`, `this morning`, `in the evening` and `tomorrow afternoon`.
+/// `in the …` means the next one to come, so tomorrow's once today's has
+/// started.
+fn parse_day_part_phrase(
+    phrase: &str,
+    now: DateTime<Local>,
+    words: &[&DateWords],
+) -> Option<NaiveDateTime> {
+    let (day, hour) = words
+        .iter()
+        .flat_map(|w| w.day_part_phrases)
+        .find(|(p, _, _)| *p == phrase)
+        .map(|(_, day, hour)| (*day, *hour))
+        .or_else(|| {
+            let (day, part) = phrase.rsplit_once(' ')?;
+            Some((
+                lookup(words, |w| w.day_part_days, day)?,
+                lookup(words, |w| w.day_parts, part)?,
+            ))
+        })?;
+
+    let today = now.date_naive();
+    let at_today = today.and_time(NaiveTime::from_hms_opt(hour, 0, 0)?);
+    match day {
+        DayPartDay::Today => Some(at_today),
+        DayPartDay::Upcoming if at_today > now.naive_local() => Some(at_today),
+        DayPartDay::Tomorrow | DayPartDay::Upcoming => Some(at_today + Duration::days(1)),
+    }
+}
+
+/// `YYYY-MM-DD`, at local midnight, or with a local `HH:MM[:SS]`.
+fn parse_absolute_date(
+    phrase: &str,
+    input: &str,
+) -> Result<Option<(DateTime<Utc>, DuePrecision)>, TaskError> {
+    if let Ok(date) = NaiveDate::parse_from_str(phrase, "%Y-%m-%d") {
+        let due = local_to_utc(end_of_day(date), input)?;
+        return Ok(Some((due, DuePrecision::Day)));
+    }
+    for fmt in ["%Y-%m-%d %H:%M", "%Y-%m-%d %H:%M:%S"] {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(phrase, fmt) {
+            return Ok(Some((local_to_utc(naive, input)?, DuePrecision::Time)));
         }
     }
+    Ok(None)
+}
+
+/// Amounts such as `3d`, `1.5h`, `2h 30m`, `5bd` or `in 2 weeks`. `Ok(None)`
+/// when `phrase` doesn't start like an amount.
+fn parse_relative_date(
+    phrase: &str,
+    input: &str,
+    now: DateTime<Local>,
+    words: &[&DateWords],
+) -> Result<Option<(DateTime<Utc>, DuePrecision)>, TaskError> {
+    let ahead = words.iter().flat_map(|w| w.ahead.iter().copied());
+    let rest = after_prefix(phrase, ahead).next().unwrap_or(phrase);
+    let one_words = || words.iter().flat_map(|w| w.one.iter().copied());
+    let starts_like_amount = rest.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '.')
+        || after_prefix(rest, one_words()).next().is_some();
+    if !starts_like_amount {
+        return Ok(None);
+    }
+
+    let invalid =
+        |hint: &str| TaskError::InvalidDate(format!("Unable to parse '{}'. {}", input, hint));
+    let out_of_range = || invalid("Date out of range");
+    let amounts = split_amounts(rest, words).ok_or_else(|| {
+        invalid("Expected amounts with units, e.g. '3d', '1.5h', '2w 3d' or 'in 5 days'")
+    })?;
+
+    let mut months = 0.0;
+    let mut days = 0;
+    let mut business_days = 0;
+    let mut exact = Duration::zero();
+    let mut timed = false;
+    let mut previous = None;
 
-    // Try date-only format first (defaults to 00:00:00)
-    if let Ok(date) = chrono::NaiveDate::parse_from_str(&trimmed, "%Y-%m-%d") {
-        let naive = date.and_hms_opt(0, 0, 0).unwrap();
-        match Local.from_local_datetime(&naive) {
-            chrono::LocalResult::Single(local_dt) => {
-                return Ok(local_dt.with_timezone(&Utc));
+    for (amount, unit) in amounts {
+        let amount: f64 = match amount {
+            one if one_words().any(|w| w == one) => 1.0,
+            n => n.parse().map_err(|_| invalid("Expected a number"))?,
+        };
+        let Some(unit) = unit else {
+            return Err(match previous {
+                Some(DateUnit::Hours) => invalid("Expected minutes after hours, e.g. '4h30m'"),
+                _ => invalid("Expected a unit after the number, e.g. '3d' or '2 weeks'"),
+            });
+        };
+        let unit = lookup(words, |w| w.units, unit)
+            .ok_or_else(|| invalid("Units are m, h, d, w, mon, q, y and bd (business days)"))?;
+        let whole = amount.fract() == 0.0;
+        timed |= matches!(unit, DateUnit::Minutes | DateUnit::Hours)
+            || (!whole && matches!(unit, DateUnit::Days | DateUnit::Weeks));
+
+        let in_days = |amount: f64| whole_number(amount).and_then(|n| n.checked_add(days));
+        match unit {
+            DateUnit::Minutes => {
+                exact = add_seconds(exact, amount * 60.0).ok_or_else(out_of_range)?
+            }
+            DateUnit::Hours => {
+                exact = add_seconds(exact, amount * 3600.0).ok_or_else(out_of_range)?
             }
-            chrono::LocalResult::Ambiguous(_earlier, later) => {
-                return Ok(later.with_timezone(&Utc));
+            DateUnit::Days if whole => days = in_days(amount).ok_or_else(out_of_range)?,
+            DateUnit::Days => {
+                exact = add_seconds(exact, amount * 86_400.0).ok_or_else(out_of_range)?
             }
-            chrono::LocalResult::None => {
-                return Err(TaskError::InvalidDate(format!(
-                    "Invalid local time '{}' (likely during DST transition)",
-                    input
-                )));
+            DateUnit::Weeks if whole => days = in_days(amount * 7.0).ok_or_else(out_of_range)?,
+            DateUnit::Weeks => {
+                exact = add_seconds(exact, amount * 7.0 * 86_400.0).ok_or_else(out_of_range)?
+            }
+            DateUnit::Months => months += amount,
+            DateUnit::Quarters => months += amount * 3.0,
+            DateUnit::Years => months += amount * 12.0,
+            DateUnit::BusinessDays if whole => {
+                business_days = whole_number(amount)
+                    .and_then(|n| n.checked_add(business_days))
+                    .ok_or_else(out_of_range)?
+            }
+            DateUnit::BusinessDays => {
+                return Err(invalid("Business days must be whole, e.g. '5bd'"));
             }
         }
+        previous = Some(unit);
     }
 
-    // Try date-time formats
-    for fmt in ["%Y-%m-%d %H:%M", "%Y-%m-%d %H:%M:%S"] {
-        if let Ok(naive) = NaiveDateTime::parse_from_str(&trimmed, fmt) {
-            match Local.from_local_datetime(&naive) {
-                chrono::LocalResult::Single(local_dt) => {
-                    return Ok(local_dt.with_timezone(&Utc));
-                }
-                chrono::LocalResult::Ambiguous(_earlier, later) => {
-                    // During DST "fall back":
-                    // prefer the later (standard time) interpretation
-                    return Ok(later.with_timezone(&Utc));
-                }
-                chrono::LocalResult::None => {
-                    return Err(TaskError::InvalidDate(format!(
-                        "Invalid local time '{}' (likely during DST transition)",
-                        input
-                    )));
-                }
+    // Calendar amounts move along local dates, so a day stays a day across
+    // DST changes. Whole months follow the calendar, a fraction counts 30 days.
+    let whole_months = months.trunc() as i32;
+    days = days
+        .checked_add((months.fract() * 30.0).round() as i64)
+        .ok_or_else(out_of_range)?;
+
+    let mut date = now.naive_local();
+    date = if whole_months >= 0 {
+        date.checked_add_months(Months::new(whole_months as u32))
+    } else {
+        date.checked_sub_months(Months::new(whole_months.unsigned_abs()))
+    }
+    .and_then(|date| date.checked_add_signed(Duration::try_days(days)?))
+    .and_then(|date| add_business_days(date, business_days))
+    .ok_or_else(out_of_range)?;
+
+    if !timed {
+        let due = local_to_utc(end_of_day(date.date()), input)?;
+        return Ok(Some((due, DuePrecision::Day)));
+    }
+    let start = if date == now.naive_local() {
+        now.with_timezone(&Utc)
+    } else {
+        local_to_utc(date, input)?
+    };
+    let due = start.checked_add_signed(exact).ok_or_else(out_of_range)?;
+    Ok(Some((due, DuePrecision::Time)))
+}
+
+/// Split `2h 30m`, `1.5 hours` or `5 business days` into `(amount, unit)`
+/// pairs; the unit is `None` when the phrase ends in a bare number.
+fn split_amounts<'a>(
+    phrase: &'a str,
+    words: &[&DateWords],
+) -> Option<Vec<(&'a str, Option<&'a str>)>> {
+    let mut pairs = Vec::new();
+    let mut rest = phrase.trim_start();
+
+    while !rest.is_empty() {
+        let one = words
+            .iter()
+            .flat_map(|w| w.one.iter())
+            .find(|one| after_prefix(rest, [**one]).next().is_some());
+        let number_len = match one {
+            Some(one) => one.len(),
+            None => rest
+                .char_indices()
+                .take_while(|&(idx, c)| c.is_ascii_digit() || c == '.' || (idx == 0 && c == '-'))
+                .count(),
+        };
+        if number_len == 0 {
+            return None;
+        }
+        let (amount, after) = rest.split_at(number_len);
+        let after = after.trim_start();
+
+        let unit_len = word_len(after);
+        if unit_len == 0 {
+            pairs.push((amount, None));
+            break;
+        }
+
+        let mut unit = &after[..unit_len];
+        let mut next = &after[unit_len..];
+        // Units of two words, like "business days"
+        let tail = next.trim_start();
+        let second_len = word_len(tail);
+        if second_len > 0 {
+            let len = after.len() - tail.len() + second_len;
+            let two_words = format!("{} {}", unit, &tail[..second_len]);
+            if lookup(words, |w| w.units, &two_words).is_some() {
+                unit = &after[..len];
+                next = &after[len..];
             }
         }
+        pairs.push((amount, Some(unit)));
+        rest = next.trim_start();
+        let and = words.iter().flat_map(|w| w.and.iter().copied());
+        rest = after_prefix(rest, and).next().unwrap_or(rest);
     }
+    Some(pairs)
+}
 
-    Err(TaskError::InvalidDate(format!(
-        "Unable to parse '{}'. Use natural language like 'today', '2h 30m', or an absolute date 'YYYY-MM-DD [HH:MM[:SS]]'",
-        input
-    )))
+fn word_len(s: &str) -> usize {
+    s.find(|c: char| !c.is_alphabetic()).unwrap_or(s.len())
+}
+
+/// `amount` as an `i64`, if it fits.
+fn whole_number(amount: f64) -> Option<i64> {
+    let n = amount.round();
+    (n.abs() < i64::MAX as f64).then_some(n as i64)
+}
+
+/// `total` plus `secs` seconds, rounded, unless that leaves chrono's range.
+fn add_seconds(total: Duration, secs: f64) -> Option<Duration> {
+    total.checked_add(&Duration::try_seconds(whole_number(secs)?)?)
+}
+
+/// Move `count` weekdays forward (or back when negative), skipping weekends.
+/// `None` when that leaves the supported range of dates.
+pub fn add_business_days(from: NaiveDateTime, count: i64) -> Option<NaiveDateTime> {
+    if count == 0 {
+        return Some(from);
+    }
+    let weekday = from.weekday().num_days_from_monday() as i64;
+    let left = count.unsigned_abs();
+    let whole_weeks = ((left / 5) as i64).checked_mul(7)?;
+    let rest = (left % 5) as i64;
+
+    // Whole weeks of five weekdays, then the rest, plus the weekend if the
+    // rest runs into it. A weekend start counts from its Friday going
+    // forward and from its Monday going back.
+    let days = if count > 0 {
+        let to_friday = (weekday - 4).max(0);
+        let weekend = if weekday - to_friday + rest > 4 { 2 } else { 0 };
+        whole_weeks.checked_add(rest + weekend - to_friday)?
+    } else {
+        let to_monday = if weekday > 4 { 7 - weekday } else { 0 };
+        let weekend = if (weekday + to_monday) % 7 < rest {
+            2
+        } else {
+            0
+        };
+        to_monday
+            .checked_sub(whole_weeks)?
+            .checked_sub(rest + weekend)?
+    };
+    from.checked_add_signed(Duration::try_days(days)?)
+}
+
+fn end_of_day(date: NaiveDate) -> NaiveDateTime {
+    date.and_hms_opt(23, 59, 59).unwrap()
+}
+
+/// The last second of `date` in local time, where whole-day due dates
+/// fall.
+pub fn end_of_local_day(date: NaiveDate) -> Result<DateTime<Utc>, TaskError> {
+    local_to_utc(end_of_day(date), &date.to_string())
+}
+
+fn local_to_utc(naive: NaiveDateTime, input: &str) -> Result<DateTime<Utc>, TaskError> {
+    match Local.from_local_datetime(&naive) {
+        chrono::LocalResult::Single(local_dt) => Ok(local_dt.with_timezone(&Utc)),
+        // During DST "fall back": prefer the later (standard time)
+        // interpretation
+        chrono::LocalResult::Ambiguous(_earlier, later) => Ok(later.with_timezone(&Utc)),
+        chrono::LocalResult::None => Err(TaskError::InvalidDate(format!(
+            "Invalid local time '{}' (likely during DST transition)",
+            input
+        ))),
+    }
+}
+
+/// Parse a span of time such as `30d`, `2w`, `12h`, `45m` or a combination
+/// like `1w 2d`, using the same unit letters as relative `--due` dates.
+pub fn parse_duration(input: &str) -> Result<Duration, TaskError> {
+    let cleaned = input.trim().to_lowercase().replace(' ', "");
+    let invalid = || {
+        TaskError::InvalidDate(format!(
+            "Unable to parse duration '{}'. Use a number and unit, e.g. '30d', '2w', '12h' or '45m'",
+            input
+        ))
+    };
+
+    if cleaned.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut total = Duration::zero();
+    let mut digits = String::new();
+
+    for c in cleaned.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+
+        let n: i64 = digits.parse().map_err(|_| invalid())?;
+        let amount = match c {
+            'm' => Duration::try_minutes(n),
+            'h' => Duration::try_hours(n),
+            'd' => Duration::try_days(n),
+            'w' => Duration::try_weeks(n),
+            _ => return Err(invalid()),
+        };
+        total = amount
+            .and_then(|amount| total.checked_add(&amount))
+            .ok_or_else(|| TaskError::InvalidDate(format!("Duration '{}' is too long", input)))?;
+        digits.clear();
+    }
+
+    if !digits.is_empty() {
+        return Err(invalid());
+    }
+
+    Ok(total)
 }
 
 pub fn status_filter_from_params(status: Option<Status>, show_all: bool) -> StatusFilter {
@@ -144,77 +532,205 @@ pub fn is_number(s: &str) -> bool {
     !s.is_empty() && s.chars().all(|c| c.is_ascii_digit())
 }
 
-/// Try to interpret `reference` as a 1-based list index first;  
-/// if that fails, fall back to a (possibly-shortened) task-ID.
-pub fn resolve_task(
+/// Try to interpret `reference` as a 1-based index into the last listing
+/// first; if that fails, fall back to a (possibly-shortened) task-ID.
+pub fn resolve_task(manager: &TaskManager, reference: &str) -> Result<Option<Task>, TaskError> {
+    let (mut found, _) = resolve_tasks(manager, &[reference.to_string()], None)?;
+    Ok(found.pop())
+}
+
+/// Resolve several references at once. Indices point into the last list
+/// `handle_list` printed, or into a fresh list of `status` tasks when that
+/// listing showed something else, and are all looked up before anything is
+/// changed. Returns the tasks found (without duplicates, in reference
+/// order) and the references that matched nothing.
+pub fn resolve_tasks(
     manager: &TaskManager,
-    reference: &str,
-    show_all: bool,
-) -> Result<Option<Task>, TaskError> {
-    if is_number(reference) {
-        let idx: usize = reference.parse().unwrap_or(0);
-        let filter = if show_all {
-            StatusFilter::All
-        } else {
-            StatusFilter::PendingOnly
-        };
-        if let Some(t) = manager
-            .list_tasks(filter)?
-            .into_iter()
-            .nth(idx.saturating_sub(1))
-        {
-            return Ok(Some(t));
+    references: &[String],
+    status: Option<Status>,
+) -> Result<(Vec<Task>, Vec<String>), TaskError> {
+    let (listed, saved) = indexed_listing(manager, status)?;
+    resolve_in_listing(manager, references, &listed, saved)
+}
+
+/// [`resolve_tasks`] against `listed`, which is the saved listing when
+/// `saved` is set.
+fn resolve_in_listing(
+    manager: &TaskManager,
+    references: &[String],
+    listed: &ListSnapshot,
+    saved: bool,
+) -> Result<(Vec<Task>, Vec<String>), TaskError> {
+    let mut found: Vec<Task> = Vec::new();
+    let mut missing = Vec::new();
+    let mut checked_stale = !saved;
+
+    for reference in references {
+        // Left over from a range past the end of the list; it names no
+        // index, and must not be taken for an id prefix either
+        if is_index_range(reference) {
+            missing.push(reference.clone());
+            continue;
+        }
+        let by_index = is_number(reference)
+            .then(|| reference.parse::<usize>().ok())
+            .flatten()
+            .and_then(|idx| listed.id_at(idx));
+
+        if by_index.is_some() && !checked_stale {
+            checked_stale = true;
+            if listed.is_stale(manager)? {
+                eprintln!(
+                    "{}",
+                    "warning: tasks changed since they were last listed; \
+                     indices refer to that listing"
+                        .bright_yellow()
+                );
+            }
+        }
+
+        let task = manager.find_task_by_id(by_index.unwrap_or(reference))?;
+
+        match task {
+            Some(task) if found.iter().any(|t| t.id == task.id) => {}
+            Some(task) => found.push(task),
+            None => missing.push(reference.clone()),
         }
     }
-    manager.find_task_by_id(reference)
-}
 
-fn get_tarea_dir() -> Result<PathBuf, TaskError> {
-    let home = env::var("HOME").map_err(|_| {
-        TaskError::Io(io::Error::new(
-            io::ErrorKind::NotFound,
-            "HOME environment variable not found",
-        ))
-    })?;
+    Ok((found, missing))
+}
 
-    let tarea_dir = PathBuf::from(home).join(".tarea");
-    if !tarea_dir.exists() {
-        fs::create_dir_all(&tarea_dir)?;
+/// The list indices given with `status` refer to: the last one printed if
+/// it showed that status, else a fresh one. Also tells whether it was the
+/// saved one.
+fn indexed_listing(
+    manager: &TaskManager,
+    status: Option<Status>,
+) -> Result<(ListSnapshot, bool), TaskError> {
+    if let Some(snapshot) =
+        ListSnapshot::load().filter(|s| status.is_none() || s.status() == status)
+    {
+        return Ok((snapshot, true));
     }
-    Ok(tarea_dir)
+    let tasks = manager.list_tasks(status_filter_from_params(status.clone(), false))?;
+    let listed = ListSnapshot::new(
+        false,
+        status.as_ref(),
+        &TagFilter::default(),
+        &DueFilter::default(),
+        &TaskSort::default(),
+        &tasks,
+    );
+    Ok((listed, false))
 }
 
-fn last_list_all_path() -> Result<PathBuf, TaskError> {
-    Ok(get_tarea_dir()?.join("last_list_all"))
+/// How many rows the list that indices given with `status` refer to has.
+pub fn indexed_len(manager: &TaskManager, status: Option<Status>) -> Result<usize, TaskError> {
+    Ok(indexed_listing(manager, status)?.0.ids.len())
 }
 
-pub fn save_last_list_all(all: bool) -> Result<(), TaskError> {
-    let path = last_list_all_path()?;
-    if all {
-        fs::write(path, b"1")?;
-    } else {
-        let _ = fs::remove_file(path);
+/// Expand bulk task references: comma separated lists and numeric index
+/// ranges such as `5-8` become individual references. Ranges stop at
+/// `max_index`, the length of the list they index into.
+pub fn expand_task_refs(raw: &[String], max_index: usize) -> Result<Vec<String>, TaskError> {
+    let mut refs = Vec::new();
+
+    for part in raw.iter().flat_map(|r| r.split([',', ' ', '\t', '\n'])) {
+        let part = part.trim();
+        if part.is_empty() {
+            continue;
+        }
+
+        match part.split_once('-') {
+            Some((start, end)) if is_index_range(part) => {
+                let invalid = || TaskError::InvalidInput(format!("Invalid index range '{part}'"));
+                let start: usize = start.parse().map_err(|_| invalid())?;
+                let end: usize = end.parse().map_err(|_| invalid())?;
+                if start == 0 || start > end {
+                    return Err(invalid());
+                }
+                // A range wholly past the end stays as it is, so it is
+                // reported as not found.
+                if start > max_index {
+                    refs.push(part.to_string());
+                } else {
+                    refs.extend((start..=end.min(max_index)).map(|i| i.to_string()));
+                }
+            }
+            _ => refs.push(part.to_string()),
+        }
     }
-    Ok(())
+
+    Ok(refs)
 }
 
-pub fn was_last_list_all() -> bool {
-    last_list_all_path().ok().is_some_and(|p| p.exists())
+/// Whether `s` is an index range such as `5-8`.
+fn is_index_range(s: &str) -> bool {
+    s.split_once('-')
+        .is_some_and(|(start, end)| is_number(start) && is_number(end))
 }
 
-pub fn delete_database() -> Result<(), TaskError> {
-    use std::io::Write;
+/// Replace a `-` reference with the whitespace separated references read
+/// from stdin. Returns whether stdin was consumed.
+pub fn read_stdin_refs(raw: &mut Vec<String>) -> Result<bool, TaskError> {
+    if !raw.iter().any(|r| r == "-") {
+        return Ok(false);
+    }
 
-    print!("Are you sure you want to delete the database? This action cannot be undone. (y/N): ");
+    let input = io::read_to_string(io::stdin())?;
+    raw.retain(|r| r != "-");
+    raw.extend(input.split_whitespace().map(str::to_string));
+    Ok(true)
+}
+
+/// Ask a yes/no question. When stdin already carried task references the
+/// answer is read from the terminal instead.
+pub fn confirm(prompt: &str, from_tty: bool) -> Result<bool, TaskError> {
+    use std::io::{BufRead, Write};
+
+    print!("{prompt} (y/N): ");
     io::stdout().flush()?;
 
     let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
+    if from_tty {
+        let tty = fs::File::open("/dev/tty").map_err(|_| {
+            TaskError::InvalidInput(
+                "cannot ask for confirmation while reading tasks from stdin, use --yes".to_string(),
+            )
+        })?;
+        io::BufReader::new(tty).read_line(&mut input)?;
+    } else {
+        io::stdin().read_line(&mut input)?;
+    }
 
-    let input = input.trim().to_lowercase();
-    if matches!(input.as_str(), "y" | "yes") {
-        let db_path = crate::database::get_db_path()?;
-        match fs::remove_file(&db_path) {
+    Ok(matches!(input.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+pub fn get_tarea_dir() -> Result<PathBuf, TaskError> {
+    let home = env::var("HOME").map_err(|_| {
+        TaskError::Io(io::Error::new(
+            io::ErrorKind::NotFound,
+            "HOME environment variable not found",
+        ))
+    })?;
+
+    let tarea_dir = PathBuf::from(home).join(".tarea");
+    if !tarea_dir.exists() {
+        fs::create_dir_all(&tarea_dir)?;
+    }
+    Ok(tarea_dir)
+}
+
+pub fn delete_database(db_path: &Path, assume_yes: bool) -> Result<(), TaskError> {
+    let confirmed = assume_yes
+        || confirm(
+            "Are you sure you want to delete the database? This action cannot be undone.",
+            false,
+        )?;
+
+    if confirmed {
+        match fs::remove_file(db_path) {
             Ok(_) => println!("{}", "Database deleted successfully".bright_green()),
             Err(e) if e.kind() == io::ErrorKind::NotFound => {
                 println!("{}", "Database file not found".bright_yellow())
@@ -262,6 +778,64 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_validate_tag_name() {
+        assert!(validate_tag_name("infra").is_ok());
+        assert!(validate_tag_name("").is_err());
+        assert!(validate_tag_name("two words").is_err());
+        assert!(validate_tag_name("a,b").is_err());
+    }
+
+    #[test]
+    fn test_split_name_and_tags() {
+        assert_eq!(
+            split_name_and_tags("Fix CI +infra +urgent"),
+            (
+                "Fix CI".to_string(),
+                vec!["infra".to_string(), "urgent".to_string()]
+            )
+        );
+        assert_eq!(
+            split_name_and_tags("Ship  +release v2 +"),
+            ("Ship v2 +".to_string(), vec!["release".to_string()])
+        );
+        assert_eq!(
+            split_name_and_tags("No  tags here"),
+            ("No  tags here".to_string(), vec![])
+        );
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        let cases = [
+            ("30d", Duration::days(30)),
+            ("2w", Duration::weeks(2)),
+            ("12h", Duration::hours(12)),
+            ("45m", Duration::minutes(45)),
+            ("1w 2d", Duration::days(9)),
+            (" 1H30M ", Duration::minutes(90)),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(parse_duration(input).unwrap(), expected, "input: {input}");
+        }
+    }
+
+    #[test]
+    fn test_parse_duration_invalid() {
+        for input in [
+            "",
+            "30",
+            "d",
+            "3x",
+            "1d2",
+            "-3d",
+            "999999999999999w",
+            "9000000000000000d",
+        ] {
+            assert!(parse_duration(input).is_err(), "input: {input}");
+        }
+    }
+
     #[test]
     fn test_parse_due_date_today() {
         let today = Local::now().date_naive();
@@ -334,7 +908,7 @@ mod tests {
     fn test_parse_due_date_absolute_date_only() {
         let result = parse_due_date("2023-12-25").unwrap();
         let date = chrono::NaiveDate::parse_from_str("2023-12-25", "%Y-%m-%d").unwrap();
-        let expected_naive = date.and_hms_opt(0, 0, 0).unwrap();
+        let expected_naive = date.and_hms_opt(23, 59, 59).unwrap();
         let expected = Local
             .from_local_datetime(&expected_naive)
             .single()
@@ -442,4 +1016,303 @@ mod tests {
         let diff = (result - expected).abs();
         assert!(diff < Duration::seconds(1));
     }
+
+    /// Wednesday 2025-08-06, 10:30 local time.
+    fn wednesday_morning() -> DateTime<Local> {
+        Local.with_ymd_and_hms(2025, 8, 6, 10, 30, 0).unwrap()
+    }
+
+    fn local(y: i32, m: u32, d: u32, h: u32, min: u32, s: u32) -> DateTime<Utc> {
+        Local
+            .with_ymd_and_hms(y, m, d, h, min, s)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn assert_due_dates(cases: &[(&str, DateTime<Utc>)]) {
+        assert_due_dates_in(Locale::En, cases);
+    }
+
+    fn assert_due_dates_in(locale: Locale, cases: &[(&str, DateTime<Utc>)]) {
+        for (input, expected) in cases {
+            let result = parse_due_date_from(input, wednesday_morning(), locale);
+            assert_eq!(
+                result.ok().map(|(due, _)| due),
+                Some(*expected),
+                "input: {input}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_due_date_precision() {
+        for (input, precision) in [
+            ("today", DuePrecision::Day),
+            ("friday", DuePrecision::Day),
+            ("in 3 days", DuePrecision::Day),
+            ("2025-08-12", DuePrecision::Day),
+            ("2025-08-12 00:00", DuePrecision::Time),
+            ("2025-08-12 14:30", DuePrecision::Time),
+            ("2h", DuePrecision::Time),
+            ("1.5d", DuePrecision::Time),
+            ("tomorrow morning", DuePrecision::Time),
+        ] {
+            let (_, parsed) = parse_due_date_from(input, wednesday_morning(), Locale::En).unwrap();
+            assert_eq!(parsed, precision, "input: {input}");
+        }
+    }
+
+    #[test]
+    fn test_parse_due_date_day_units() {
+        assert_due_dates(&[
+            ("3d", local(2025, 8, 9, 23, 59, 59)),
+            ("1d", local(2025, 8, 7, 23, 59, 59)),
+            ("2w", local(2025, 8, 20, 23, 59, 59)),
+            ("1w 2d", local(2025, 8, 15, 23, 59, 59)),
+            ("1mon", local(2025, 9, 6, 23, 59, 59)),
+            ("2 months", local(2025, 10, 6, 23, 59, 59)),
+            ("1q", local(2025, 11, 6, 23, 59, 59)),
+            ("1y", local(2026, 8, 6, 23, 59, 59)),
+            ("-3d", local(2025, 8, 3, 23, 59, 59)),
+        ]);
+    }
+
+    #[test]
+    fn test_parse_due_date_decimals() {
+        assert_due_dates(&[
+            ("1.5h", local(2025, 8, 6, 12, 0, 0)),
+            ("0.5m", local(2025, 8, 6, 10, 30, 30)),
+            ("1.5d", local(2025, 8, 7, 22, 30, 0)),
+            ("1.5mon", local(2025, 9, 21, 23, 59, 59)),
+            ("1.5y", local(2027, 2, 6, 23, 59, 59)),
+            ("1d 2.5h", local(2025, 8, 7, 13, 0, 0)),
+        ]);
+    }
+
+    #[test]
+    fn test_parse_due_date_in_phrases() {
+        assert_due_dates(&[
+            ("in 5 days", local(2025, 8, 11, 23, 59, 59)),
+            ("in 2 weeks", local(2025, 8, 20, 23, 59, 59)),
+            ("in a week", local(2025, 8, 13, 23, 59, 59)),
+            ("in an hour", local(2025, 8, 6, 11, 30, 0)),
+            ("in 1 hour and 15 minutes", local(2025, 8, 6, 11, 45, 0)),
+            ("In  3 Months", local(2025, 11, 6, 23, 59, 59)),
+        ]);
+    }
+
+    #[test]
+    fn test_parse_due_date_weekdays_and_next() {
+        assert_due_dates(&[
+            ("friday", local(2025, 8, 8, 23, 59, 59)),
+            ("fri", local(2025, 8, 8, 23, 59, 59)),
+            ("next friday", local(2025, 8, 8, 23, 59, 59)),
+            ("monday", local(2025, 8, 11, 23, 59, 59)),
+            ("wednesday", local(2025, 8, 6, 23, 59, 59)),
+            ("this wed", local(2025, 8, 6, 23, 59, 59)),
+            ("next wednesday", local(2025, 8, 13, 23, 59, 59)),
+            ("next week", local(2025, 8, 11, 23, 59, 59)),
+            ("next month", local(2025, 9, 1, 23, 59, 59)),
+            ("next year", local(2026, 1, 1, 23, 59, 59)),
+        ]);
+    }
+
+    #[test]
+    fn test_parse_due_date_times_of_day() {
+        assert_due_dates(&[
+            ("tonight", local(2025, 8, 6, 21, 0, 0)),
+            ("this morning", local(2025, 8, 6, 9, 0, 0)),
+            ("this afternoon", local(2025, 8, 6, 15, 0, 0)),
+            ("in the evening", local(2025, 8, 6, 19, 0, 0)),
+            // This morning has already started, so the next one is tomorrow's
+            ("in the morning", local(2025, 8, 7, 9, 0, 0)),
+            ("tomorrow night", local(2025, 8, 7, 21, 0, 0)),
+        ]);
+    }
+
+    #[test]
+    fn test_parse_due_date_business_days() {
+        assert_due_dates(&[
+            ("1bd", local(2025, 8, 7, 23, 59, 59)),
+            ("3bd", local(2025, 8, 11, 23, 59, 59)),
+            ("5bd", local(2025, 8, 13, 23, 59, 59)),
+            ("in 3 business days", local(2025, 8, 11, 23, 59, 59)),
+            ("-3bd", local(2025, 8, 1, 23, 59, 59)),
+        ]);
+    }
+
+    #[test]
+    fn test_add_business_days_matches_walking_day_by_day() {
+        let walk = |from: NaiveDateTime, count: i64| {
+            let mut when = from;
+            for _ in 0..count.abs() {
+                when += Duration::days(count.signum());
+                while when.weekday().num_days_from_monday() > 4 {
+                    when += Duration::days(count.signum());
+                }
+            }
+            when
+        };
+        // Monday 2025-08-04 to Sunday 2025-08-10
+        for day in 4..=10 {
+            let from = NaiveDate::from_ymd_opt(2025, 8, day)
+                .and_then(|date| date.and_hms_opt(9, 0, 0))
+                .unwrap();
+            for count in -12..=12 {
+                assert_eq!(
+                    add_business_days(from, count),
+                    Some(walk(from, count)),
+                    "{from} {count}"
+                );
+            }
+        }
+        assert_eq!(add_business_days(NaiveDateTime::MAX, 1), None);
+        assert_eq!(add_business_days(NaiveDateTime::MIN, i64::MIN), None);
+    }
+
+    #[test]
+    fn test_parse_due_date_out_of_range() {
+        for input in [
+            "99999999999d",
+            "999999999w",
+            "2000000000bd",
+            "-2000000000bd",
+            "99999999999999999999h",
+            "1e300m",
+            "300000 years",
+        ] {
+            let result = parse_due_date_from(input, wednesday_morning(), Locale::En);
+            assert!(
+                matches!(result, Err(TaskError::InvalidDate(_))),
+                "input: {input}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_due_date_spanish() {
+        assert_due_dates_in(
+            Locale::Es,
+            &[
+                ("hoy", local(2025, 8, 6, 23, 59, 59)),
+                ("mañana", local(2025, 8, 7, 23, 59, 59)),
+                ("Pasado Mañana", local(2025, 8, 8, 23, 59, 59)),
+                ("el viernes", local(2025, 8, 8, 23, 59, 59)),
+                ("miércoles", local(2025, 8, 6, 23, 59, 59)),
+                ("el próximo miércoles", local(2025, 8, 13, 23, 59, 59)),
+                ("próxima semana", local(2025, 8, 11, 23, 59, 59)),
+                ("el mes que viene", local(2025, 9, 1, 23, 59, 59)),
+                ("en 3 días", local(2025, 8, 9, 23, 59, 59)),
+                (
+                    "dentro de una hora y 15 minutos",
+                    local(2025, 8, 6, 11, 45, 0),
+                ),
+                ("en 2 días hábiles", local(2025, 8, 8, 23, 59, 59)),
+                ("esta noche", local(2025, 8, 6, 21, 0, 0)),
+                ("mañana por la tarde", local(2025, 8, 7, 15, 0, 0)),
+                ("por la mañana", local(2025, 8, 7, 9, 0, 0)),
+                // English keeps working
+                ("next friday", local(2025, 8, 8, 23, 59, 59)),
+                ("in 5 days", local(2025, 8, 11, 23, 59, 59)),
+            ],
+        );
+
+        // Spanish words are only read with a Spanish locale
+        assert!(parse_due_date_from("mañana", wednesday_morning(), Locale::En).is_err());
+    }
+
+    #[test]
+    fn test_parse_due_date_invalid_phrases() {
+        for input in [
+            "next",
+            "next fortnight",
+            "in",
+            "in days",
+            "3",
+            "3x",
+            "1.5bd",
+            "1.2.3d",
+            "friday morning please",
+            "this",
+        ] {
+            let result = parse_due_date_from(input, wednesday_morning(), Locale::En);
+            assert!(result.is_err(), "input: {input}");
+        }
+    }
+
+    fn refs(input: &[&str]) -> Vec<String> {
+        input.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_expand_task_refs_lists_and_ranges() {
+        assert_eq!(
+            expand_task_refs(&refs(&["1,3,5-8", "a1b2"]), 10).unwrap(),
+            refs(&["1", "3", "5", "6", "7", "8", "a1b2"])
+        );
+        assert_eq!(
+            expand_task_refs(&refs(&["a1b2 c3d4\n"]), 10).unwrap(),
+            refs(&["a1b2", "c3d4"])
+        );
+    }
+
+    #[test]
+    fn test_expand_task_refs_stops_ranges_at_the_list_end() {
+        assert_eq!(
+            expand_task_refs(&refs(&["2-99999999"]), 4).unwrap(),
+            refs(&["2", "3", "4"])
+        );
+        assert_eq!(
+            expand_task_refs(&refs(&["7-9"]), 4).unwrap(),
+            refs(&["7-9"])
+        );
+    }
+
+    #[test]
+    fn test_range_past_the_list_end_never_matches_an_id() {
+        let manager =
+            TaskManager::from_connection(rusqlite::Connection::open_in_memory().unwrap()).unwrap();
+        let mut tasks = Vec::new();
+        for (name, id) in [
+            ("a", "1a2b3c4d-0000-4000-8000-000000000001"),
+            ("b", "2a2b3c4d-0000-4000-8000-000000000002"),
+            ("c", "3a2b3c4d-0000-4000-8000-000000000003"),
+            ("d", "7a2b3c4d-0000-4000-8000-000000000004"),
+        ] {
+            let mut task = Task::new(name.to_string(), None, None).unwrap();
+            task.id = id.to_string();
+            manager.add_task(task.clone()).unwrap();
+            tasks.push(task);
+        }
+        let listed = ListSnapshot::new(
+            false,
+            None,
+            &TagFilter::default(),
+            &DueFilter::default(),
+            &TaskSort::default(),
+            &tasks,
+        );
+
+        let references = expand_task_refs(&refs(&["7-9"]), tasks.len()).unwrap();
+        let (found, missing) = resolve_in_listing(&manager, &references, &listed, false).unwrap();
+        assert!(found.is_empty());
+        assert_eq!(missing, refs(&["7-9"]));
+
+        let references = expand_task_refs(&refs(&["3-9"]), tasks.len()).unwrap();
+        let (found, missing) = resolve_in_listing(&manager, &references, &listed, false).unwrap();
+        let names: Vec<_> = found.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["c", "d"]);
+        assert!(missing.is_empty());
+    }
+
+    #[test]
+    fn test_expand_task_refs_invalid_range() {
+        assert!(expand_task_refs(&refs(&["8-5"]), 10).is_err());
+        assert!(expand_task_refs(&refs(&["0-2"]), 10).is_err());
+        // Only purely numeric ranges are expanded
+        assert_eq!(
+            expand_task_refs(&refs(&["1a-2"]), 10).unwrap(),
+            refs(&["1a-2"])
+        );
+    }
 }